@@ -0,0 +1,249 @@
+use std::io::{self, Write};
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender, TrySendError};
+use std::time::{Duration, SystemTime};
+
+// Lines buffered between request tasks and the writer thread; beyond this entries are
+// dropped (and counted) rather than making a request wait on stdout
+pub const ACCESS_LOG_BUFFER: usize = 8192;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AccessLogFormat {
+    Off,
+    Json,
+    Common,
+    Combined,
+}
+
+impl AccessLogFormat {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "off" | "none" | "false" => Some(AccessLogFormat::Off),
+            "json" => Some(AccessLogFormat::Json),
+            "common" | "clf" => Some(AccessLogFormat::Common),
+            "combined" => Some(AccessLogFormat::Combined),
+            _ => None,
+        }
+    }
+}
+
+// One served request - borrowed from the connection's buffers, formatted immediately
+pub struct AccessLogEntry<'a> {
+    pub client_ip: IpAddr,
+    pub timestamp: SystemTime,
+    pub method: &'a str,
+    pub path: &'a str,
+    pub version: &'a str,
+    pub status: u16,
+    pub bytes: u64,
+    pub duration: Duration,
+    pub user_agent: Option<&'a [u8]>,
+    pub referer: Option<&'a [u8]>,
+}
+
+impl AccessLogEntry<'_> {
+    // Render a single line (without the trailing newline). Common and Combined follow the
+    // Apache formats exactly so existing parsers keep working; duration is JSON-only.
+    pub fn format(&self, format: AccessLogFormat) -> String {
+        let mut line = String::with_capacity(256);
+        match format {
+            AccessLogFormat::Off => {}
+            AccessLogFormat::Json => {
+                line.push_str(&format!(
+                    r#"{{"time":"{}","client_ip":"{}","method":"#,
+                    rfc3339(self.timestamp),
+                    self.client_ip
+                ));
+                push_json_string(&mut line, self.method.as_bytes());
+                line.push_str(r#","path":"#);
+                push_json_string(&mut line, self.path.as_bytes());
+                line.push_str(r#","protocol":"#);
+                push_json_string(&mut line, self.version.as_bytes());
+                line.push_str(&format!(
+                    r#","status":{},"bytes":{},"duration_us":{},"user_agent":"#,
+                    self.status,
+                    self.bytes,
+                    self.duration.as_micros()
+                ));
+                push_json_optional(&mut line, self.user_agent);
+                line.push_str(r#","referer":"#);
+                push_json_optional(&mut line, self.referer);
+                line.push('}');
+            }
+            AccessLogFormat::Common | AccessLogFormat::Combined => {
+                // host ident authuser [date] "request" status bytes
+                line.push_str(&format!("{} - - [{}] \"", self.client_ip, clf_date(self.timestamp)));
+                push_clf_escaped(&mut line, self.method.as_bytes());
+                line.push(' ');
+                push_clf_escaped(&mut line, self.path.as_bytes());
+                line.push(' ');
+                push_clf_escaped(&mut line, self.version.as_bytes());
+                line.push_str(&format!("\" {} {}", self.status, self.bytes));
+
+                if format == AccessLogFormat::Combined {
+                    line.push_str(" \"");
+                    push_clf_escaped(&mut line, self.referer.unwrap_or(b"-"));
+                    line.push_str("\" \"");
+                    push_clf_escaped(&mut line, self.user_agent.unwrap_or(b"-"));
+                    line.push('"');
+                }
+            }
+        }
+        line
+    }
+}
+
+// Non-blocking access logger: request tasks format a line and hand it to a dedicated
+// writer thread through a bounded channel. A full channel drops the line instead of
+// stalling the request; drops are reported on stderr.
+pub struct AccessLogger {
+    format: AccessLogFormat,
+    sample_every: u64,
+    counter: AtomicU64,
+    sender: SyncSender<String>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl AccessLogger {
+    pub fn spawn(format: AccessLogFormat, sample_every: u64) -> io::Result<Self> {
+        Self::spawn_with_writer(format, sample_every, ACCESS_LOG_BUFFER, io::stdout())
+    }
+
+    // Writer and capacity are injectable for tests
+    pub fn spawn_with_writer<W>(format: AccessLogFormat, sample_every: u64, capacity: usize, writer: W) -> io::Result<Self>
+    where
+        W: Write + Send + 'static,
+    {
+        let (sender, receiver) = sync_channel(capacity);
+        let dropped = Arc::new(AtomicU64::new(0));
+
+        let writer_dropped = Arc::clone(&dropped);
+        std::thread::Builder::new()
+            .name("kiss-access-log".into())
+            .spawn(move || write_lines(receiver, writer, &writer_dropped))?;
+
+        Ok(Self {
+            format,
+            sample_every: sample_every.max(1),
+            counter: AtomicU64::new(0),
+            sender,
+            dropped,
+        })
+    }
+
+    pub fn format(&self) -> AccessLogFormat {
+        self.format
+    }
+
+    // Decide up front whether this request is logged, so unsampled requests skip
+    // capturing the extra headers entirely
+    #[inline]
+    pub fn sample(&self) -> bool {
+        self.sample_every == 1 || self.counter.fetch_add(1, Ordering::Relaxed) % self.sample_every == 0
+    }
+
+    pub fn log(&self, entry: &AccessLogEntry<'_>) {
+        match self.sender.try_send(entry.format(self.format)) {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) => {
+                self.dropped.fetch_add(1, Ordering::Relaxed);
+            }
+            Err(TrySendError::Disconnected(_)) => {}
+        }
+    }
+
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+}
+
+// Writer thread: block for the next line, drain whatever else is queued, then flush once
+fn write_lines<W: Write>(receiver: Receiver<String>, writer: W, dropped: &AtomicU64) {
+    let mut writer = io::BufWriter::with_capacity(64 * 1024, writer);
+    let mut reported = 0;
+
+    while let Ok(line) = receiver.recv() {
+        let mut result = writeln!(writer, "{}", line);
+        while let (true, Ok(line)) = (result.is_ok(), receiver.try_recv()) {
+            result = writeln!(writer, "{}", line);
+        }
+        if let Err(e) = result.and_then(|_| writer.flush()) {
+            eprintln!("Warning: access log stopped: {}", e);
+            return;
+        }
+
+        let total = dropped.load(Ordering::Relaxed);
+        if total > reported {
+            eprintln!("Warning: access log buffer full, {} entries dropped", total - reported);
+            reported = total;
+        }
+    }
+}
+
+fn push_json_optional(out: &mut String, value: Option<&[u8]>) {
+    match value {
+        Some(value) => push_json_string(out, value),
+        None => out.push_str("null"),
+    }
+}
+
+fn push_json_string(out: &mut String, value: &[u8]) {
+    out.push('"');
+    for c in String::from_utf8_lossy(value).chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if (c as u32) < 0x20 || c == '\u{7f}' => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+// Quote-safe, printable-ASCII only - client-controlled values can't forge log lines
+fn push_clf_escaped(out: &mut String, value: &[u8]) {
+    for &byte in value {
+        match byte {
+            b'"' | b'\\' => {
+                out.push('\\');
+                out.push(byte as char);
+            }
+            0x20..=0x7e => out.push(byte as char),
+            _ => out.push_str(&format!("\\x{:02X}", byte)),
+        }
+    }
+}
+
+// `10/Oct/2000:13:55:36 +0000`
+fn clf_date(time: SystemTime) -> String {
+    const MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+    let (year, month, day, hour, minute, second) = civil_time(time);
+    format!("{:02}/{}/{}:{:02}:{:02}:{:02} +0000", day, MONTHS[month as usize - 1], year, hour, minute, second)
+}
+
+// `2000-10-10T13:55:36.123Z`
+fn rfc3339(time: SystemTime) -> String {
+    let (year, month, day, hour, minute, second) = civil_time(time);
+    let millis = time.duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default().subsec_millis();
+    format!("{}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z", year, month, day, hour, minute, second, millis)
+}
+
+// UTC calendar fields from a timestamp (days-to-civil, H. Hinnant)
+fn civil_time(time: SystemTime) -> (i64, u32, u32, u32, u32, u32) {
+    let secs = time.duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default().as_secs() as i64;
+    let (days, rem) = (secs.div_euclid(86400), secs.rem_euclid(86400));
+
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    (year, month, day, (rem / 3600) as u32, (rem % 3600 / 60) as u32, (rem % 60) as u32)
+}
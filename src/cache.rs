@@ -0,0 +1,626 @@
+use crate::digest::{sha256, to_hex};
+use crate::encoding::{AcceptEncoding, ContentEncoding};
+use crate::error_pages::ErrorPages;
+use crate::redirects::{Redirects, Resolution};
+use crate::uri::{normalize_path, parse_target};
+use rustc_hash::FxHashMap;
+use std::sync::atomic::{AtomicPtr, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+// Memory-optimized cache entry - structured for optimal cache line efficiency
+#[derive(Clone, Debug)]
+#[repr(C)]
+pub struct CacheEntry {
+    // Hot path data first (most frequently accessed in request handling)
+    pub complete_response: Arc<[u8]>,     // 8 bytes (pointer)
+    pub headers_only: Arc<[u8]>,          // 8 bytes (pointer)
+    pub not_modified_response: Arc<[u8]>, // 8 bytes (pointer)
+
+    // Frequently used for conditional requests
+    pub last_modified_timestamp: SystemTime, // 16 bytes (u64 + u32 typically)
+    pub etag: Arc<str>,                   // 8 bytes (pointer)
+
+    // Precompressed representations, None for the common no-sidecar case
+    pub variants: Option<Arc<[EncodedVariant]>>, // 16 bytes (fat pointer)
+
+    // Total: 64 bytes - exactly one cache line
+}
+
+// Precompressed sibling of a cached file (`app.js.br` for `/app.js`) with its own
+// pre-built responses and ETag
+#[derive(Clone, Debug)]
+pub struct EncodedVariant {
+    pub encoding: ContentEncoding,
+    pub entry: CacheEntry,
+}
+
+impl CacheEntry {
+    // Representation to serve for a request's Accept-Encoding - identity unless a
+    // variant is at least as acceptable
+    #[inline]
+    pub fn negotiate(&self, accept_encoding: Option<&[u8]>) -> &CacheEntry {
+        let (variants, accept_encoding) = match (&self.variants, accept_encoding) {
+            (Some(variants), Some(accept_encoding)) => (variants, accept_encoding),
+            _ => return self,
+        };
+
+        let accept = AcceptEncoding::parse(accept_encoding);
+        match accept.negotiate(variants.iter().map(|variant| variant.encoding)) {
+            Some(encoding) => variants
+                .iter()
+                .find(|variant| variant.encoding == encoding)
+                .map_or(self, |variant| &variant.entry),
+            None => self,
+        }
+    }
+
+    // Size of every pre-built response for this file, variants included
+    fn response_bytes(&self) -> usize {
+        let own = self.complete_response.len() + self.headers_only.len() + self.not_modified_response.len();
+        let variants: usize = self.variants.iter().flat_map(|variants| variants.iter()).map(|v| v.entry.response_bytes()).sum();
+        own + variants
+    }
+
+    // Byte-level comparison of every representation, used when diffing generations
+    fn same_content(&self, other: &CacheEntry) -> bool {
+        if self.complete_response != other.complete_response {
+            return false;
+        }
+        match (&self.variants, &other.variants) {
+            (None, None) => true,
+            (Some(ours), Some(theirs)) => {
+                ours.len() == theirs.len()
+                    && ours.iter().zip(theirs.iter()).all(|(a, b)| {
+                        a.encoding == b.encoding && a.entry.complete_response == b.entry.complete_response
+                    })
+            }
+            _ => false,
+        }
+    }
+}
+
+// How ETags are derived when the cache is built
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EtagMode {
+    // `W/"size-mtime"` - free to compute, but only as good as the file timestamps
+    Weak,
+    // Hash of the representation's bytes
+    Strong,
+}
+
+impl EtagMode {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "weak" => Some(EtagMode::Weak),
+            "strong" | "hash" => Some(EtagMode::Strong),
+            _ => None,
+        }
+    }
+}
+
+// Strong ETag from content alone: the first 128 bits of its SHA-256. Identical bytes get
+// the same validator on every replica and rebuild, whatever their mtimes.
+pub fn content_etag(content: &[u8]) -> String {
+    format!("\"{}\"", to_hex(&sha256(content)[..16]))
+}
+
+// Validator headers of a request, as raw values
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Preconditions<'a> {
+    pub if_match: Option<&'a [u8]>,
+    pub if_none_match: Option<&'a [u8]>,
+    pub if_modified_since: Option<&'a [u8]>,
+    pub if_unmodified_since: Option<&'a [u8]>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Precondition {
+    // Serve the representation (or the requested range of it)
+    Proceed,
+    // 304 Not Modified
+    NotModified,
+    // 412 Precondition Failed
+    Failed,
+}
+
+impl CacheEntry {
+    // Evaluate preconditions for a GET or HEAD in RFC 9110 13.2.2 order: If-Match, else
+    // If-Unmodified-Since; then If-None-Match, else If-Modified-Since. A date header is
+    // only consulted when its entity-tag counterpart is absent, and unparseable dates
+    // are ignored.
+    pub fn evaluate_preconditions(&self, conditions: &Preconditions<'_>) -> Precondition {
+        if let Some(if_match) = conditions.if_match {
+            if !etag_list_matches(if_match, &self.etag, Comparison::Strong) {
+                return Precondition::Failed;
+            }
+        } else if let Some(date) = conditions.if_unmodified_since.and_then(parse_date) {
+            if self.last_modified_timestamp > date {
+                return Precondition::Failed;
+            }
+        }
+
+        if let Some(if_none_match) = conditions.if_none_match {
+            if etag_list_matches(if_none_match, &self.etag, Comparison::Weak) {
+                return Precondition::NotModified;
+            }
+        } else if let Some(date) = conditions.if_modified_since.and_then(parse_date) {
+            if self.last_modified_timestamp <= date {
+                return Precondition::NotModified;
+            }
+        }
+
+        Precondition::Proceed
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Comparison {
+    Strong,
+    Weak,
+}
+
+// Match `etag` against an `If-Match`/`If-None-Match` value: `*` or a comma-separated list
+// of entity-tags (RFC 9110 8.8.3). Strong comparison requires both tags to be strong; weak
+// comparison ignores the `W/` prefix. A malformed list matches nothing.
+fn etag_list_matches(value: &[u8], etag: &str, comparison: Comparison) -> bool {
+    let (ours_weak, ours) = split_etag(etag.as_bytes());
+    let mut rest = trim_ows(value);
+    if rest == b"*" {
+        return true;
+    }
+
+    loop {
+        // Empty list elements are allowed anywhere: OWS "," OWS
+        while let Some(after) = rest.strip_prefix(b",") {
+            rest = trim_ows(after);
+        }
+        if rest.is_empty() {
+            return false;
+        }
+
+        let weak = rest.starts_with(b"W/");
+        if weak {
+            rest = &rest[2..];
+        }
+        // Opaque tag: DQUOTE *etagc DQUOTE
+        let tag = match rest.strip_prefix(b"\"") {
+            Some(after) => match after.iter().position(|&b| b == b'"') {
+                Some(end) => &after[..end],
+                None => return false,
+            },
+            None => return false,
+        };
+        let matches = match comparison {
+            Comparison::Weak => tag == ours,
+            Comparison::Strong => !weak && !ours_weak && tag == ours,
+        };
+        if matches {
+            return true;
+        }
+
+        // Elements are separated by a comma
+        rest = trim_ows(&rest[tag.len() + 2..]);
+        if !rest.is_empty() && !rest.starts_with(b",") {
+            return false;
+        }
+    }
+}
+
+// (weak, opaque tag without quotes) of one of our own well-formed ETags
+fn split_etag(etag: &[u8]) -> (bool, &[u8]) {
+    let (weak, tag) = match etag.strip_prefix(b"W/") {
+        Some(tag) => (true, tag),
+        None => (false, etag),
+    };
+    (weak, tag.strip_prefix(b"\"").and_then(|tag| tag.strip_suffix(b"\"")).unwrap_or(tag))
+}
+
+fn trim_ows(value: &[u8]) -> &[u8] {
+    let start = value.iter().position(|&b| b != b' ' && b != b'\t').unwrap_or(value.len());
+    let end = value.iter().rposition(|&b| b != b' ' && b != b'\t').map_or(start, |end| end + 1);
+    &value[start..end]
+}
+
+fn parse_date(value: &[u8]) -> Option<SystemTime> {
+    httpdate::parse_http_date(std::str::from_utf8(value).ok()?.trim()).ok()
+}
+
+// A stored path alongside its entry - the full key guards against hash collisions
+#[derive(Debug, Clone)]
+struct TrieSlot {
+    path: Box<str>,
+    entry: CacheEntry,
+}
+
+// Two distinct normalized paths sharing the same 32-bit FNV hash
+#[derive(Debug, Clone, PartialEq)]
+pub struct HashCollision {
+    pub hash: u32,
+    pub existing: Box<str>,
+    pub inserted: Box<str>,
+}
+
+// Directory index names when none are configured
+pub const DEFAULT_INDEX_FILES: &[&str] = &["index.html"];
+
+// Compact trie for prefix matching - optimized for trailing slash handling
+#[derive(Debug, Clone)]
+pub struct PathTrie {
+    // Maps normalized path hashes to buckets of (full path, entry); buckets hold
+    // a single slot unless two paths collide
+    exact_matches: FxHashMap<u32, Vec<TrieSlot>>,
+    // Special handling for index files
+    index_entries: FxHashMap<u32, Vec<TrieSlot>>, // Maps directory hash to index.html entry
+    // Collisions seen while inserting, reported once the cache is built
+    collisions: Vec<HashCollision>,
+    // `_redirects` rules of the same content tree, swapped in with the files
+    redirects: Redirects,
+    // Custom `<status>.html` error pages, also swapped in with the files
+    error_pages: ErrorPages,
+    // File names registered as their directory's index, in order of preference
+    index_files: Vec<String>,
+}
+
+impl Default for PathTrie {
+    fn default() -> Self {
+        Self::with_index_files(DEFAULT_INDEX_FILES.iter().map(|name| name.to_string()).collect())
+    }
+}
+
+impl PathTrie {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_index_files(index_files: Vec<String>) -> Self {
+        Self {
+            exact_matches: FxHashMap::default(),
+            index_entries: FxHashMap::default(),
+            collisions: Vec::new(),
+            redirects: Redirects::default(),
+            error_pages: ErrorPages::default(),
+            index_files,
+        }
+    }
+
+    // Optimized path normalization with integrated query stripping
+    // Returns the normalized slice (borrowed, no allocation) with its hash
+    #[inline]
+    pub fn normalize_path_hash(path: &str) -> (&str, u32, bool) {
+        let end_pos = path.bytes().position(|byte| byte == b'?').unwrap_or(path.len());
+        Self::key_hash(&path[..end_pos])
+    }
+
+    // Key and hash of an already canonical path: trailing slash handling + hash computation
+    #[inline]
+    fn key_hash(path: &str) -> (&str, u32, bool) {
+        const FNV_OFFSET_BASIS: u32 = 2166136261;
+        const FNV_PRIME: u32 = 16777619;
+
+        let path_bytes = path.as_bytes();
+        let mut hash = FNV_OFFSET_BASIS;
+        let mut end_pos = path_bytes.len();
+        let mut is_directory_style = false;
+
+        // Check for directory-style path (trailing slash before query)
+        if end_pos > 1 && path_bytes[end_pos - 1] == b'/' {
+            is_directory_style = true;
+            end_pos -= 1; // Remove trailing slash from hash computation
+        }
+
+        // Hash the clean, normalized path portion
+        for &byte in &path_bytes[..end_pos] {
+            hash ^= byte as u32;
+            hash = hash.wrapping_mul(FNV_PRIME);
+        }
+
+        (&path[..end_pos], hash, is_directory_style)
+    }
+
+    // Insert or replace the slot for `key`, recording a collision if another path owns the hash
+    fn insert_slot(
+        map: &mut FxHashMap<u32, Vec<TrieSlot>>,
+        collisions: &mut Vec<HashCollision>,
+        key: &str,
+        hash: u32,
+        entry: CacheEntry,
+    ) {
+        let bucket = map.entry(hash).or_default();
+        if let Some(slot) = bucket.iter_mut().find(|slot| &*slot.path == key) {
+            slot.entry = entry;
+            return;
+        }
+        if let Some(existing) = bucket.first() {
+            collisions.push(HashCollision {
+                hash,
+                existing: existing.path.clone(),
+                inserted: Box::from(key),
+            });
+        }
+        bucket.push(TrieSlot { path: Box::from(key), entry });
+    }
+
+    // Zero-allocation bucket scan - compares the full normalized path
+    #[inline]
+    fn find_slot<'a>(map: &'a FxHashMap<u32, Vec<TrieSlot>>, key: &str, hash: u32) -> Option<&'a CacheEntry> {
+        map.get(&hash)?
+            .iter()
+            .find(|slot| &*slot.path == key)
+            .map(|slot| &slot.entry)
+    }
+
+    // `path` is a decoded file path - normalized the same way request targets are, but
+    // never cut at `?`, which is just another character in a file name
+    pub fn insert(&mut self, path: &str, entry: CacheEntry) {
+        let path = normalize_path(path);
+        let path = path.as_ref();
+        let (key, path_hash, _is_directory_style) = Self::key_hash(path);
+
+        // Always store in exact matches
+        Self::insert_slot(&mut self.exact_matches, &mut self.collisions, key, path_hash, entry.clone());
+
+        // Index files are also registered for directory-style access. A name listed
+        // earlier wins, whichever file is inserted first.
+        let (dir_path, name) = path.split_at(path.rfind('/').map_or(0, |slash| slash + 1));
+        if let Some(rank) = self.index_files.iter().position(|index| index == name) {
+            let preferred_exists = self.index_files[..rank].iter().any(|index| {
+                let preferred = format!("{}{}", dir_path, index);
+                let (key, hash, _) = Self::key_hash(&preferred);
+                Self::find_slot(&self.exact_matches, key, hash).is_some()
+            });
+            if !preferred_exists {
+                let (dir_key, dir_hash, _) = Self::key_hash(dir_path);
+                Self::insert_slot(&mut self.index_entries, &mut self.collisions, dir_key, dir_hash, entry);
+            }
+        }
+    }
+
+    // Look up a raw request target, query and percent-encoding included
+    pub fn get(&self, target: &str) -> Option<CacheEntry> {
+        let target = parse_target(target).ok()?;
+        self.lookup(&target.path)
+    }
+
+    // Look up a canonical path, as produced by `parse_target`
+    pub fn lookup(&self, path: &str) -> Option<CacheEntry> {
+        let (key, path_hash, is_directory_style) = Self::key_hash(path);
+
+        // First try exact match
+        if let Some(entry) = Self::find_slot(&self.exact_matches, key, path_hash) {
+            return Some(entry.clone());
+        }
+
+        // For directory-style requests, try index.html
+        if is_directory_style || key == "/" {
+            if let Some(entry) = Self::find_slot(&self.index_entries, key, path_hash) {
+                return Some(entry.clone());
+            }
+        }
+
+        None
+    }
+
+    pub fn set_redirects(&mut self, redirects: Redirects) {
+        self.redirects = redirects;
+    }
+
+    pub fn redirects(&self) -> &Redirects {
+        &self.redirects
+    }
+
+    pub fn set_error_pages(&mut self, error_pages: ErrorPages) {
+        self.error_pages = error_pages;
+    }
+
+    pub fn error_pages(&self) -> &ErrorPages {
+        &self.error_pages
+    }
+
+    pub fn entry_count(&self) -> usize {
+        self.exact_matches.values().map(Vec::len).sum()
+    }
+
+    // Pre-built response bytes held by this trie; index entries share their Arcs
+    // with exact matches so only those are counted
+    pub fn cached_bytes(&self) -> usize {
+        self.exact_matches
+            .values()
+            .flatten()
+            .map(|slot| slot.entry.response_bytes())
+            .sum()
+    }
+
+    pub fn collisions(&self) -> &[HashCollision] {
+        &self.collisions
+    }
+
+    // Compare served files against a previous generation - used to report reloads
+    pub fn diff(&self, previous: &PathTrie) -> GenerationDiff {
+        let mut diff = GenerationDiff::default();
+
+        for (&hash, bucket) in &self.exact_matches {
+            for slot in bucket {
+                match Self::find_slot(&previous.exact_matches, &slot.path, hash) {
+                    None => diff.added += 1,
+                    Some(old) if !old.same_content(&slot.entry) => diff.changed += 1,
+                    Some(_) => {}
+                }
+            }
+        }
+        for (&hash, bucket) in &previous.exact_matches {
+            diff.removed += bucket
+                .iter()
+                .filter(|slot| Self::find_slot(&self.exact_matches, &slot.path, hash).is_none())
+                .count();
+        }
+
+        diff
+    }
+}
+
+// File-level changes between two cache generations
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct GenerationDiff {
+    pub added: usize,
+    pub removed: usize,
+    pub changed: usize,
+}
+
+// Reader counter padded to its own cache line so the two epochs don't false-share
+#[derive(Debug, Default)]
+#[repr(align(64))]
+struct ReaderCount(AtomicUsize);
+
+// Lock-free cache with atomic RCU pattern
+//
+// Readers announce themselves in one of two epoch counters, load the current
+// generation and leave once the lookup is done. Writers publish a new generation
+// with a single pointer swap, then wait for a grace period (both epoch counters
+// drained once) before freeing the old one. Reads never block; only writers wait.
+#[derive(Debug)]
+pub struct OptimizedCache {
+    // Atomic pointer to current cache generation (lock-free reads)
+    current: AtomicPtr<CacheGeneration>,
+    // Parity selects the counter new readers register in
+    epoch: AtomicUsize,
+    readers: [ReaderCount; 2],
+    // Serializes writers only - never taken on the read path
+    writer: Mutex<()>,
+}
+
+#[derive(Debug)]
+struct CacheGeneration {
+    // PathTrie for efficient prefix matching and trailing slash handling
+    trie: PathTrie,
+    // Summed once per generation rather than on every metrics scrape
+    cached_bytes: usize,
+}
+
+impl CacheGeneration {
+    fn new(trie: PathTrie) -> Self {
+        let cached_bytes = trie.cached_bytes();
+        Self { trie, cached_bytes }
+    }
+}
+
+// Read-side critical section - the generation stays alive until this is dropped
+struct ReadGuard<'a> {
+    cache: &'a OptimizedCache,
+    epoch: usize,
+    generation: &'a CacheGeneration,
+}
+
+impl Drop for ReadGuard<'_> {
+    fn drop(&mut self) {
+        self.cache.readers[self.epoch].0.fetch_sub(1, Ordering::Release);
+    }
+}
+
+impl Default for OptimizedCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl OptimizedCache {
+    pub fn new() -> Self {
+        Self::from_trie(PathTrie::new())
+    }
+
+    pub fn from_trie(trie: PathTrie) -> Self {
+        let initial_cache = Box::into_raw(Box::new(CacheGeneration::new(trie)));
+
+        Self {
+            current: AtomicPtr::new(initial_cache),
+            epoch: AtomicUsize::new(0),
+            readers: Default::default(),
+            writer: Mutex::new(()),
+        }
+    }
+
+    #[inline]
+    fn read(&self) -> ReadGuard<'_> {
+        // SeqCst pairs with the writer's swap and counter checks: either the writer
+        // sees this reader registered, or this reader sees the new generation
+        let epoch = self.epoch.load(Ordering::SeqCst) & 1;
+        self.readers[epoch].0.fetch_add(1, Ordering::SeqCst);
+        let cache_ptr = self.current.load(Ordering::SeqCst);
+
+        // SAFETY: Registered readers hold off reclamation of the generation they loaded
+        let generation = unsafe { &*cache_ptr };
+        ReadGuard { cache: self, epoch, generation }
+    }
+
+    // Lock-free read operation using atomic load with trie lookup
+    pub fn get(&self, path: &str) -> Option<CacheEntry> {
+        // The returned entry owns Arc handles, so it outlives the generation safely
+        self.read().generation.trie.get(path)
+    }
+
+    pub fn lookup(&self, path: &str) -> Option<CacheEntry> {
+        self.read().generation.trie.lookup(path)
+    }
+
+    // Atomically publish a new generation, freeing the old one after a grace period
+    // Blocks until in-flight readers of the old generation have finished
+    pub fn replace(&self, trie: PathTrie) -> GenerationDiff {
+        let _writer = self.writer.lock().unwrap_or_else(|e| e.into_inner());
+
+        // SAFETY: Holding the writer lock - only we could free the current generation
+        let diff = trie.diff(unsafe { &(*self.current.load(Ordering::SeqCst)).trie });
+
+        let new_ptr = Box::into_raw(Box::new(CacheGeneration::new(trie)));
+        let old_ptr = self.current.swap(new_ptr, Ordering::SeqCst);
+
+        self.synchronize();
+
+        // SAFETY: No reader can still reference the old generation after the grace period
+        drop(unsafe { Box::from_raw(old_ptr) });
+        diff
+    }
+
+    // Grace period: flip the epoch twice, draining each counter in turn. Every reader
+    // registered before the swap sits in one of the two counters.
+    fn synchronize(&self) {
+        for _ in 0..2 {
+            let draining = self.epoch.fetch_add(1, Ordering::SeqCst) & 1;
+            let mut spins = 0u32;
+            while self.readers[draining].0.load(Ordering::SeqCst) != 0 {
+                if spins < 64 {
+                    spins += 1;
+                    std::hint::spin_loop();
+                } else {
+                    std::thread::yield_now();
+                }
+            }
+        }
+    }
+
+    // Redirect rules of the current generation - see `Redirects::resolve`
+    pub fn resolve_redirect(&self, path: &str, query: Option<&str>, shadowed: bool) -> Option<Resolution> {
+        self.read().generation.trie.redirects().resolve(path, query, shadowed)
+    }
+
+    // Custom page of the current generation for an error status, if the content root has one
+    pub fn error_page(&self, status: u16, is_head: bool) -> Option<Arc<[u8]>> {
+        let guard = self.read();
+        let page = guard.generation.trie.error_pages().get(status)?;
+        Some(Arc::clone(page.for_method(is_head)))
+    }
+
+    pub fn entry_count(&self) -> usize {
+        self.read().generation.trie.entry_count()
+    }
+
+    pub fn cached_bytes(&self) -> usize {
+        self.read().generation.cached_bytes
+    }
+}
+
+impl Drop for OptimizedCache {
+    fn drop(&mut self) {
+        // SAFETY: Exclusive access - no readers or writers remain
+        drop(unsafe { Box::from_raw(*self.current.get_mut()) });
+    }
+}
@@ -0,0 +1,78 @@
+use std::cell::Cell;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime};
+
+// `Date: ` + IMF-fixdate (always 29 bytes) + CRLF
+pub const DATE_HEADER_LEN: usize = 37;
+
+pub type DateHeader = [u8; DATE_HEADER_LEN];
+
+thread_local! {
+    // Last header formatted on this thread and the second it is for - formatting happens
+    // at most once per second per worker thread
+    static FORMATTED: Cell<(u64, DateHeader)> = const { Cell::new((u64::MAX, [0; DATE_HEADER_LEN])) };
+}
+
+// Shared wall clock at one-second resolution, the precision of an HTTP date. A background
+// task advances it with `tick`; reading it is a single relaxed atomic load.
+pub struct Clock {
+    seconds: AtomicU64,
+}
+
+impl Clock {
+    pub fn new() -> Self {
+        Self::at(SystemTime::now())
+    }
+
+    pub fn at(time: SystemTime) -> Self {
+        Self { seconds: AtomicU64::new(unix_seconds(time)) }
+    }
+
+    // Advance to the current time
+    pub fn tick(&self) {
+        self.set(SystemTime::now());
+    }
+
+    pub fn set(&self, time: SystemTime) {
+        self.seconds.store(unix_seconds(time), Ordering::Relaxed);
+    }
+
+    // Time until the next whole second, so ticks land just after the date changes
+    pub fn until_next_tick() -> Duration {
+        let since_epoch = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default();
+        Duration::from_secs(1) - Duration::from_nanos(u64::from(since_epoch.subsec_nanos()))
+    }
+
+    // `Date: <IMF-fixdate>\r\n`, ready to splice into a response head
+    pub fn date_header(&self) -> DateHeader {
+        let seconds = self.seconds.load(Ordering::Relaxed);
+        FORMATTED.with(|formatted| {
+            let (cached_at, header) = formatted.get();
+            if cached_at == seconds {
+                return header;
+            }
+            let header = format_date_header(seconds);
+            formatted.set((seconds, header));
+            header
+        })
+    }
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn unix_seconds(time: SystemTime) -> u64 {
+    time.duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default().as_secs()
+}
+
+fn format_date_header(seconds: u64) -> DateHeader {
+    let date = httpdate::fmt_http_date(SystemTime::UNIX_EPOCH + Duration::from_secs(seconds));
+    let mut header = [0; DATE_HEADER_LEN];
+    header[..6].copy_from_slice(b"Date: ");
+    header[6..35].copy_from_slice(date.as_bytes());
+    header[35..].copy_from_slice(b"\r\n");
+    header
+}
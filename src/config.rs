@@ -0,0 +1,518 @@
+use crate::access_log::AccessLogFormat;
+use crate::cache::{EtagMode, DEFAULT_INDEX_FILES};
+use crate::mime;
+use crate::request::HeadLimits;
+use crate::security::{SecurityHeaders, SecurityProfile, SECURITY_HEADER_OPTIONS};
+use crate::spa::SpaFallback;
+use crate::uri::parse_target;
+use crate::url_style::{TrailingSlash, UrlStyle};
+use std::fmt;
+use std::net::IpAddr;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+// Environment variable prefix - every option `--foo-bar` maps to `KISS_FOO_BAR`
+pub const ENV_PREFIX: &str = "KISS_";
+
+// Reproducible-builds convention: Unix time of the source the content was built from
+pub const SOURCE_DATE_EPOCH: &str = "SOURCE_DATE_EPOCH";
+
+// Bounds enforced at startup so a typo can't produce an unusable server
+const MIN_REQUEST_SIZE: usize = 256;
+const MAX_REQUEST_SIZE_LIMIT: usize = 1024 * 1024;
+const MAX_HEADER_COUNT_LIMIT: usize = 10_000;
+const MAX_TIMEOUT_SECS: u64 = 3600;
+const MIN_ADMIN_TOKEN_LEN: usize = 16;
+
+// Runtime configuration - resolved once at startup, read-only afterwards
+#[derive(Clone, Debug, PartialEq)]
+pub struct Config {
+    pub bind_address: IpAddr,
+    pub port: u16,
+    pub static_dir: PathBuf,
+    pub max_request_size: usize,
+    pub max_header_size: usize,
+    pub max_headers_size: usize,
+    pub max_headers: usize,
+    pub connection_timeout: Duration,
+    pub keepalive_timeout: Duration,
+    pub watch: bool,
+    pub watch_debounce: Duration,
+    pub admin_token: Option<String>,
+    pub compress: bool,
+    // Detect the type of extensionless files from their first bytes
+    pub sniff: bool,
+    // Serve `spa_fallback` for missed routes under `spa_include` but not `spa_exclude`
+    pub spa: bool,
+    pub spa_fallback: String,
+    pub spa_include: Vec<String>,
+    pub spa_exclude: Vec<String>,
+    // Canonical spellings of directory and HTML URLs, and the status redirecting to them
+    pub trailing_slash: TrailingSlash,
+    pub clean_urls: bool,
+    pub index_files: Vec<String>,
+    pub redirect_status: u16,
+    pub etag: EtagMode,
+    pub header_rules: Option<PathBuf>,
+    // Extension to media type mappings: a mime.types file, then single overrides
+    pub mime_types: Option<PathBuf>,
+    pub mime_overrides: Vec<(String, String)>,
+    pub security_profile: SecurityProfile,
+    // Single security headers set on top of the profile, in the order given; None removes
+    pub security_overrides: Vec<(&'static str, Option<String>)>,
+    // Last-Modified of every file when set, instead of its mtime
+    pub source_date_epoch: Option<SystemTime>,
+    pub access_log: AccessLogFormat,
+    pub access_log_sample: u64,
+    pub metrics: bool,
+    pub metrics_port: Option<u16>,
+    pub shutdown_delay: Duration,
+    pub shutdown_timeout: Duration,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            bind_address: IpAddr::from([0, 0, 0, 0]),
+            port: 8080,
+            static_dir: PathBuf::from("./content"),
+            max_request_size: 8192,
+            max_header_size: 8192,
+            max_headers_size: 32 * 1024,
+            max_headers: 100,
+            connection_timeout: Duration::from_secs(30),
+            keepalive_timeout: Duration::from_secs(5),
+            watch: false,
+            watch_debounce: Duration::from_millis(500),
+            admin_token: None,
+            compress: false,
+            sniff: false,
+            spa: false,
+            spa_fallback: "/index.html".to_string(),
+            spa_include: vec!["/".to_string()],
+            spa_exclude: Vec::new(),
+            trailing_slash: TrailingSlash::Off,
+            clean_urls: false,
+            index_files: DEFAULT_INDEX_FILES.iter().map(|name| name.to_string()).collect(),
+            redirect_status: 301,
+            etag: EtagMode::Weak,
+            header_rules: None,
+            mime_types: None,
+            mime_overrides: Vec::new(),
+            security_profile: SecurityProfile::Custom,
+            security_overrides: Vec::new(),
+            source_date_epoch: None,
+            access_log: AccessLogFormat::Off,
+            access_log_sample: 1,
+            metrics: false,
+            metrics_port: None,
+            shutdown_delay: Duration::ZERO,
+            shutdown_timeout: Duration::from_secs(20),
+        }
+    }
+}
+
+// Option table shared by CLI parsing, env lookup and usage output
+// An empty `value_name` marks a switch: `--flag` alone means true
+struct OptionSpec {
+    name: &'static str,
+    value_name: &'static str,
+    help: &'static str,
+}
+
+impl OptionSpec {
+    fn is_switch(&self) -> bool {
+        self.value_name.is_empty()
+    }
+}
+
+const OPTIONS: &[OptionSpec] = &[
+    OptionSpec { name: "bind", value_name: "ADDR", help: "Address to listen on (default: 0.0.0.0)" },
+    OptionSpec { name: "port", value_name: "PORT", help: "Port to listen on (default: 8080)" },
+    OptionSpec { name: "static-dir", value_name: "DIR", help: "Directory to serve (default: ./content)" },
+    OptionSpec { name: "max-request-size", value_name: "BYTES", help: "Maximum request line size, 414 beyond it (default: 8192)" },
+    OptionSpec { name: "max-header-size", value_name: "BYTES", help: "Maximum size of one header line, 431 beyond it (default: 8192)" },
+    OptionSpec { name: "max-headers-size", value_name: "BYTES", help: "Maximum size of all headers together (default: 32768)" },
+    OptionSpec { name: "max-headers", value_name: "N", help: "Maximum number of header lines (default: 100)" },
+    OptionSpec { name: "connection-timeout", value_name: "SECS", help: "Total connection lifetime (default: 30)" },
+    OptionSpec { name: "keepalive-timeout", value_name: "SECS", help: "Idle time between keep-alive requests (default: 5)" },
+    OptionSpec { name: "watch", value_name: "", help: "Rebuild the cache when the content directory changes" },
+    OptionSpec { name: "watch-debounce-ms", value_name: "MS", help: "Quiet period before a change triggers a rebuild (default: 500)" },
+    OptionSpec { name: "admin-token", value_name: "TOKEN", help: "Bearer token enabling POST /admin/reload (default: disabled)" },
+    OptionSpec { name: "compress", value_name: "", help: "Build gzip/brotli variants of text assets when the cache is built" },
+    OptionSpec { name: "etag", value_name: "MODE", help: "ETags from size and mtime (weak) or a content hash (strong) (default: weak)" },
+    OptionSpec { name: "header-rules", value_name: "FILE", help: "Per-path Cache-Control and custom header rules (default: none)" },
+    OptionSpec { name: "mime-types", value_name: "FILE", help: "Apache or nginx mime.types file overriding built-in types (default: none)" },
+    OptionSpec { name: "mime-type", value_name: "EXT=TYPE", help: "Override the type of one extension; comma-separated list allowed" },
+    OptionSpec { name: "sniff", value_name: "", help: "Detect the type of extensionless files from their content" },
+    OptionSpec { name: "spa", value_name: "", help: "Serve the fallback document for client-side routes instead of 404" },
+    OptionSpec { name: "spa-fallback", value_name: "PATH", help: "Document served for client-side routes (default: /index.html)" },
+    OptionSpec { name: "spa-include", value_name: "PREFIXES", help: "Comma-separated path prefixes that fall back (default: /)" },
+    OptionSpec { name: "spa-exclude", value_name: "PREFIXES", help: "Comma-separated path prefixes that never fall back, e.g. /api/,/assets/" },
+    OptionSpec { name: "trailing-slash", value_name: "MODE", help: "Canonical directory URLs: off, add (/docs -> /docs/) or remove (default: off)" },
+    OptionSpec { name: "clean-urls", value_name: "", help: "Serve /about from about.html and redirect /about.html to /about" },
+    OptionSpec { name: "index-files", value_name: "NAMES", help: "Comma-separated directory index file names, by preference (default: index.html)" },
+    OptionSpec { name: "redirect-status", value_name: "STATUS", help: "Status of redirects to canonical URLs: 301 or 308 (default: 301)" },
+    OptionSpec { name: "security-headers", value_name: "PROFILE", help: "Security headers: none, baseline, strict or custom (default: custom)" },
+    OptionSpec { name: "csp", value_name: "POLICY", help: "Content-Security-Policy, or 'off' to drop the profile's" },
+    OptionSpec { name: "hsts", value_name: "VALUE", help: "Strict-Transport-Security, or 'off'" },
+    OptionSpec { name: "referrer-policy", value_name: "POLICY", help: "Referrer-Policy, or 'off'" },
+    OptionSpec { name: "permissions-policy", value_name: "POLICY", help: "Permissions-Policy, or 'off'" },
+    OptionSpec { name: "frame-options", value_name: "VALUE", help: "X-Frame-Options (DENY or SAMEORIGIN), or 'off'" },
+    OptionSpec { name: "coop", value_name: "POLICY", help: "Cross-Origin-Opener-Policy, or 'off'" },
+    OptionSpec { name: "coep", value_name: "POLICY", help: "Cross-Origin-Embedder-Policy, or 'off'" },
+    OptionSpec { name: "access-log", value_name: "FORMAT", help: "Access log to stdout: off, json, common or combined (default: off)" },
+    OptionSpec { name: "access-log-sample", value_name: "N", help: "Log one in every N requests (default: 1)" },
+    OptionSpec { name: "metrics", value_name: "", help: "Serve Prometheus metrics at /metrics on the main port" },
+    OptionSpec { name: "metrics-port", value_name: "PORT", help: "Serve /metrics on a separate port instead (default: disabled)" },
+    OptionSpec { name: "shutdown-delay", value_name: "SECS", help: "Keep serving after SIGTERM while endpoints are removed (default: 0)" },
+    OptionSpec { name: "shutdown-timeout", value_name: "SECS", help: "Maximum wait for in-flight connections on shutdown (default: 20)" },
+];
+
+#[derive(Debug, PartialEq)]
+pub enum ConfigError {
+    HelpRequested,
+    UnknownOption(String),
+    MissingValue(String),
+    InvalidValue { option: String, value: String, reason: String },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::HelpRequested => write!(f, "help requested"),
+            ConfigError::UnknownOption(option) => write!(f, "unknown option '{}'", option),
+            ConfigError::MissingValue(option) => write!(f, "option '--{}' requires a value", option),
+            ConfigError::InvalidValue { option, value, reason } => {
+                write!(f, "invalid value '{}' for '{}': {}", value, option, reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl Config {
+    // Resolve configuration from the process environment and command line
+    pub fn from_env_and_args() -> Result<Self, ConfigError> {
+        Self::parse(std::env::args().skip(1), |key| std::env::var(key).ok())
+    }
+
+    // Precedence: defaults < KISS_* environment variables < CLI flags
+    pub fn parse<I, S, F>(args: I, env: F) -> Result<Self, ConfigError>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+        F: Fn(&str) -> Option<String>,
+    {
+        let mut config = Config::default();
+
+        for spec in OPTIONS {
+            let key = env_var_name(spec.name);
+            if let Some(value) = env(&key) {
+                config.apply(spec.name, &value, &key)?;
+            }
+        }
+        // An empty value is treated as unset, anything else must be a Unix timestamp
+        if let Some(value) = env(SOURCE_DATE_EPOCH).filter(|value| !value.is_empty()) {
+            let seconds = value.parse::<u64>().map_err(|_| ConfigError::InvalidValue {
+                option: SOURCE_DATE_EPOCH.to_string(),
+                value: value.clone(),
+                reason: "expected a Unix timestamp".to_string(),
+            })?;
+            config.source_date_epoch = Some(SystemTime::UNIX_EPOCH + Duration::from_secs(seconds));
+        }
+
+        let mut args = args.into_iter().map(Into::into);
+        while let Some(arg) = args.next() {
+            if arg == "-h" || arg == "--help" {
+                return Err(ConfigError::HelpRequested);
+            }
+
+            let flag = arg.strip_prefix("--").ok_or_else(|| ConfigError::UnknownOption(arg.clone()))?;
+            let (name, inline_value) = match flag.split_once('=') {
+                Some((name, value)) => (name, Some(value.to_string())),
+                None => (flag, None),
+            };
+
+            let spec = OPTIONS
+                .iter()
+                .find(|spec| spec.name == name)
+                .ok_or_else(|| ConfigError::UnknownOption(arg.clone()))?;
+
+            let value = match inline_value {
+                Some(value) => value,
+                None if spec.is_switch() => "true".to_string(),
+                None => args.next().ok_or_else(|| ConfigError::MissingValue(name.to_string()))?,
+            };
+            config.apply(name, &value, &format!("--{}", name))?;
+        }
+
+        config.validate()?;
+        Ok(config)
+    }
+
+    // Apply a single option value; `source` names the flag or env var for error messages
+    fn apply(&mut self, name: &str, value: &str, source: &str) -> Result<(), ConfigError> {
+        let invalid = |reason: &str| ConfigError::InvalidValue {
+            option: source.to_string(),
+            value: value.to_string(),
+            reason: reason.to_string(),
+        };
+
+        match name {
+            "bind" => self.bind_address = value.parse().map_err(|_| invalid("expected an IP address"))?,
+            "port" => self.port = value.parse().map_err(|_| invalid("expected a port number"))?,
+            "static-dir" => {
+                if value.is_empty() {
+                    return Err(invalid("must not be empty"));
+                }
+                self.static_dir = PathBuf::from(value);
+            }
+            "max-request-size" => self.max_request_size = value.parse().map_err(|_| invalid("expected a size in bytes"))?,
+            "max-header-size" => self.max_header_size = value.parse().map_err(|_| invalid("expected a size in bytes"))?,
+            "max-headers-size" => self.max_headers_size = value.parse().map_err(|_| invalid("expected a size in bytes"))?,
+            "max-headers" => self.max_headers = value.parse().map_err(|_| invalid("expected a number of headers"))?,
+            "connection-timeout" => self.connection_timeout = parse_secs(value).ok_or_else(|| invalid("expected whole seconds"))?,
+            "keepalive-timeout" => self.keepalive_timeout = parse_secs(value).ok_or_else(|| invalid("expected whole seconds"))?,
+            "watch" => self.watch = parse_bool(value).ok_or_else(|| invalid("expected true or false"))?,
+            "watch-debounce-ms" => {
+                self.watch_debounce = value.parse().map(Duration::from_millis).map_err(|_| invalid("expected milliseconds"))?
+            }
+            "admin-token" => {
+                // Never echo secrets back in error messages
+                if value.len() < MIN_ADMIN_TOKEN_LEN {
+                    return Err(ConfigError::InvalidValue {
+                        option: source.to_string(),
+                        value: "<redacted>".to_string(),
+                        reason: format!("must be at least {} characters", MIN_ADMIN_TOKEN_LEN),
+                    });
+                }
+                self.admin_token = Some(value.to_string());
+            }
+            "compress" => self.compress = parse_bool(value).ok_or_else(|| invalid("expected true or false"))?,
+            "sniff" => self.sniff = parse_bool(value).ok_or_else(|| invalid("expected true or false"))?,
+            "spa" => self.spa = parse_bool(value).ok_or_else(|| invalid("expected true or false"))?,
+            "spa-fallback" => {
+                self.spa_fallback = match parse_target(value) {
+                    Ok(target) if target.query.is_none() => target.path.into_owned(),
+                    _ => return Err(invalid("expected an absolute URL path")),
+                }
+            }
+            "spa-include" => self.spa_include = parse_prefixes(value).ok_or_else(|| invalid("expected paths starting with /"))?,
+            "spa-exclude" => self.spa_exclude = parse_prefixes(value).ok_or_else(|| invalid("expected paths starting with /"))?,
+            "trailing-slash" => {
+                self.trailing_slash = TrailingSlash::parse(value).ok_or_else(|| invalid("expected off, add or remove"))?
+            }
+            "clean-urls" => self.clean_urls = parse_bool(value).ok_or_else(|| invalid("expected true or false"))?,
+            "index-files" => self.index_files = parse_index_files(value).ok_or_else(|| invalid("expected file names without '/'"))?,
+            "redirect-status" => {
+                self.redirect_status = match value.trim() {
+                    "301" => 301,
+                    "308" => 308,
+                    _ => return Err(invalid("expected 301 or 308")),
+                }
+            }
+            "etag" => self.etag = EtagMode::parse(value).ok_or_else(|| invalid("expected weak or strong"))?,
+            "header-rules" => {
+                if value.is_empty() {
+                    return Err(invalid("must not be empty"));
+                }
+                self.header_rules = Some(PathBuf::from(value));
+            }
+            "mime-types" => {
+                if value.is_empty() {
+                    return Err(invalid("must not be empty"));
+                }
+                self.mime_types = Some(PathBuf::from(value));
+            }
+            "mime-type" => {
+                for pair in value.split(',').filter(|pair| !pair.trim().is_empty()) {
+                    self.mime_overrides.push(mime::parse_override(pair).map_err(|reason| invalid(&reason))?);
+                }
+            }
+            "security-headers" => {
+                self.security_profile = SecurityProfile::parse(value).ok_or_else(|| invalid("expected none, baseline, strict or custom"))?
+            }
+            "csp" | "hsts" | "referrer-policy" | "permissions-policy" | "frame-options" | "coop" | "coep" => {
+                let header = SECURITY_HEADER_OPTIONS
+                    .iter()
+                    .find(|(option, _)| *option == name)
+                    .map(|&(_, header)| header)
+                    .ok_or_else(|| ConfigError::UnknownOption(source.to_string()))?;
+                let value = match value.trim() {
+                    "" => return Err(invalid("must not be empty")),
+                    "off" => None,
+                    value if value.chars().any(|c| c.is_control()) => return Err(invalid("must not contain control characters")),
+                    value => Some(value.to_string()),
+                };
+                self.security_overrides.push((header, value));
+            }
+            "access-log" => {
+                self.access_log = AccessLogFormat::parse(value).ok_or_else(|| invalid("expected off, json, common or combined"))?
+            }
+            "access-log-sample" => {
+                self.access_log_sample = match value.parse() {
+                    Ok(n) if n > 0 => n,
+                    _ => return Err(invalid("expected a positive integer")),
+                }
+            }
+            "metrics" => self.metrics = parse_bool(value).ok_or_else(|| invalid("expected true or false"))?,
+            "metrics-port" => {
+                self.metrics_port = match value.parse() {
+                    Ok(port) if port > 0 => Some(port),
+                    _ => return Err(invalid("expected a port between 1 and 65535")),
+                }
+            }
+            "shutdown-delay" => self.shutdown_delay = parse_secs(value).ok_or_else(|| invalid("expected whole seconds"))?,
+            "shutdown-timeout" => self.shutdown_timeout = parse_secs(value).ok_or_else(|| invalid("expected whole seconds"))?,
+            _ => return Err(ConfigError::UnknownOption(source.to_string())),
+        }
+        Ok(())
+    }
+
+    // Cross-field and range checks - run after all sources are merged
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let invalid = |option: &str, value: String, reason: &str| ConfigError::InvalidValue {
+            option: option.to_string(),
+            value,
+            reason: reason.to_string(),
+        };
+
+        if self.port == 0 {
+            return Err(invalid("port", "0".into(), "must be between 1 and 65535"));
+        }
+        for (option, size) in [
+            ("max-request-size", self.max_request_size),
+            ("max-header-size", self.max_header_size),
+            ("max-headers-size", self.max_headers_size),
+        ] {
+            if !(MIN_REQUEST_SIZE..=MAX_REQUEST_SIZE_LIMIT).contains(&size) {
+                return Err(invalid(option, size.to_string(), &format!("must be between {} and {}", MIN_REQUEST_SIZE, MAX_REQUEST_SIZE_LIMIT)));
+            }
+        }
+        if self.max_header_size > self.max_headers_size {
+            return Err(invalid("max-header-size", self.max_header_size.to_string(), "must not exceed max-headers-size"));
+        }
+        if !(1..=MAX_HEADER_COUNT_LIMIT).contains(&self.max_headers) {
+            return Err(invalid("max-headers", self.max_headers.to_string(), &format!("must be between 1 and {}", MAX_HEADER_COUNT_LIMIT)));
+        }
+        for (option, timeout) in [("connection-timeout", self.connection_timeout), ("keepalive-timeout", self.keepalive_timeout)] {
+            if timeout.is_zero() || timeout.as_secs() > MAX_TIMEOUT_SECS {
+                return Err(invalid(option, timeout.as_secs().to_string(), &format!("must be between 1 and {}", MAX_TIMEOUT_SECS)));
+            }
+        }
+        // Zero is allowed for both: no pre-stop delay, or exit without waiting
+        for (option, duration) in [("shutdown-delay", self.shutdown_delay), ("shutdown-timeout", self.shutdown_timeout)] {
+            if duration.as_secs() > MAX_TIMEOUT_SECS {
+                return Err(invalid(option, duration.as_secs().to_string(), &format!("must be at most {}", MAX_TIMEOUT_SECS)));
+            }
+        }
+        if self.keepalive_timeout > self.connection_timeout {
+            return Err(invalid(
+                "keepalive-timeout",
+                self.keepalive_timeout.as_secs().to_string(),
+                "must not exceed connection-timeout",
+            ));
+        }
+        if self.metrics_port == Some(self.port) {
+            return Err(invalid("metrics-port", self.port.to_string(), "must differ from port"));
+        }
+        for (option, path) in [("header-rules", &self.header_rules), ("mime-types", &self.mime_types)] {
+            if let Some(path) = path.as_ref().filter(|path| !path.is_file()) {
+                return Err(invalid(option, path.display().to_string(), "not a file"));
+            }
+        }
+        // A missing directory is tolerated (served as empty), but a file in its place is a mistake
+        if self.static_dir.exists() && !self.static_dir.is_dir() {
+            return Err(invalid("static-dir", self.static_dir.display().to_string(), "not a directory"));
+        }
+        Ok(())
+    }
+
+    // Security headers for every response: the profile with individual options applied
+    pub fn security_headers(&self) -> SecurityHeaders {
+        SecurityHeaders::new(self.security_profile, &self.security_overrides)
+    }
+
+    // Fallback for client-side routes, when enabled
+    pub fn spa_fallback(&self) -> Option<SpaFallback> {
+        self.spa.then(|| SpaFallback {
+            document: self.spa_fallback.clone(),
+            include: self.spa_include.clone(),
+            exclude: self.spa_exclude.clone(),
+        })
+    }
+
+    pub fn url_style(&self) -> UrlStyle {
+        UrlStyle {
+            trailing_slash: self.trailing_slash,
+            clean_urls: self.clean_urls,
+            index_files: self.index_files.clone(),
+        }
+    }
+
+    // Request head limits, as enforced by the parser
+    pub fn head_limits(&self) -> HeadLimits {
+        HeadLimits {
+            request_line: self.max_request_size,
+            header_line: self.max_header_size,
+            header_block: self.max_headers_size,
+            header_count: self.max_headers,
+        }
+    }
+
+    pub fn usage() -> String {
+        let mut usage = String::from("Usage: kiss [OPTIONS]\n\nOptions:\n");
+        for spec in OPTIONS {
+            let flag = if spec.is_switch() {
+                format!("--{}", spec.name)
+            } else {
+                format!("--{} <{}>", spec.name, spec.value_name)
+            };
+            usage.push_str(&format!("  {:<32} {} [env: {}]\n", flag, spec.help, env_var_name(spec.name)));
+        }
+        usage.push_str(&format!("  {:<32} Print this help\n", "-h, --help"));
+        usage.push_str(&format!("\nEnvironment:\n  {:<32} Unix time used as Last-Modified for every file\n", SOURCE_DATE_EPOCH));
+        usage
+    }
+}
+
+fn env_var_name(option: &str) -> String {
+    let mut key = String::with_capacity(ENV_PREFIX.len() + option.len());
+    key.push_str(ENV_PREFIX);
+    key.extend(option.chars().map(|c| if c == '-' { '_' } else { c.to_ascii_uppercase() }));
+    key
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+    match value.to_ascii_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => Some(true),
+        "0" | "false" | "no" | "off" => Some(false),
+        _ => None,
+    }
+}
+
+// Comma-separated URL path prefixes, decoded like request paths so they compare equal
+fn parse_prefixes(value: &str) -> Option<Vec<String>> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|prefix| !prefix.is_empty())
+        .map(|prefix| match parse_target(prefix) {
+            Ok(target) if target.query.is_none() => Some(target.path.into_owned()),
+            _ => None,
+        })
+        .collect()
+}
+
+// Comma-separated file names; at least one is required
+fn parse_index_files(value: &str) -> Option<Vec<String>> {
+    let names: Vec<String> = value.split(',').map(str::trim).filter(|name| !name.is_empty()).map(str::to_string).collect();
+    let valid = |name: &String| name != "." && name != ".." && !name.contains(['/', '\\']) && !name.chars().any(char::is_control);
+    (!names.is_empty() && names.iter().all(valid)).then_some(names)
+}
+
+fn parse_secs(value: &str) -> Option<Duration> {
+    value.parse::<u64>().ok().map(Duration::from_secs)
+}
@@ -0,0 +1,157 @@
+use std::path::Path;
+use std::sync::Arc;
+
+// An error KISS sends: its status line, headers it always carries besides the security
+// headers, whether the connection stays open, and the built-in plain-text body
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ErrorStatus {
+    pub code: u16,
+    pub status_line: &'static str,
+    pub extra_headers: &'static str,
+    pub connection: &'static str,
+    pub fallback: &'static str,
+}
+
+pub const ERROR_STATUSES: &[ErrorStatus] = &[
+    error(400, "400 Bad Request", "", "keep-alive", "Malformed request"),
+    error(401, "401 Unauthorized", "WWW-Authenticate: Bearer\r\n", "keep-alive", "Unauthorized"),
+    error(404, "404 Not Found", "", "keep-alive", "File not found"),
+    error(405, "405 Method Not Allowed", "", "keep-alive", "Method not allowed"),
+    error(408, "408 Request Timeout", "", "keep-alive", "Request timeout"),
+    error(412, "412 Precondition Failed", "", "keep-alive", "Precondition failed"),
+    error(413, "413 Content Too Large", "", "close", "Content too large"),
+    error(414, "414 URI Too Long", "", "close", "URI too long"),
+    error(431, "431 Request Header Fields Too Large", "", "close", "Request header fields too large"),
+];
+
+const fn error(
+    code: u16,
+    status_line: &'static str,
+    extra_headers: &'static str,
+    connection: &'static str,
+    fallback: &'static str,
+) -> ErrorStatus {
+    ErrorStatus { code, status_line, extra_headers, connection, fallback }
+}
+
+pub fn error_status(code: u16) -> Option<&'static ErrorStatus> {
+    ERROR_STATUSES.iter().find(|status| status.code == code)
+}
+
+// Pre-compiled error response, with and without its body for HEAD requests
+#[derive(Clone, Debug)]
+pub struct ErrorResponse {
+    pub status: u16,
+    pub complete: Arc<[u8]>,
+    pub headers_only: Arc<[u8]>,
+}
+
+impl ErrorResponse {
+    pub fn render(status: &ErrorStatus, content_type: &str, body: &[u8], security: &str) -> Self {
+        let headers_only = format!(
+            "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\n{}{}Connection: {}\r\n\r\n",
+            status.status_line,
+            content_type,
+            body.len(),
+            status.extra_headers,
+            security,
+            status.connection
+        )
+        .into_bytes();
+        let mut complete = headers_only.clone();
+        complete.extend_from_slice(body);
+        Self { status: status.code, complete: Arc::from(complete), headers_only: Arc::from(headers_only) }
+    }
+
+    // The plain-text response sent when the content root has no page for the status
+    pub fn builtin(status: &ErrorStatus, security: &str) -> Self {
+        Self::render(status, "text/plain", status.fallback.as_bytes(), security)
+    }
+
+    pub fn for_method(&self, is_head: bool) -> &Arc<[u8]> {
+        if is_head {
+            &self.headers_only
+        } else {
+            &self.complete
+        }
+    }
+}
+
+// Custom `<status>.html` pages from the content root, read with each cache generation so
+// edits take effect on the next reload. Statuses without a page aren't listed. Only pages
+// for statuses in `ERROR_STATUSES` are loaded - a `403.html` or `500.html` would never be
+// sent, so such files are only noted in `ignored` for a warning.
+#[derive(Clone, Debug, Default)]
+pub struct ErrorPages {
+    pages: Vec<ErrorResponse>,
+    ignored: Vec<u16>,
+}
+
+impl ErrorPages {
+    // `content_type` gives the Content-Type of a page from its path and content; pages
+    // that can't be read are reported and left to the built-in response
+    pub fn load(
+        static_dir: &Path,
+        security: &str,
+        content_type: impl Fn(&Path, &[u8]) -> String,
+        errors: &mut Vec<String>,
+    ) -> Self {
+        let mut pages = Vec::new();
+        for status in ERROR_STATUSES {
+            let path = static_dir.join(format!("{}.html", status.code));
+            if !path.is_file() {
+                continue;
+            }
+            match std::fs::read(&path) {
+                Ok(body) => pages.push(ErrorResponse::render(status, &content_type(&path, &body), &body, security)),
+                Err(e) => errors.push(format!("{}: {}", path.display(), e)),
+            }
+        }
+        Self { pages, ignored: ignored_pages(static_dir) }
+    }
+
+    pub fn get(&self, status: u16) -> Option<&ErrorResponse> {
+        self.pages.iter().find(|page| page.status == status)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pages.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.pages.len()
+    }
+
+    // Status codes that have a page, in ascending order
+    pub fn codes(&self) -> impl Iterator<Item = u16> + '_ {
+        self.pages.iter().map(|page| page.status)
+    }
+
+    // Status codes of `<status>.html` files the server never sends, in ascending order
+    pub fn ignored(&self) -> &[u16] {
+        &self.ignored
+    }
+}
+
+// `NNN.html` files in the content root named after an HTTP status without an entry in
+// `ERROR_STATUSES`
+fn ignored_pages(static_dir: &Path) -> Vec<u16> {
+    let entries = match std::fs::read_dir(static_dir) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+    let mut ignored: Vec<u16> = entries
+        .flatten()
+        .filter(|entry| entry.path().is_file())
+        .filter_map(|entry| {
+            let name = entry.file_name();
+            let code = name.to_str()?.strip_suffix(".html")?;
+            if code.len() != 3 || !code.bytes().all(|b| b.is_ascii_digit()) {
+                return None;
+            }
+            code.parse().ok().filter(|code| (100..600).contains(code) && error_status(*code).is_none())
+        })
+        .collect();
+    ignored.sort_unstable();
+    ignored
+}
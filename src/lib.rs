@@ -1,5 +1,27 @@
 use std::path::Path;
 
+pub mod access_log;
+pub mod cache;
+pub mod clock;
+pub mod config;
+pub mod digest;
+pub mod encoding;
+pub mod error_pages;
+pub mod metrics;
+pub mod mime;
+pub mod pattern;
+pub mod range;
+pub mod redirects;
+pub mod request;
+pub mod response;
+pub mod rules;
+pub mod security;
+pub mod sniff;
+pub mod spa;
+pub mod uri;
+pub mod url_style;
+pub mod watch;
+
 // Optimized MIME type system using enum indices instead of HashMap lookups
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum MimeType {
@@ -20,34 +42,154 @@ pub enum MimeType {
     Ttf = 14,
     Eot = 15,
     OctetStream = 16, // Default for unknown files
+    Wasm = 17,
+    WebManifest = 18,
+    JsonLd = 19,
+    GeoJson = 20,
+    Xhtml = 21,
+    Rss = 22,
+    Atom = 23,
+    Yaml = 24,
+    Toml = 25,
+    Csv = 26,
+    TabSeparated = 27,
+    Markdown = 28,
+    Calendar = 29,
+    WebVtt = 30,
+    Webp = 31,
+    Avif = 32,
+    Apng = 33,
+    Bmp = 34,
+    Tiff = 35,
+    Otf = 36,
+    Mp4 = 37,
+    Webm = 38,
+    OggVideo = 39,
+    QuickTime = 40,
+    MpegTs = 41,
+    Mp3 = 42,
+    OggAudio = 43,
+    Wav = 44,
+    Flac = 45,
+    Mp4Audio = 46,
+    Aac = 47,
+    WebmAudio = 48,
+    HlsPlaylist = 49,
+    DashManifest = 50,
+    GltfJson = 51,
+    GltfBinary = 52,
+    Zip = 53,
+    Gzip = 54,
+    Tar = 55,
+    Bzip2 = 56,
+    Xz = 57,
+    Zstd = 58,
+    SevenZip = 59,
+    Epub = 60,
 }
 
 impl MimeType {
     // Static array for O(1) lookup - much faster than HashMap
-    const MIME_STRINGS: [&'static str; 17] = [
-        "text/html; charset=utf-8",           // Html
-        "text/css; charset=utf-8",            // Css
-        "text/javascript; charset=utf-8",     // Javascript
-        "application/json; charset=utf-8",    // Json
-        "application/xml; charset=utf-8",     // Xml
-        "text/plain; charset=utf-8",          // PlainText
-        "image/x-icon",                       // Icon
-        "image/png",                          // Png
-        "image/jpeg",                         // Jpeg
-        "image/gif",                          // Gif
-        "image/svg+xml",                      // Svg
-        "application/pdf",                    // Pdf
-        "font/woff",                          // Woff
-        "font/woff2",                         // Woff2
-        "font/ttf",                           // Ttf
-        "application/vnd.ms-fontobject",      // Eot
-        "application/octet-stream",           // OctetStream
+    const MIME_STRINGS: [&'static str; 61] = [
+        "text/html; charset=utf-8",                  // Html
+        "text/css; charset=utf-8",                   // Css
+        "text/javascript; charset=utf-8",            // Javascript
+        "application/json; charset=utf-8",           // Json
+        "application/xml; charset=utf-8",            // Xml
+        "text/plain; charset=utf-8",                 // PlainText
+        "image/x-icon",                              // Icon
+        "image/png",                                 // Png
+        "image/jpeg",                                // Jpeg
+        "image/gif",                                 // Gif
+        "image/svg+xml",                             // Svg
+        "application/pdf",                           // Pdf
+        "font/woff",                                 // Woff
+        "font/woff2",                                // Woff2
+        "font/ttf",                                  // Ttf
+        "application/vnd.ms-fontobject",             // Eot
+        "application/octet-stream",                  // OctetStream
+        "application/wasm",                          // Wasm
+        "application/manifest+json; charset=utf-8",  // WebManifest
+        "application/ld+json; charset=utf-8",        // JsonLd
+        "application/geo+json; charset=utf-8",       // GeoJson
+        "application/xhtml+xml; charset=utf-8",      // Xhtml
+        "application/rss+xml; charset=utf-8",        // Rss
+        "application/atom+xml; charset=utf-8",       // Atom
+        "application/yaml; charset=utf-8",           // Yaml
+        "application/toml; charset=utf-8",           // Toml
+        "text/csv; charset=utf-8",                   // Csv
+        "text/tab-separated-values; charset=utf-8",  // TabSeparated
+        "text/markdown; charset=utf-8",              // Markdown
+        "text/calendar; charset=utf-8",              // Calendar
+        "text/vtt; charset=utf-8",                   // WebVtt
+        "image/webp",                                // Webp
+        "image/avif",                                // Avif
+        "image/apng",                                // Apng
+        "image/bmp",                                 // Bmp
+        "image/tiff",                                // Tiff
+        "font/otf",                                  // Otf
+        "video/mp4",                                 // Mp4
+        "video/webm",                                // Webm
+        "video/ogg",                                 // OggVideo
+        "video/quicktime",                           // QuickTime
+        "video/mp2t",                                // MpegTs
+        "audio/mpeg",                                // Mp3
+        "audio/ogg",                                 // OggAudio
+        "audio/wav",                                 // Wav
+        "audio/flac",                                // Flac
+        "audio/mp4",                                 // Mp4Audio
+        "audio/aac",                                 // Aac
+        "audio/webm",                                // WebmAudio
+        "application/vnd.apple.mpegurl",             // HlsPlaylist
+        "application/dash+xml",                      // DashManifest
+        "model/gltf+json",                           // GltfJson
+        "model/gltf-binary",                         // GltfBinary
+        "application/zip",                           // Zip
+        "application/gzip",                          // Gzip
+        "application/x-tar",                         // Tar
+        "application/x-bzip2",                       // Bzip2
+        "application/x-xz",                          // Xz
+        "application/zstd",                          // Zstd
+        "application/x-7z-compressed",               // SevenZip
+        "application/epub+zip",                      // Epub
     ];
     
     // Convert enum to MIME string - zero allocation, O(1) lookup
     pub fn as_str(self) -> &'static str {
         Self::MIME_STRINGS[self as usize]
     }
+
+    // Text-like types worth compressing - images, media, fonts, archives and PDFs are
+    // already compressed. WebAssembly modules shrink well too.
+    pub fn is_compressible(self) -> bool {
+        matches!(
+            self,
+            MimeType::Html
+                | MimeType::Css
+                | MimeType::Javascript
+                | MimeType::Json
+                | MimeType::Xml
+                | MimeType::PlainText
+                | MimeType::Svg
+                | MimeType::Wasm
+                | MimeType::WebManifest
+                | MimeType::JsonLd
+                | MimeType::GeoJson
+                | MimeType::Xhtml
+                | MimeType::Rss
+                | MimeType::Atom
+                | MimeType::Yaml
+                | MimeType::Toml
+                | MimeType::Csv
+                | MimeType::TabSeparated
+                | MimeType::Markdown
+                | MimeType::Calendar
+                | MimeType::WebVtt
+                | MimeType::HlsPlaylist
+                | MimeType::DashManifest
+                | MimeType::GltfJson
+        )
+    }
 }
 
 
@@ -58,13 +200,13 @@ pub fn get_mime_type_enum(file_path: &Path) -> MimeType {
         match extension.to_ascii_lowercase().as_str() {
             "html" | "htm" => MimeType::Html,
             "css" => MimeType::Css,
-            "js" => MimeType::Javascript,
-            "json" => MimeType::Json,
-            "xml" => MimeType::Xml,
-            "txt" => MimeType::PlainText,
+            "js" | "mjs" | "cjs" => MimeType::Javascript,
+            "json" | "map" => MimeType::Json,
+            "xml" | "xsl" => MimeType::Xml,
+            "txt" | "text" | "log" => MimeType::PlainText,
             "ico" => MimeType::Icon,
             "png" => MimeType::Png,
-            "jpg" | "jpeg" => MimeType::Jpeg,
+            "jpg" | "jpeg" | "jfif" => MimeType::Jpeg,
             "gif" => MimeType::Gif,
             "svg" => MimeType::Svg,
             "pdf" => MimeType::Pdf,
@@ -72,10 +214,53 @@ pub fn get_mime_type_enum(file_path: &Path) -> MimeType {
             "woff2" => MimeType::Woff2,
             "ttf" => MimeType::Ttf,
             "eot" => MimeType::Eot,
+            "wasm" => MimeType::Wasm,
+            "webmanifest" => MimeType::WebManifest,
+            "jsonld" => MimeType::JsonLd,
+            "geojson" => MimeType::GeoJson,
+            "xhtml" => MimeType::Xhtml,
+            "rss" => MimeType::Rss,
+            "atom" => MimeType::Atom,
+            "yaml" | "yml" => MimeType::Yaml,
+            "toml" => MimeType::Toml,
+            "csv" => MimeType::Csv,
+            "tsv" => MimeType::TabSeparated,
+            "md" | "markdown" => MimeType::Markdown,
+            "ics" => MimeType::Calendar,
+            "vtt" => MimeType::WebVtt,
+            "webp" => MimeType::Webp,
+            "avif" => MimeType::Avif,
+            "apng" => MimeType::Apng,
+            "bmp" => MimeType::Bmp,
+            "tif" | "tiff" => MimeType::Tiff,
+            "otf" => MimeType::Otf,
+            "mp4" | "m4v" => MimeType::Mp4,
+            "webm" => MimeType::Webm,
+            "ogv" => MimeType::OggVideo,
+            "mov" => MimeType::QuickTime,
+            "ts" => MimeType::MpegTs,
+            "mp3" => MimeType::Mp3,
+            "ogg" | "oga" | "opus" => MimeType::OggAudio,
+            "wav" => MimeType::Wav,
+            "flac" => MimeType::Flac,
+            "m4a" => MimeType::Mp4Audio,
+            "aac" => MimeType::Aac,
+            "weba" => MimeType::WebmAudio,
+            "m3u8" => MimeType::HlsPlaylist,
+            "mpd" => MimeType::DashManifest,
+            "gltf" => MimeType::GltfJson,
+            "glb" => MimeType::GltfBinary,
+            "zip" => MimeType::Zip,
+            "gz" | "tgz" => MimeType::Gzip,
+            "tar" => MimeType::Tar,
+            "bz2" => MimeType::Bzip2,
+            "xz" => MimeType::Xz,
+            "zst" => MimeType::Zstd,
+            "7z" => MimeType::SevenZip,
+            "epub" => MimeType::Epub,
             _ => MimeType::OctetStream,
         }
     } else {
         MimeType::OctetStream
     }
 }
-
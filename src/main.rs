@@ -1,185 +1,45 @@
-use rustc_hash::FxHashMap;
 use std::fs::{read_dir, metadata, read};
-use std::sync::atomic::{AtomicBool, AtomicPtr, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::time::SystemTime;
-use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::io::AsyncReadExt;
 use tokio::net::{TcpListener, TcpStream};
 use tokio::signal;
 use tokio::time::{timeout, Duration};
-use once_cell::sync::OnceCell;
-use std::sync::Arc;
+use once_cell::sync::{Lazy, OnceCell};
+use std::sync::{Arc, Mutex};
+use std::path::{Path, PathBuf};
+use rustc_hash::{FxHashMap, FxHashSet};
+use kiss::clock::Clock;
+use kiss::cache::{content_etag, CacheEntry, EncodedVariant, EtagMode, GenerationDiff, OptimizedCache, PathTrie, Precondition, Preconditions};
+use kiss::access_log::{AccessLogEntry, AccessLogFormat, AccessLogger};
+use kiss::config::{Config, ConfigError};
+use kiss::encoding::{precompress, ContentEncoding};
+use kiss::error_pages::{error_status, ErrorPages, ErrorResponse};
+use kiss::metrics::{CacheStats, Metrics};
 use kiss::get_mime_type_enum;
-
-const PORT: u16 = 8080;
-const MAX_REQUEST_SIZE: usize = 8192;
-const STATIC_DIR: &str = "./content";
-const CONNECTION_TIMEOUT_SECS: u64 = 30;
-const KEEPALIVE_TIMEOUT_SECS: u64 = 5;
+use kiss::mime::{ContentType, MimeTypes};
+use kiss::range::{if_range_matches, parse_range, ByteRange, RangeRequest};
+use kiss::redirects::{status_text, Redirects, Resolution, REDIRECTS_FILE};
+use kiss::request::{head_lines, HeadStatus, Rejection, RequestBuffer};
+use kiss::response::{Chunk, ResponseQueue, Sent};
+use kiss::rules::HeaderRules;
+use kiss::security::SecurityHeaders;
+use kiss::sniff::sniff;
+use kiss::spa::SpaFallback;
+use kiss::uri::{is_forbidden_byte, parse_target};
+use kiss::url_style::{Located, UrlStyle};
+use std::net::IpAddr;
+use std::time::Instant;
 
 static SHUTDOWN: AtomicBool = AtomicBool::new(false);
-
-// Memory-optimized cache entry - structured for optimal cache line efficiency
-#[derive(Clone, Debug)]
-#[repr(C)]
-struct CacheEntry {
-    // Hot path data first (most frequently accessed in request handling)
-    complete_response: Arc<[u8]>,     // 8 bytes (pointer)
-    headers_only: Arc<[u8]>,          // 8 bytes (pointer)
-    not_modified_response: Arc<[u8]>, // 8 bytes (pointer)
-    
-    // Frequently used for conditional requests
-    last_modified_timestamp: SystemTime, // 16 bytes (u64 + u32 typically)
-    etag: Arc<str>,                   // 8 bytes (pointer)
-    
-    // Total: 48 bytes - fits well within cache lines
-}
-
-// Compact trie for prefix matching - optimized for trailing slash handling
-#[derive(Debug, Clone)]
-struct PathTrie {
-    // Maps normalized paths to cache entries
-    exact_matches: FxHashMap<u32, CacheEntry>,
-    // Special handling for index files
-    index_entries: FxHashMap<u32, CacheEntry>, // Maps directory hash to index.html entry
-}
-
-impl PathTrie {
-    fn new() -> Self {
-        Self {
-            exact_matches: FxHashMap::default(),
-            index_entries: FxHashMap::default(),
-        }
-    }
-    
-    // Optimized path normalization with integrated query stripping
-    // Single-pass processing: query detection + hash computation + trailing slash handling
-    #[inline]
-    fn normalize_path_hash(path: &str) -> (u32, bool) {
-        const FNV_OFFSET_BASIS: u32 = 2166136261;
-        const FNV_PRIME: u32 = 16777619;
-        
-        let path_bytes = path.as_bytes();
-        let mut hash = FNV_OFFSET_BASIS;
-        let mut end_pos = path_bytes.len();
-        let mut is_directory_style = false;
-        
-        // Single pass: find query position and check for trailing slash
-        for (i, &byte) in path_bytes.iter().enumerate() {
-            if byte == b'?' {
-                end_pos = i; // Stop at query parameter
-                break;
-            }
-        }
-        
-        // Check for directory-style path (trailing slash before query)
-        if end_pos > 1 && path_bytes[end_pos - 1] == b'/' {
-            is_directory_style = true;
-            end_pos -= 1; // Remove trailing slash from hash computation
-        }
-        
-        // Hash the clean, normalized path portion
-        for &byte in &path_bytes[..end_pos] {
-            hash ^= byte as u32;
-            hash = hash.wrapping_mul(FNV_PRIME);
-        }
-        
-        (hash, is_directory_style)
-    }
-    
-    fn insert(&mut self, path: &str, entry: CacheEntry) {
-        let (path_hash, _is_directory_style) = Self::normalize_path_hash(path);
-        
-        // Always store in exact matches
-        self.exact_matches.insert(path_hash, entry.clone());
-        
-        // If this is an index.html file, also register it for directory-style access
-        if path.ends_with("/index.html") {
-            let dir_path = &path[..path.len()-11]; // Remove "/index.html"
-            let (dir_hash, _) = Self::normalize_path_hash(dir_path);
-            self.index_entries.insert(dir_hash, entry);
-        }
-    }
-    
-    fn get(&self, path: &str) -> Option<CacheEntry> {
-        let (path_hash, is_directory_style) = Self::normalize_path_hash(path);
-        
-        // First try exact match
-        if let Some(entry) = self.exact_matches.get(&path_hash) {
-            return Some(entry.clone());
-        }
-        
-        // For directory-style requests, try index.html
-        if is_directory_style || path == "/" {
-            if let Some(entry) = self.index_entries.get(&path_hash) {
-                return Some(entry.clone());
-            }
-        }
-        
-        None
-    }
-    
-    fn entry_count(&self) -> usize {
-        self.exact_matches.len()
-    }
-}
-
-// Lock-free cache with atomic RCU pattern
-#[derive(Debug)]
-struct OptimizedCache {
-    // Atomic pointer to current cache generation (lock-free reads)
-    current: AtomicPtr<CacheGeneration>,
-}
-
-#[derive(Debug)]
-struct CacheGeneration {
-    // PathTrie for efficient prefix matching and trailing slash handling
-    trie: PathTrie,
-}
-
-impl OptimizedCache {
-    fn new() -> Self {
-        let initial_cache = Box::into_raw(Box::new(CacheGeneration {
-            trie: PathTrie::new(),
-        }));
-        
-        Self {
-            current: AtomicPtr::new(initial_cache),
-        }
-    }
-    
-    // Lock-free read operation using atomic load with trie lookup
-    fn get(&self, path: &str) -> Option<CacheEntry> {
-        // Load the current cache pointer atomically
-        let cache_ptr = self.current.load(Ordering::Acquire);
-        
-        // SAFETY: The pointer is valid as long as we don't perform concurrent updates
-        // For a static file server, the cache is built once at startup
-        unsafe {
-            let cache = &*cache_ptr;
-            cache.trie.get(path)
-        }
-    }
-    
-    // Only used during initial cache building (single-threaded)
-    fn insert(&self, path: String, entry: CacheEntry) {
-        // Load current cache
-        let cache_ptr = self.current.load(Ordering::Acquire);
-        
-        // SAFETY: During cache building phase, this is single-threaded
-        unsafe {
-            let cache = &mut *(cache_ptr as *mut CacheGeneration);
-            cache.trie.insert(&path, entry);
-        }
-    }
-    
-    fn entry_count(&self) -> usize {
-        let cache_ptr = self.current.load(Ordering::Acquire);
-        unsafe {
-            let cache = &*cache_ptr;
-            cache.trie.entry_count()
-        }
-    }
-}
+// Wakes connections idling between keep-alive requests once draining starts
+static DRAIN: Lazy<tokio::sync::watch::Sender<bool>> = Lazy::new(|| tokio::sync::watch::channel(false).0);
+// Open client connections - shutdown waits for this to reach zero
+static ACTIVE_CONNECTIONS: AtomicUsize = AtomicUsize::new(0);
+// Signalled whenever the last open connection closes, so shutdown needn't poll the count
+static CONNECTIONS_CLOSED: Lazy<tokio::sync::Notify> = Lazy::new(tokio::sync::Notify::new);
+// Source of the `Date` header on every response, advanced once per second
+static CLOCK: Lazy<Clock> = Lazy::new(Clock::new);
 
 // Zero-I/O file metadata - everything preloaded in memory
 #[derive(Clone, Debug)]
@@ -191,52 +51,102 @@ struct FileMetadata {
     last_modified_timestamp: SystemTime, // For If-Modified-Since comparison
 }
 
-// Static storage for header templates and optimized file cache - initialized at startup
+impl FileMetadata {
+    // Convert to the shared, cheaply clonable cache representation
+    fn into_cache_entry(self, variants: Option<Arc<[EncodedVariant]>>) -> CacheEntry {
+        CacheEntry {
+            // Hot path data first for better cache locality
+            complete_response: Arc::from(self.complete_response.into_boxed_slice()),
+            headers_only: Arc::from(self.headers_only.into_boxed_slice()),
+            not_modified_response: Arc::from(self.not_modified_response.into_boxed_slice()),
+            last_modified_timestamp: self.last_modified_timestamp,
+            etag: Arc::from(self.etag.into_boxed_str()),
+            variants,
+        }
+    }
+}
+
+// Static storage for configuration, header templates and optimized file cache - initialized at startup
+static CONFIG: OnceCell<Config> = OnceCell::new();
 static HEADER_TEMPLATES: OnceCell<HeaderTemplates> = OnceCell::new();
 static FILE_CACHE: OnceCell<OptimizedCache> = OnceCell::new();
+static RELOAD_LOCK: Mutex<()> = Mutex::new(());
+static ACCESS_LOG: OnceCell<AccessLogger> = OnceCell::new();
+static METRICS: OnceCell<Metrics> = OnceCell::new();
+static HEADER_RULES: OnceCell<HeaderRules> = OnceCell::new();
+static MIME_TYPES: OnceCell<MimeTypes> = OnceCell::new();
+static SPA_FALLBACK: OnceCell<SpaFallback> = OnceCell::new();
+static URL_STYLE: OnceCell<UrlStyle> = OnceCell::new();
+
+// Freshness of cached files unless a header rule sets Cache-Control
+const DEFAULT_CACHE_CONTROL: &str = "public, max-age=3600";
 
 // Pre-compiled response templates split into headers and bodies for unified handling
 #[derive(Debug)]
 struct HeaderTemplates {
-    // Error responses (headers + body combined for simplicity since they're small)
-    not_found: Vec<u8>,
-    method_not_allowed: Vec<u8>,
-    request_too_large: Vec<u8>,
-    bad_request: Vec<u8>,
-    request_timeout: Vec<u8>,
+    // Built-in plain-text error responses, for statuses the content root has no
+    // `<status>.html` page for
+    not_found: ErrorResponse,
+    method_not_allowed: ErrorResponse,
+    uri_too_long: ErrorResponse,
+    header_fields_too_large: ErrorResponse,
+    content_too_large: ErrorResponse,
+    precondition_failed: ErrorResponse,
+    bad_request: ErrorResponse,
+    request_timeout: ErrorResponse,
+    unauthorized: ErrorResponse,
     
     // Health endpoint responses (unified single-write pattern)
     health_complete: Vec<u8>,
     health_headers_only: Vec<u8>,
     ready_complete: Vec<u8>,
     ready_headers_only: Vec<u8>,
+    draining_complete: Vec<u8>,
+    draining_headers_only: Vec<u8>,
+    
+    // Security headers for cached files, and as lines for responses built per request
+    security: SecurityHeaders,
+    security_headers: String,
 }
 
 impl HeaderTemplates {
-    fn new() -> Self {
-        let (health_complete, health_headers_only) = Self::create_health_response();
-        let (ready_complete, ready_headers_only) = Self::create_ready_response();
+    // Every template carries the same security headers
+    fn new(security_headers: SecurityHeaders) -> Self {
+        let rendered = security_headers.render();
+        let security = rendered.as_str();
+        let (health_complete, health_headers_only) = Self::create_health_response(security);
+        let (ready_complete, ready_headers_only) = Self::create_ready_response(security);
+        let (draining_complete, draining_headers_only) = Self::create_draining_response(security);
+        let text = |code| ErrorResponse::builtin(error_status(code).expect("known error status"), security);
         
         Self {
-            not_found: b"HTTP/1.1 404 Not Found\r\nContent-Type: text/plain\r\nContent-Length: 14\r\nX-Content-Type-Options: nosniff\r\nConnection: keep-alive\r\n\r\nFile not found".to_vec(),
-            method_not_allowed: b"HTTP/1.1 405 Method Not Allowed\r\nContent-Type: text/plain\r\nContent-Length: 18\r\nX-Content-Type-Options: nosniff\r\nConnection: keep-alive\r\n\r\nMethod not allowed".to_vec(),
-            request_too_large: b"HTTP/1.1 413 Request Entity Too Large\r\nContent-Type: text/plain\r\nContent-Length: 17\r\nX-Content-Type-Options: nosniff\r\nConnection: keep-alive\r\n\r\nRequest too large".to_vec(),
-            bad_request: b"HTTP/1.1 400 Bad Request\r\nContent-Type: text/plain\r\nContent-Length: 17\r\nX-Content-Type-Options: nosniff\r\nConnection: keep-alive\r\n\r\nMalformed request".to_vec(),
-            request_timeout: b"HTTP/1.1 408 Request Timeout\r\nContent-Type: text/plain\r\nContent-Length: 15\r\nX-Content-Type-Options: nosniff\r\nConnection: keep-alive\r\n\r\nRequest timeout".to_vec(),
+            not_found: text(404),
+            method_not_allowed: text(405),
+            uri_too_long: text(414),
+            precondition_failed: text(412),
+            content_too_large: text(413),
+            header_fields_too_large: text(431),
+            bad_request: text(400),
+            request_timeout: text(408),
+            unauthorized: text(401),
             
             health_complete,
             health_headers_only,
             ready_complete,
             ready_headers_only,
+            draining_complete,
+            draining_headers_only,
+            security: security_headers,
+            security_headers: rendered.clone(),
         }
     }
     
     
-    fn create_health_response() -> (Vec<u8>, Vec<u8>) {
+    fn create_health_response(security: &str) -> (Vec<u8>, Vec<u8>) {
         let body = br#"{"status":"healthy","timestamp":"0"}"#;
         let headers = format!(
-            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nX-Content-Type-Options: nosniff\r\nConnection: keep-alive\r\n\r\n",
-            body.len()
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n{}Connection: keep-alive\r\n\r\n",
+            body.len(), security
         ).into_bytes();
         
         // Create unified single-write responses
@@ -247,11 +157,11 @@ impl HeaderTemplates {
         (complete_response, headers)
     }
     
-    fn create_ready_response() -> (Vec<u8>, Vec<u8>) {
+    fn create_ready_response(security: &str) -> (Vec<u8>, Vec<u8>) {
         let body = br#"{"status":"ready","timestamp":"0"}"#;
         let headers = format!(
-            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nX-Content-Type-Options: nosniff\r\nConnection: keep-alive\r\n\r\n",
-            body.len()
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n{}Connection: keep-alive\r\n\r\n",
+            body.len(), security
         ).into_bytes();
         
         // Create unified single-write responses
@@ -261,6 +171,21 @@ impl HeaderTemplates {
         
         (complete_response, headers)
     }
+    
+    // Readiness once shutdown has begun - load balancers stop routing here
+    fn create_draining_response(security: &str) -> (Vec<u8>, Vec<u8>) {
+        let body = br#"{"status":"draining"}"#;
+        let headers = format!(
+            "HTTP/1.1 503 Service Unavailable\r\nContent-Type: application/json\r\nContent-Length: {}\r\nCache-Control: no-store\r\n{}Connection: close\r\n\r\n",
+            body.len(), security
+        ).into_bytes();
+        
+        let mut complete_response = Vec::with_capacity(headers.len() + body.len());
+        complete_response.extend_from_slice(&headers);
+        complete_response.extend_from_slice(body);
+        
+        (complete_response, headers)
+    }
 }
 
 // Optimized case-insensitive ASCII comparison using SIMD-friendly approach
@@ -273,7 +198,7 @@ fn header_starts_with(header_line: &[u8], prefix: &[u8]) -> bool {
     for i in 0..prefix.len() {
         let h = header_line[i];
         let p = prefix[i];
-        if h != p && h.to_ascii_lowercase() != p.to_ascii_lowercase() {
+        if h != p && !h.eq_ignore_ascii_case(&p) {
             return false;
         }
     }
@@ -305,7 +230,7 @@ fn header_contains(header_line: &[u8], substring: &[u8]) -> bool {
         for j in 1..substring.len() {
             let h = header_line[i + j];
             let s = substring[j];
-            if h != s && h.to_ascii_lowercase() != s.to_ascii_lowercase() {
+            if h != s && !h.eq_ignore_ascii_case(&s) {
                 matches = false;
                 break;
             }
@@ -319,19 +244,6 @@ fn header_contains(header_line: &[u8], substring: &[u8]) -> bool {
     false
 }
 
-// Helper function to read a line into a byte buffer
-async fn read_line_bytes(reader: &mut BufReader<&mut TcpStream>, buffer: &mut Vec<u8>) -> Result<usize, std::io::Error> {
-    let mut total_bytes = 0;
-    loop {
-        let bytes_read = reader.read_until(b'\n', buffer).await?;
-        total_bytes += bytes_read;
-        if bytes_read == 0 || buffer.ends_with(b"\n") {
-            break;
-        }
-    }
-    Ok(total_bytes)
-}
-
 // Fast header line trimming
 fn trim_header_line(line: &[u8]) -> &[u8] {
     let mut start = 0;
@@ -395,30 +307,159 @@ fn parse_request_line_fast(request: &[u8]) -> Option<(&[u8], &str, &str)> {
     let path = std::str::from_utf8(path_bytes).ok()?;
     let version = std::str::from_utf8(version_bytes).ok()?;
     
-    // Basic validation
-    if method.is_empty() || path.is_empty() || version.is_empty() {
+    // Basic validation - the target splits on spaces only, so any other control byte
+    // still in it is refused here, for every route
+    if method.is_empty() || path.is_empty() || version.is_empty() || path.bytes().any(is_forbidden_byte) {
         return None;
     }
     
     Some((method, path, version))
 }
 
-fn build_file_cache() -> OptimizedCache {
-    let cache = OptimizedCache::new();
+fn build_file_cache(static_dir: &Path) -> OptimizedCache {
+    let (trie, errors) = build_path_trie(static_dir);
+    for error in &errors {
+        eprintln!("Warning: {}", error);
+    }
+    OptimizedCache::from_trie(trie)
+}
+
+// Build a complete, immutable cache generation - used at startup and for every reload
+// Returns the trie along with files or directories that could not be cached
+fn build_path_trie(static_dir: &Path) -> (PathTrie, Vec<String>) {
+    let mut trie = CONFIG.get().map_or_else(PathTrie::new, |config| PathTrie::with_index_files(config.index_files.clone()));
+    let mut errors = Vec::new();
+    let mut files = Vec::new();
+    
+    if let Err(e) = discover_files_recursive(&static_dir.to_string_lossy(), "", &mut files, &mut errors) {
+        errors.push(format!("Failed to build file cache: {}", e));
+    }
+    insert_discovered_files(&files, &mut trie, &mut errors);
+    trie.set_redirects(load_redirects(static_dir, &mut errors));
+    trie.set_error_pages(load_error_pages(static_dir, &mut errors));
     
-    if let Err(e) = discover_files_recursive(STATIC_DIR, "", &cache) {
-        eprintln!("Warning: Failed to build file cache: {}", e);
+    // Colliding paths are still served correctly, but surface them so they can be investigated
+    let collisions = trie.collisions();
+    for collision in collisions {
+        eprintln!(
+            "Warning: path hash collision {:#010x} between {} and {}",
+            collision.hash, collision.existing, collision.inserted
+        );
+    }
+    
+    let entry_count = trie.entry_count();
+    println!("Optimized file cache built with {} entries ({} hash collisions)", entry_count, collisions.len());
+    (trie, errors)
+}
+
+// `_redirects` from the content root, with fixed redirects pre-rendered. Bad lines are
+// reported and skipped.
+fn load_redirects(static_dir: &Path, errors: &mut Vec<String>) -> Redirects {
+    let path = static_dir.join(REDIRECTS_FILE);
+    let text = match std::fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Redirects::default(),
+        Err(e) => {
+            errors.push(format!("{}: {}", path.display(), e));
+            return Redirects::default();
+        }
+    };
+    let (mut redirects, rule_errors) = Redirects::parse(&text);
+    for error in rule_errors {
+        errors.push(format!("{}: {}", path.display(), error));
+    }
+    redirects.prebuild(redirect_response);
+    println!("Loaded {} redirect rules from {}", redirects.len(), REDIRECTS_FILE);
+    redirects
+}
+
+// `<status>.html` error pages from the content root, rendered with the security headers
+// and a Content-Type from the usual MIME lookup and charset detection
+fn load_error_pages(static_dir: &Path, errors: &mut Vec<String>) -> ErrorPages {
+    let security = HEADER_TEMPLATES.get().map_or("", |templates| templates.security_headers.as_str());
+    let content_type = |path: &Path, body: &[u8]| content_type_of(path).with_charset_of(body).as_str().to_string();
+    let pages = ErrorPages::load(static_dir, security, content_type, errors);
+    if !pages.is_empty() {
+        let names: Vec<String> = pages.codes().map(|code| format!("{}.html", code)).collect();
+        println!("Using custom error pages: {}", names.join(", "));
+    }
+    if !pages.ignored().is_empty() {
+        let names: Vec<String> = pages.ignored().iter().map(|code| format!("{}.html", code)).collect();
+        eprintln!("Warning: Ignoring {} - KISS never sends these statuses", names.join(", "));
+    }
+    pages
+}
+
+// Outcome of a cache rebuild - logged and returned by the admin endpoint
+struct ReloadReport {
+    entries: usize,
+    diff: GenerationDiff,
+    errors: Vec<String>,
+}
+
+impl ReloadReport {
+    fn log(&self, trigger: &str) {
+        println!(
+            "Cache reload ({}): {} entries, {} added, {} removed, {} changed, {} errors",
+            trigger, self.entries, self.diff.added, self.diff.removed, self.diff.changed, self.errors.len()
+        );
+        for error in &self.errors {
+            eprintln!("Warning: {}", error);
+        }
+    }
+    
+    fn to_json(&self) -> String {
+        let errors: Vec<String> = self.errors.iter().map(|e| json_string(e)).collect();
+        format!(
+            r#"{{"status":"reloaded","entries":{},"added":{},"removed":{},"changed":{},"errors":[{}]}}"#,
+            self.entries, self.diff.added, self.diff.removed, self.diff.changed, errors.join(",")
+        )
+    }
+}
+
+// Minimal JSON string encoding for report fields (paths and OS error messages)
+fn json_string(value: &str) -> String {
+    let mut out = String::with_capacity(value.len() + 2);
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
     }
+    out.push('"');
+    out
+}
+
+// Rebuild off the request path, then publish atomically; the old generation is
+// freed once requests still reading it have finished. Reloads from the watcher,
+// SIGHUP and the admin endpoint are serialized so each report diffs against its predecessor.
+fn reload_file_cache(static_dir: &Path, trigger: &str) -> ReloadReport {
+    let _reload = RELOAD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    
+    let (trie, errors) = build_path_trie(static_dir);
+    let entries = trie.entry_count();
+    let diff = FILE_CACHE.get().unwrap().replace(trie);
     
-    let entry_count = cache.entry_count();
-    println!("Optimized file cache built with {} entries", entry_count);
-    cache
+    let report = ReloadReport { entries, diff, errors };
+    report.log(trigger);
+    report
+}
+
+// A regular file under the content root, collected before cache entries are built
+// so precompressed sidecars can be matched with their base file
+struct DiscoveredFile {
+    url_path: String,
+    fs_path: PathBuf,
 }
 
 fn discover_files_recursive(
     base_dir: &str,
     relative_path: &str,
-    cache: &OptimizedCache,
+    files: &mut Vec<DiscoveredFile>,
+    errors: &mut Vec<String>,
 ) -> Result<(), Box<dyn std::error::Error>> {
     // Optimized path construction using pre-allocated capacity
     let mut full_path = String::with_capacity(base_dir.len() + relative_path.len() + 1);
@@ -432,11 +473,33 @@ fn discover_files_recursive(
     
     for entry in entries {
         let entry = entry?;
-        let metadata = entry.metadata()?;
         
-        // Use OsStr to avoid unnecessary UTF-8 conversion until needed
+        // URLs name files by their UTF-8 spelling - other names could never be requested
         let file_name_os = entry.file_name();
-        let file_name = file_name_os.to_string_lossy();
+        let Some(file_name) = file_name_os.to_str() else {
+            errors.push(format!("{}: file name is not valid UTF-8", entry.path().display()));
+            continue;
+        };
+        
+        // Skip Kubernetes atomic-writer internals (`..data`, `..2024_01_01_...`) - the
+        // visible names are symlinks into them
+        if file_name.starts_with("..") {
+            continue;
+        }
+        
+        // Redirect rules are configuration, not content
+        if relative_path.is_empty() && file_name == REDIRECTS_FILE {
+            continue;
+        }
+        
+        // Follow symlinks to files (ConfigMap/Secret volumes), but never symlinked directories
+        let mut metadata = entry.metadata()?;
+        if metadata.file_type().is_symlink() {
+            match std::fs::metadata(entry.path()) {
+                Ok(target) if target.is_file() => metadata = target,
+                _ => continue,
+            }
+        }
         
         // Optimized path joining - pre-allocate with capacity
         let current_relative = if relative_path.is_empty() {
@@ -445,43 +508,124 @@ fn discover_files_recursive(
             let mut path = String::with_capacity(relative_path.len() + file_name.len() + 1);
             path.push_str(relative_path);
             path.push('/');
-            path.push_str(&file_name);
+            path.push_str(file_name);
             path
         };
         
         if metadata.is_file() {
-            // Generate cache entry for this file
-            if let Ok(file_metadata) = generate_file_metadata(&entry.path(), &current_relative) {
-                // Optimized URL path construction
-                let mut url_path = String::with_capacity(current_relative.len() + 1);
-                url_path.push('/');
-                url_path.push_str(&current_relative);
-                
-                // Convert FileMetadata to optimized CacheEntry
-                let cache_entry = CacheEntry {
-                    // Hot path data first for better cache locality
-                    complete_response: Arc::from(file_metadata.complete_response.into_boxed_slice()),
-                    headers_only: Arc::from(file_metadata.headers_only.into_boxed_slice()),
-                    not_modified_response: Arc::from(file_metadata.not_modified_response.into_boxed_slice()),
-                    last_modified_timestamp: file_metadata.last_modified_timestamp,
-                    etag: Arc::from(file_metadata.etag.into_boxed_str()),
-                };
-                
-                // Cache entry - trie automatically handles trailing slashes and index.html mapping
-                cache.insert(url_path, cache_entry);
-            }
+            // Optimized URL path construction
+            let mut url_path = String::with_capacity(current_relative.len() + 1);
+            url_path.push('/');
+            url_path.push_str(&current_relative);
+            
+            files.push(DiscoveredFile { url_path, fs_path: entry.path() });
         } else if metadata.is_dir() {
-            // Recursively process directories
-            discover_files_recursive(base_dir, &current_relative, cache)?;
+            // Recursively process directories - an unreadable directory doesn't abort the build
+            if let Err(e) = discover_files_recursive(base_dir, &current_relative, files, errors) {
+                errors.push(format!("{}: {}", entry.path().display(), e));
+            }
         }
     }
     
     Ok(())
 }
 
-fn generate_file_metadata(file_path: &std::path::Path, _relative_path: &str) -> Result<FileMetadata, Box<dyn std::error::Error>> {
+// Build cache entries for discovered files. `app.js.br`, `app.js.zst` and `app.js.gz` next
+// to `app.js` become encoding variants of `/app.js`; sidecars without a base file are
+// served as ordinary files. With --compress, missing gzip/brotli variants of text
+// assets are generated here so the request path stays a plain lookup.
+fn insert_discovered_files(files: &[DiscoveredFile], trie: &mut PathTrie, errors: &mut Vec<String>) {
+    let compress = CONFIG.get().is_some_and(|config| config.compress);
+    let sniff_content = CONFIG.get().is_some_and(|config| config.sniff);
+    let url_paths: FxHashSet<&str> = files.iter().map(|file| file.url_path.as_str()).collect();
+    
+    let mut sidecars: FxHashMap<&str, Vec<(ContentEncoding, &Path)>> = FxHashMap::default();
+    let mut grouped: FxHashSet<&str> = FxHashSet::default();
+    for file in files {
+        if let Some((base, encoding)) = ContentEncoding::strip_sidecar_extension(&file.url_path) {
+            if url_paths.contains(base) {
+                sidecars.entry(base).or_default().push((encoding, &file.fs_path));
+                grouped.insert(&file.url_path);
+            }
+        }
+    }
+    
+    for file in files {
+        if grouped.contains(file.url_path.as_str()) {
+            continue;
+        }
+        
+        let identity = match load_file(&file.fs_path) {
+            Ok(identity) => identity,
+            Err(e) => {
+                errors.push(format!("{}: {}", file.fs_path.display(), e));
+                continue;
+            }
+        };
+        
+        // Sidecars from the build pipeline take precedence over generated variants
+        let mut encoded: Vec<(ContentEncoding, LoadedFile)> = Vec::new();
+        for &(encoding, sidecar_path) in sidecars.get(file.url_path.as_str()).into_iter().flatten() {
+            match load_file(sidecar_path) {
+                Ok(sidecar) => encoded.push((encoding, sidecar)),
+                Err(e) => errors.push(format!("{}: {}", sidecar_path.display(), e)),
+            }
+        }
+        
+        // Variants inherit the base file's type and charset - `app.js.br` is still JavaScript
+        let content_type = content_type_of(&file.fs_path);
+        let content_type = if sniff_content && file.fs_path.extension().is_none() {
+            sniff(&identity.content).map_or(content_type, ContentType::from)
+        } else {
+            content_type
+        };
+        let content_type = content_type.with_charset_of(&identity.content);
+        if compress && content_type.is_compressible() {
+            for encoding in [ContentEncoding::Brotli, ContentEncoding::Gzip] {
+                if encoded.iter().any(|&(existing, _)| existing == encoding) {
+                    continue;
+                }
+                // Kept only when it actually saves space
+                if let Some(content) = precompress(&identity.content, encoding) {
+                    encoded.push((encoding, LoadedFile { content, last_modified: identity.last_modified }));
+                }
+            }
+        }
+        encoded.sort_by_key(|&(encoding, _)| ContentEncoding::ALL.iter().position(|&e| e == encoding));
+        
+        let vary = !encoded.is_empty();
+        let variants: Vec<EncodedVariant> = encoded
+            .iter()
+            .map(|(encoding, loaded)| EncodedVariant {
+                encoding: *encoding,
+                entry: generate_file_metadata(loaded, &file.url_path, &content_type, Some(*encoding), vary).into_cache_entry(None),
+            })
+            .collect();
+        let variants = if variants.is_empty() { None } else { Some(Arc::from(variants)) };
+        
+        // Cache entry - trie automatically handles trailing slashes and index.html mapping
+        let file_metadata = generate_file_metadata(&identity, &file.url_path, &content_type, None, vary);
+        trie.insert(&file.url_path, file_metadata.into_cache_entry(variants));
+    }
+}
+
+// Type from the file name: configured mappings first, then the built-in table
+fn content_type_of(file_path: &Path) -> ContentType {
+    match MIME_TYPES.get() {
+        Some(types) => types.lookup(file_path),
+        None => ContentType::from(get_mime_type_enum(file_path)),
+    }
+}
+
+// File content and its second-precision modification time (or SOURCE_DATE_EPOCH)
+struct LoadedFile {
+    content: Vec<u8>,
+    last_modified: SystemTime,
+}
+
+// ZERO-I/O OPTIMIZATION: Pre-load file content into memory
+fn load_file(file_path: &Path) -> Result<LoadedFile, Box<dyn std::error::Error>> {
     let file_metadata = metadata(file_path)?;
-    let size = file_metadata.len();
     let last_modified_raw = file_metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
     // Truncate to second precision during cache building for HTTP compliance
     let last_modified = {
@@ -491,348 +635,1109 @@ fn generate_file_metadata(file_path: &std::path::Path, _relative_path: &str) ->
         SystemTime::UNIX_EPOCH + seconds_only
     };
     
-    // Generate weak ETag using size and modification time
-    let mtime_secs = last_modified
-        .duration_since(SystemTime::UNIX_EPOCH)
-        .unwrap_or(Duration::from_secs(0))
-        .as_secs();
-    let etag = format!("W/\"{}-{}\"", size, mtime_secs);
+    // Reproducible builds pin every file to the source date, whatever the image's mtimes
+    let last_modified = CONFIG.get().and_then(|config| config.source_date_epoch).unwrap_or(last_modified);
     
-    // Get MIME type using fast enum lookup during cache building
-    let mime_type_enum = get_mime_type_enum(file_path);
-    let mime_type_str = mime_type_enum.as_str();
+    let content = read(file_path)?;
+    Ok(LoadedFile { content, last_modified })
+}
+
+// `encoding` labels the bytes as a compressed variant; `vary` is set on every
+// representation of a resource that has variants, identity included. Header rules
+// matching `url_path` are applied here, once per cache build.
+fn generate_file_metadata(
+    file: &LoadedFile,
+    url_path: &str,
+    content_type: &ContentType,
+    encoding: Option<ContentEncoding>,
+    vary: bool,
+) -> FileMetadata {
+    let content = &file.content;
+    let last_modified = file.last_modified;
+    let actual_size = content.len();
+    
+    // Weak ETags use size and modification time - variants carry their coding so no two
+    // representations ever share a validator. Strong ETags hash the bytes actually sent,
+    // which differ between representations anyway.
+    let etag_mode = CONFIG.get().map_or(EtagMode::Weak, |config| config.etag);
+    let etag = match (etag_mode, encoding) {
+        (EtagMode::Strong, _) => content_etag(content),
+        (EtagMode::Weak, encoding) => {
+            let mtime_secs = last_modified
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap_or(Duration::from_secs(0))
+                .as_secs();
+            match encoding {
+                Some(encoding) => format!("W/\"{}-{}-{}\"", actual_size, mtime_secs, encoding.as_str()),
+                None => format!("W/\"{}-{}\"", actual_size, mtime_secs),
+            }
+        }
+    };
+    
+    let mime_type_str = content_type.as_str();
+    
+    // Negotiation headers, empty for the common single-representation case
+    let mut negotiation = String::new();
+    if let Some(encoding) = encoding {
+        negotiation.push_str("Content-Encoding: ");
+        negotiation.push_str(encoding.as_str());
+        negotiation.push_str("\r\n");
+    }
+    if vary {
+        negotiation.push_str("Vary: Accept-Encoding\r\n");
+    }
     
     // Format HTTP date once during cache building - RFC 7231 compliant
     let last_modified_str = httpdate::fmt_http_date(last_modified);
     
-    // ZERO-I/O OPTIMIZATION: Pre-load file content into memory
-    let content = read(file_path)?;
-    let actual_size = content.len();
+    // Rule headers replace the default Cache-Control or a security header of the same
+    // name, and other custom headers follow the security ones
+    let rule_headers = HEADER_RULES.get().map(|rules| rules.headers_for(url_path)).unwrap_or_default();
+    let mut cache_control = DEFAULT_CACHE_CONTROL;
+    let mut security: Vec<(&str, &str)> = HEADER_TEMPLATES.get().map(|templates| templates.security.iter().collect()).unwrap_or_default();
+    let mut custom: Vec<(&str, &str)> = Vec::new();
+    for (name, value) in rule_headers {
+        if name.eq_ignore_ascii_case("cache-control") {
+            cache_control = value;
+        } else if let Some(header) = security.iter_mut().find(|(existing, _)| existing.eq_ignore_ascii_case(name)) {
+            header.1 = value;
+        } else {
+            custom.push((name, value));
+        }
+    }
+    let header_lines = |headers: &[(&str, &str)]| -> String {
+        headers.iter().map(|(name, value)| format!("{}: {}\r\n", name, value)).collect()
+    };
+    let security_headers = header_lines(&security);
     
     // Pre-generate complete HTTP headers
     let headers = format!(
-        "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nContent-Length: {}\r\nLast-Modified: {}\r\nETag: {}\r\nCache-Control: public, max-age=3600\r\nX-Content-Type-Options: nosniff\r\nConnection: keep-alive\r\n\r\n",
-        mime_type_str, actual_size, last_modified_str, etag
+        "HTTP/1.1 200 OK\r\nContent-Type: {}\r\n{}Content-Length: {}\r\nLast-Modified: {}\r\nETag: {}\r\nCache-Control: {}\r\nAccept-Ranges: bytes\r\n{}{}Connection: keep-alive\r\n\r\n",
+        mime_type_str, negotiation, actual_size, last_modified_str, etag, cache_control, security_headers, header_lines(&custom)
     ).into_bytes();
     
     // Pre-generate headers-only response for HEAD requests
-    let headers_only = format!(
-        "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nContent-Length: {}\r\nLast-Modified: {}\r\nETag: {}\r\nCache-Control: public, max-age=3600\r\nX-Content-Type-Options: nosniff\r\nConnection: keep-alive\r\n\r\n",
-        mime_type_str, actual_size, last_modified_str, etag
-    ).into_bytes();
+    let headers_only = headers.clone();
     
     // Pre-combine headers + content for single write()
     let mut complete_response = Vec::with_capacity(headers.len() + content.len());
     complete_response.extend_from_slice(&headers);
-    complete_response.extend_from_slice(&content);
+    complete_response.extend_from_slice(content);
     
-    // Pre-generate custom 304 Not Modified response with file-specific ETag
+    // Pre-generate custom 304 Not Modified response with file-specific ETag. It repeats
+    // Cache-Control and Expires (RFC 9110 15.4.5) and the security headers.
+    let vary_header = if vary { "Vary: Accept-Encoding\r\n" } else { "" };
+    let expires: Vec<(&str, &str)> = custom.iter().copied().filter(|(name, _)| name.eq_ignore_ascii_case("expires")).collect();
     let not_modified_response = format!(
-        "HTTP/1.1 304 Not Modified\r\nETag: {}\r\nCache-Control: public, max-age=3600\r\nConnection: keep-alive\r\n\r\n",
-        etag
+        "HTTP/1.1 304 Not Modified\r\nETag: {}\r\nCache-Control: {}\r\n{}{}{}Connection: keep-alive\r\n\r\n",
+        etag, cache_control, header_lines(&expires), vary_header, security_headers
     ).into_bytes();
     
-    Ok(FileMetadata {
+    FileMetadata {
         complete_response,
         headers_only,
         not_modified_response,
         etag,
         last_modified_timestamp: last_modified,
-    })
+    }
 }
 
 
 #[tokio::main]
 async fn main() {
+    // Resolve and validate configuration before touching the filesystem or network
+    let config = match Config::from_env_and_args() {
+        Ok(config) => config,
+        Err(ConfigError::HelpRequested) => {
+            print!("{}", Config::usage());
+            return;
+        }
+        Err(e) => {
+            eprintln!("Error: {}\n\n{}", e, Config::usage());
+            std::process::exit(2);
+        }
+    };
+    let config = CONFIG.get_or_init(|| config);
+
+    if config.access_log != AccessLogFormat::Off {
+        match AccessLogger::spawn(config.access_log, config.access_log_sample) {
+            Ok(logger) => {
+                let _ = ACCESS_LOG.set(logger);
+            }
+            Err(e) => eprintln!("Warning: Failed to start access log: {}", e),
+        }
+    }
+    
+    if config.metrics || config.metrics_port.is_some() {
+        let _ = METRICS.set(Metrics::new());
+    }
+    
+    // Rules are part of the configuration: a broken file stops startup like a bad flag
+    if let Some(path) = &config.header_rules {
+        match HeaderRules::load(path) {
+            Ok(rules) => {
+                println!("Loaded {} header rule(s) from {}", rules.len(), path.display());
+                let _ = HEADER_RULES.set(rules);
+            }
+            Err(e) => {
+                eprintln!("Error: header rules {}: {}", path.display(), e);
+                std::process::exit(2);
+            }
+        }
+    }
+    
+    if let Some(fallback) = config.spa_fallback() {
+        let _ = SPA_FALLBACK.set(fallback);
+    }
+    let _ = URL_STYLE.set(config.url_style());
+    
+    // Built-in types, then the mime.types file, then single overrides
+    if config.mime_types.is_some() || !config.mime_overrides.is_empty() {
+        let mut types = match &config.mime_types {
+            Some(path) => match MimeTypes::load(path) {
+                Ok(types) => {
+                    println!("Loaded {} MIME type mapping(s) from {}", types.len(), path.display());
+                    types
+                }
+                Err(e) => {
+                    eprintln!("Error: mime types {}: {}", path.display(), e);
+                    std::process::exit(2);
+                }
+            },
+            None => MimeTypes::default(),
+        };
+        for (extension, media_type) in &config.mime_overrides {
+            // Already validated with the rest of the configuration
+            let _ = types.insert(extension, media_type);
+        }
+        let _ = MIME_TYPES.set(types);
+    }
+    
     // Initialize header templates and file cache at startup - not on first request
-    HEADER_TEMPLATES.set(HeaderTemplates::new())
+    HEADER_TEMPLATES.set(HeaderTemplates::new(config.security_headers()))
         .expect("Failed to initialize header templates");
     
-    let cache = build_file_cache();
+    tokio::spawn(tick_clock());
+    
+    let cache = build_file_cache(&config.static_dir);
     FILE_CACHE.set(cache)
         .expect("Failed to initialize file cache");
+    if let Some(fallback) = SPA_FALLBACK.get() {
+        if FILE_CACHE.get().unwrap().lookup(&fallback.document).is_none() {
+            eprintln!("Warning: SPA fallback {} is not in the content directory", fallback.document);
+        }
+    }
+    
+    if config.watch {
+        let static_dir = config.static_dir.clone();
+        let watcher = kiss::watch::spawn_watcher(config.static_dir.clone(), config.watch_debounce, move || {
+            reload_file_cache(&static_dir, "watch");
+        });
+        match watcher {
+            Ok(_) => println!("Watching {} for changes", config.static_dir.display()),
+            Err(e) => eprintln!("Warning: Failed to watch content directory: {}", e),
+        }
+    }
 
-    let listener = TcpListener::bind(format!("0.0.0.0:{}", PORT))
+    // Listen for the shutdown signal before accepting anything, and for as long as the
+    // accept loop runs
+    let shutdown = shutdown_signal();
+    tokio::pin!(shutdown);
+
+    let listener = TcpListener::bind((config.bind_address, config.port))
         .await
         .expect("Failed to bind to address");
 
-    println!("Async KISS server running on http://0.0.0.0:{}", PORT);
+    println!("Async KISS server running on http://{}:{}", config.bind_address, config.port);
+
+    if let Some(metrics_port) = config.metrics_port {
+        let metrics_listener = TcpListener::bind((config.bind_address, metrics_port))
+            .await
+            .expect("Failed to bind metrics port");
+        println!("Metrics available on http://{}:{}/metrics", config.bind_address, metrics_port);
+        tokio::spawn(serve_metrics(metrics_listener));
+    }
+
+    let mut reload_requests = reload_signal();
 
     loop {
         tokio::select! {
-            result = listener.accept() => {
-                match result {
-                    Ok((stream, _)) => {
-                        // Configure TCP socket for performance
-                        let _ = stream.set_nodelay(true);
-                        tokio::spawn(handle_connection(stream));
-                    }
-                    Err(_) => continue,
-                }
+            result = listener.accept() => accept_connection(result),
+            Some(()) = reload_requests.recv() => {
+                let static_dir = config.static_dir.clone();
+                tokio::task::spawn_blocking(move || reload_file_cache(&static_dir, "SIGHUP"));
             }
-            _ = shutdown_signal() => {
-                println!("Shutdown signal received, stopping server...");
-                SHUTDOWN.store(true, Ordering::Relaxed);
-                break;
+            _ = &mut shutdown => break,
+        }
+    }
+
+    // Draining: /ready turns 503, responses carry `Connection: close` and idle
+    // keep-alive connections are closed
+    println!("Shutdown signal received, draining connections...");
+    SHUTDOWN.store(true, Ordering::Relaxed);
+    DRAIN.send_replace(true);
+
+    // A second signal skips the remaining drain
+    let second_signal = shutdown_signal();
+    let forced = async {
+        second_signal.await;
+        println!("Second shutdown signal received, exiting immediately");
+    };
+    tokio::pin!(forced);
+
+    // Pre-stop delay: keep accepting while load balancers notice the failing readiness
+    // probe and remove this instance
+    if !config.shutdown_delay.is_zero() {
+        let delay = tokio::time::sleep(config.shutdown_delay);
+        tokio::pin!(delay);
+        loop {
+            tokio::select! {
+                result = listener.accept() => accept_connection(result),
+                _ = &mut delay => break,
+                _ = &mut forced => std::process::exit(1),
+            }
+        }
+    }
+    drop(listener);
+
+    tokio::select! {
+        remaining = wait_for_connections(config.shutdown_timeout) => {
+            if remaining > 0 {
+                println!("Shutdown timeout reached with {} connection(s) still open", remaining);
+            } else {
+                println!("All connections drained");
             }
         }
+        _ = &mut forced => std::process::exit(1),
     }
 
     println!("Server shutdown complete");
 }
 
-async fn shutdown_signal() {
-    let ctrl_c = async {
-        signal::ctrl_c()
-            .await
-            .expect("failed to install Ctrl+C handler");
+// Keep the shared clock current, ticking just after each whole second
+async fn tick_clock() {
+    loop {
+        tokio::time::sleep(Clock::until_next_tick()).await;
+        CLOCK.tick();
+    }
+}
+
+fn accept_connection(result: std::io::Result<(TcpStream, std::net::SocketAddr)>) {
+    if let Ok((stream, peer)) = result {
+        // Configure TCP socket for performance
+        let _ = stream.set_nodelay(true);
+        tokio::spawn(handle_connection(stream, peer.ip()));
+    }
+}
+
+// Wait until the open connection count reaches zero or the deadline passes; returns how
+// many were still open. A count that drops to zero between the check and the wait leaves
+// a stored permit, so the wake-up is never missed - and a stale permit from before
+// shutdown only costs one more check.
+async fn wait_for_connections(deadline: Duration) -> usize {
+    let drained = async {
+        while ACTIVE_CONNECTIONS.load(Ordering::Acquire) > 0 {
+            CONNECTIONS_CLOSED.notified().await;
+        }
     };
+    let _ = timeout(deadline, drained).await;
+    ACTIVE_CONNECTIONS.load(Ordering::Acquire)
+}
 
+fn draining() -> bool {
+    SHUTDOWN.load(Ordering::Relaxed)
+}
+
+// Resolves on Ctrl+C or SIGTERM. The handlers are installed when this is called rather
+// than when it is first polled - a signal delivered with no listener registered is lost.
+fn shutdown_signal() -> impl std::future::Future<Output = ()> {
     #[cfg(unix)]
-    let terminate = async {
-        signal::unix::signal(signal::unix::SignalKind::terminate())
-            .expect("failed to install signal handler")
-            .recv()
-            .await;
+    let (mut interrupt, mut terminate) = {
+        use signal::unix::{signal, SignalKind};
+        (
+            signal(SignalKind::interrupt()).expect("failed to install Ctrl+C handler"),
+            signal(SignalKind::terminate()).expect("failed to install signal handler"),
+        )
     };
 
+    async move {
+        #[cfg(unix)]
+        tokio::select! {
+            _ = interrupt.recv() => {},
+            _ = terminate.recv() => {},
+        }
+
+        #[cfg(not(unix))]
+        signal::ctrl_c().await.expect("failed to install Ctrl+C handler");
+    }
+}
+
+// SIGHUP requests a cache rebuild - delivered as a channel so the accept loop can select on it
+fn reload_signal() -> tokio::sync::mpsc::Receiver<()> {
+    let (tx, rx) = tokio::sync::mpsc::channel(1);
+
+    #[cfg(unix)]
+    {
+        let mut hangup = signal::unix::signal(signal::unix::SignalKind::hangup())
+            .expect("failed to install SIGHUP handler");
+        tokio::spawn(async move {
+            while hangup.recv().await.is_some() {
+                // A reload already queued covers this signal too
+                let _ = tx.try_send(());
+            }
+        });
+    }
+
     #[cfg(not(unix))]
-    let terminate = std::future::pending::<()>();
+    std::mem::forget(tx);
 
-    tokio::select! {
-        _ = ctrl_c => {},
-        _ = terminate => {},
+    rx
+}
+
+// Counts a connection as in flight for as long as it is alive
+struct ActiveConnection;
+
+impl ActiveConnection {
+    fn open() -> Self {
+        ACTIVE_CONNECTIONS.fetch_add(1, Ordering::AcqRel);
+        ActiveConnection
     }
 }
 
-async fn handle_connection(mut stream: TcpStream) {
+impl Drop for ActiveConnection {
+    fn drop(&mut self) {
+        if ACTIVE_CONNECTIONS.fetch_sub(1, Ordering::AcqRel) == 1 {
+            CONNECTIONS_CLOSED.notify_one();
+        }
+    }
+}
+
+async fn handle_connection(mut stream: TcpStream, client_ip: IpAddr) {
+    let _active = ActiveConnection::open();
+    let metrics = METRICS.get();
+    if let Some(metrics) = metrics {
+        metrics.connection_opened();
+    }
+    
     // Set connection timeout
     let connection_result = timeout(
-        Duration::from_secs(CONNECTION_TIMEOUT_SECS),
-        handle_connection_inner(&mut stream),
+        CONFIG.get().unwrap().connection_timeout,
+        handle_connection_inner(&mut stream, client_ip),
     )
     .await;
 
     if connection_result.is_err() {
-        let _ = stream.write_all(&HEADER_TEMPLATES.get().unwrap().request_timeout).await;
-        let _ = stream.flush().await;
+        let mut output = ResponseQueue::new(|| CLOCK.date_header(), draining);
+        let sent = send_error(&mut output, &HEADER_TEMPLATES.get().unwrap().request_timeout, false);
+        if output.flush(&mut stream).await.is_ok() {
+            if let Some(metrics) = metrics {
+                metrics.record_request(b"-", sent.status, sent.bytes, Duration::ZERO);
+            }
+        }
+    }
+    
+    if let Some(metrics) = metrics {
+        metrics.connection_closed();
+    }
+}
+
+// Log and count a request head the parser refused - a steady stream of these from one
+// address usually means a scanner, an abusive client or a desynchronised proxy
+fn reject_request_head(client_ip: IpAddr, reason: Rejection) {
+    eprintln!("Rejected request from {}: {} ({})", client_ip, reason.as_str(), reason.status());
+    if let Some(metrics) = METRICS.get() {
+        metrics.record_rejected(reason);
     }
 }
 
-async fn handle_connection_inner(stream: &mut TcpStream) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    // Pre-allocate buffers once per connection (not per request)
-    let mut request_line = String::with_capacity(512);
-    let mut header_buffer = Vec::with_capacity(1024);
-    let mut if_modified_since_buf = Vec::with_capacity(256);
-    let mut if_none_match_buf = Vec::with_capacity(256);
+// Request header values that influence the response - borrowed from per-connection buffers
+struct RequestHeaders<'a> {
+    conditions: Preconditions<'a>,
+    authorization: Option<&'a [u8]>,
+    range: Option<&'a [u8]>,
+    if_range: Option<&'a [u8]>,
+    accept_encoding: Option<&'a [u8]>,
+    // Captured only for requests sampled into the access log
+    user_agent: Option<&'a [u8]>,
+    referer: Option<&'a [u8]>,
+}
+
+// When a request started; `log_time` is set only if it was sampled into the access log
+#[derive(Clone, Copy)]
+struct RequestStart {
+    at: Instant,
+    log_time: Option<SystemTime>,
+}
+
+// Bookkeeping once a response is written: metrics, then the access log for sampled
+// requests. Unparsed requests are recorded as `- - -`.
+fn finish_request(
+    start: RequestStart,
+    client_ip: IpAddr,
+    request: (&str, &str, &str),
+    sent: Sent,
+    user_agent: Option<&[u8]>,
+    referer: Option<&[u8]>,
+) {
+    let (method, path, version) = request;
+    let duration = start.at.elapsed();
     
-    loop {
-        // Check for shutdown
-        if SHUTDOWN.load(Ordering::Relaxed) {
-            break;
-        }
+    if let Some(metrics) = METRICS.get() {
+        metrics.record_request(method.as_bytes(), sent.status, sent.bytes, duration);
+    }
+    
+    if let (Some(logger), Some(timestamp)) = (ACCESS_LOG.get(), start.log_time) {
+        logger.log(&AccessLogEntry {
+            client_ip,
+            timestamp,
+            method,
+            path,
+            version,
+            status: sent.status,
+            bytes: sent.bytes,
+            duration,
+            user_agent,
+            referer,
+        });
+    }
+}
 
-        // Clear buffers for reuse (eliminate per-request allocations)
-        request_line.clear();
-        header_buffer.clear();
-        if_modified_since_buf.clear();
-        if_none_match_buf.clear();
+// A response still waiting in the output queue. Its request is recorded once the write
+// that sends it completes, so latency and bytes cover the send, not just the queueing.
+struct Unsent {
+    start: RequestStart,
+    method: &'static str,
+    sent: Sent,
+    // Copied out of the connection buffer only for requests sampled into the access log
+    logged: Option<LoggedRequest>,
+}
 
-        // Create fresh BufReader per request - optimal for brief line reading
-        let mut reader = BufReader::new(&mut *stream);
+struct LoggedRequest {
+    path: String,
+    version: String,
+    user_agent: Option<Vec<u8>>,
+    referer: Option<Vec<u8>>,
+}
 
-        // Read request line with timeout
-        match timeout(
-            Duration::from_secs(KEEPALIVE_TIMEOUT_SECS),
-            reader.read_line(&mut request_line),
-        )
-        .await
-        {
-            Ok(Ok(0)) | Err(_) => break, // Connection closed or timeout
-            Ok(Err(_)) => break,         // Read error
-            Ok(Ok(size)) if size > MAX_REQUEST_SIZE => {
-                send_precompiled_response(stream, &HEADER_TEMPLATES.get().unwrap().request_too_large).await?;
-                break;
-            }
-            Ok(Ok(_)) => {}
+impl Unsent {
+    fn finish(self, client_ip: IpAddr) {
+        match &self.logged {
+            Some(logged) => finish_request(
+                self.start,
+                client_ip,
+                (self.method, &logged.path, &logged.version),
+                self.sent,
+                logged.user_agent.as_deref(),
+                logged.referer.as_deref(),
+            ),
+            None => finish_request(self.start, client_ip, (self.method, "-", "-"), self.sent, None, None),
         }
+    }
+}
+
+// Write the queued responses, then record the requests they answer
+async fn flush_responses(
+    stream: &mut TcpStream,
+    output: &mut ResponseQueue,
+    unsent: &mut Vec<Unsent>,
+    client_ip: IpAddr,
+) -> std::io::Result<()> {
+    output.flush(stream).await?;
+    for request in unsent.drain(..) {
+        request.finish(client_ip);
+    }
+    Ok(())
+}
 
-        if request_line.trim().is_empty() {
-            continue; // Keep-alive, wait for next request
+async fn handle_connection_inner(
+    stream: &mut TcpStream,
+    client_ip: IpAddr,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let config = CONFIG.get().unwrap();
+    let templates = HEADER_TEMPLATES.get().unwrap();
+    let access_log = ACCESS_LOG.get();
+    let metrics = METRICS.get();
+    const UNPARSED: (&str, &str, &str) = ("-", "-", "-");
+    // Sampling is decided once per request so unsampled ones skip the extra header capture
+    let start_request = || RequestStart {
+        at: Instant::now(),
+        log_time: access_log.filter(|log| log.sample()).map(|_| SystemTime::now()),
+    };
+    let mut served_requests = 0u64;
+    let mut drain = DRAIN.subscribe();
+    let limits = config.head_limits();
+    
+    // One buffer for the life of the connection: pipelined requests are parsed from it in
+    // order, and their responses are queued so a batch leaves in as few writes as possible
+    let mut buffer = RequestBuffer::with_capacity(4096);
+    let mut output = ResponseQueue::new(|| CLOCK.date_header(), draining);
+    let mut unsent = Vec::new();
+    let mut accept_encoding_buf = Vec::with_capacity(64);
+    
+    loop {
+        let (head, consumed, body) = match buffer.next_head(&limits) {
+            HeadStatus::Complete { head, consumed, body } => (head, consumed, body),
+            HeadStatus::Rejected(reason) => {
+                let started = start_request();
+                reject_request_head(client_ip, reason);
+                let response = match reason.status() {
+                    414 => &templates.uri_too_long,
+                    431 => &templates.header_fields_too_large,
+                    413 => &templates.content_too_large,
+                    _ => &templates.bad_request,
+                };
+                output.closing = true;
+                let sent = send_error(&mut output, response, false);
+                flush_responses(stream, &mut output, &mut unsent, client_ip).await?;
+                finish_request(started, client_ip, UNPARSED, sent, None, None);
+                break;
+            }
+            HeadStatus::Partial => {
+                // Nothing complete is buffered - send what's queued before waiting on the client
+                flush_responses(stream, &mut output, &mut unsent, client_ip).await?;
+                
+                // Read with timeout; an idle keep-alive connection is closed as soon as
+                // draining starts
+                let idle = served_requests > 0 && buffer.is_empty();
+                let spare = buffer.spare();
+                let read = async {
+                    if idle {
+                        tokio::select! {
+                            result = stream.read_buf(spare) => result,
+                            _ = drain.wait_for(|&draining| draining) => Ok(0),
+                        }
+                    } else {
+                        stream.read_buf(spare).await
+                    }
+                };
+                match timeout(config.keepalive_timeout, read).await {
+                    Ok(Ok(0)) | Err(_) => break, // Connection closed or timeout
+                    Ok(Err(_)) => break,         // Read error
+                    Ok(Ok(_)) => continue,
+                }
+            }
+        };
+        
+        let started = start_request();
+        if served_requests > 0 {
+            if let Some(metrics) = metrics {
+                metrics.record_keepalive_reuse();
+            }
         }
+        served_requests += 1;
 
-        // Zero-allocation HTTP parsing - avoid string splits and allocations
-        let request_bytes = request_line.trim().as_bytes();
-        let (method, path, version) = match parse_request_line_fast(request_bytes) {
+        // Zero-allocation HTTP parsing - the request line and headers are borrowed
+        // straight from the connection buffer
+        let mut lines = head_lines(buffer.get(head));
+        let request_line = trim_header_line(lines.next().unwrap_or_default());
+        let (method, path, version) = match parse_request_line_fast(request_line) {
             Some((m, p, v)) => (m, p, v),
             None => {
-                send_precompiled_response(stream, &HEADER_TEMPLATES.get().unwrap().bad_request).await?;
+                output.closing = true;
+                let sent = send_error(&mut output, &templates.bad_request, false);
+                flush_responses(stream, &mut output, &mut unsent, client_ip).await?;
+                finish_request(started, client_ip, UNPARSED, sent, None, None);
                 break;
             }
         };
+        let method_str = std::str::from_utf8(method).unwrap_or("-");
 
-        if method != b"GET" && method != b"HEAD" {
-            send_precompiled_response(stream, &HEADER_TEMPLATES.get().unwrap().method_not_allowed).await?;
+        // POST is only accepted for the admin endpoint, checked once headers are read
+        let is_post = method == b"POST";
+        if method != b"GET" && method != b"HEAD" && !is_post {
+            output.closing = true;
+            let sent = send_error(&mut output, &templates.method_not_allowed, false);
+            flush_responses(stream, &mut output, &mut unsent, client_ip).await?;
+            finish_request(started, client_ip, (method_str, path, version), sent, None, None);
             break;
         }
 
 
         // Enhanced connection management - faster header parsing
         let mut keep_alive = version == "HTTP/1.1"; // Default for HTTP/1.1
-        let mut if_modified_since: Option<&[u8]> = None;
-        let mut if_none_match: Option<&[u8]> = None;
+        let mut conditions = Preconditions::default();
+        let mut authorization: Option<&[u8]> = None;
+        let mut range: Option<&[u8]> = None;
+        let mut if_range: Option<&[u8]> = None;
+        let mut accept_encoding: Option<&[u8]> = None;
+        let mut accept_encoding_repeated = false;
+        let mut user_agent: Option<&[u8]> = None;
+        let mut referer: Option<&[u8]> = None;
+        accept_encoding_buf.clear();
         
-        // Optimized header parsing with zero allocations
-        loop {
-            header_buffer.clear(); // Reuse vec, just clear content
+        for line in lines {
+            let line = trim_header_line(line);
+            if line.is_empty() {
+                continue;
+            }
             
-            // Read header line into byte buffer
-            match read_line_bytes(&mut reader, &mut header_buffer).await {
-                Ok(0) => break, // Connection closed
-                Ok(_) => {
-                    if header_buffer.is_empty() || (header_buffer.len() == 2 && header_buffer == b"\r\n") {
-                        break; // End of headers
-                    }
-                    
-                    // Trim CRLF and whitespace
-                    let line = trim_header_line(&header_buffer);
-                    if line.is_empty() {
-                        break;
-                    }
-                    
-                    // Optimized header parsing using byte slices
-                    if header_starts_with(line, b"connection:") {
-                        let connection_close_requested = header_contains(line, b"close");
-                        keep_alive = !connection_close_requested && (version == "HTTP/1.1" || header_contains(line, b"keep-alive"));
-                    } else if header_starts_with(line, b"if-modified-since:") {
-                        if let Some(value) = extract_header_value(line, b"if-modified-since:") {
-                            if_modified_since_buf.clear();
-                            if_modified_since_buf.extend_from_slice(value);
-                            if_modified_since = Some(&if_modified_since_buf);
-                        }
-                    } else if header_starts_with(line, b"if-none-match:") {
-                        if let Some(value) = extract_header_value(line, b"if-none-match:") {
-                            if_none_match_buf.clear();
-                            if_none_match_buf.extend_from_slice(value);
-                            if_none_match = Some(&if_none_match_buf);
+            // Optimized header parsing using byte slices
+            if header_starts_with(line, b"connection:") {
+                let connection_close_requested = header_contains(line, b"close");
+                keep_alive = !connection_close_requested && (version == "HTTP/1.1" || header_contains(line, b"keep-alive"));
+            } else if header_starts_with(line, b"if-modified-since:") {
+                if let Some(value) = extract_header_value(line, b"if-modified-since:") {
+                    conditions.if_modified_since = Some(value);
+                }
+            } else if header_starts_with(line, b"if-none-match:") {
+                if let Some(value) = extract_header_value(line, b"if-none-match:") {
+                    conditions.if_none_match = Some(value);
+                }
+            } else if header_starts_with(line, b"if-match:") {
+                if let Some(value) = extract_header_value(line, b"if-match:") {
+                    conditions.if_match = Some(value);
+                }
+            } else if header_starts_with(line, b"if-unmodified-since:") {
+                if let Some(value) = extract_header_value(line, b"if-unmodified-since:") {
+                    conditions.if_unmodified_since = Some(value);
+                }
+            } else if header_starts_with(line, b"authorization:") {
+                if let Some(value) = extract_header_value(line, b"authorization:") {
+                    authorization = Some(value);
+                }
+            } else if header_starts_with(line, b"range:") {
+                if let Some(value) = extract_header_value(line, b"range:") {
+                    range = Some(value);
+                }
+            } else if header_starts_with(line, b"if-range:") {
+                if let Some(value) = extract_header_value(line, b"if-range:") {
+                    if_range = Some(value);
+                }
+            } else if header_starts_with(line, b"accept-encoding:") {
+                // Repeated Accept-Encoding lines form one comma-separated list; only then
+                // is the value copied
+                if let Some(value) = extract_header_value(line, b"accept-encoding:") {
+                    match accept_encoding {
+                        None => accept_encoding = Some(value),
+                        Some(first) => {
+                            if !accept_encoding_repeated {
+                                accept_encoding_buf.extend_from_slice(first);
+                                accept_encoding_repeated = true;
+                            }
+                            accept_encoding_buf.push(b',');
+                            accept_encoding_buf.extend_from_slice(value);
                         }
                     }
                 }
-                Err(_) => break,
+            } else if started.log_time.is_some() && header_starts_with(line, b"user-agent:") {
+                if let Some(value) = extract_header_value(line, b"user-agent:") {
+                    user_agent = Some(value);
+                }
+            } else if started.log_time.is_some() && header_starts_with(line, b"referer:") {
+                if let Some(value) = extract_header_value(line, b"referer:") {
+                    referer = Some(value);
+                }
             }
         }
+        if accept_encoding_repeated {
+            accept_encoding = Some(&accept_encoding_buf);
+        }
 
         // Fast method detection and request handling
         let is_head = method == b"HEAD";
+        let headers = RequestHeaders {
+            conditions,
+            authorization,
+            range,
+            if_range,
+            accept_encoding,
+            user_agent,
+            referer,
+        };
         
-        // Direct stream usage for optimal response performance
-        match handle_request(stream, path, is_head, if_modified_since, if_none_match).await {
-            Ok(_) => {
-                if !keep_alive {
-                    break;
-                }
+        if !is_post && (conditions.if_none_match.is_some() || conditions.if_modified_since.is_some()) {
+            if let Some(metrics) = metrics {
+                metrics.record_conditional();
             }
+        }
+        
+        output.closing = !keep_alive;
+        let result = if is_post {
+            handle_admin_request(&mut output, path, headers.authorization).await
+        } else {
+            Ok(handle_request(&mut output, path, is_head, &headers))
+        };
+        let sent = match result {
+            Ok(sent) => sent,
             Err(_) => break,
+        };
+        
+        // Large responses go out right away; small ones wait to share a write with the
+        // responses to requests pipelined behind them
+        let close = !keep_alive || draining();
+        if close || output.len() >= COALESCE_LIMIT {
+            flush_responses(stream, &mut output, &mut unsent, client_ip).await?;
+            finish_request(started, client_ip, (method_str, path, version), sent, headers.user_agent, headers.referer);
+        } else {
+            let method = if is_post { "POST" } else if is_head { "HEAD" } else { "GET" };
+            let logged = started.log_time.map(|_| LoggedRequest {
+                path: path.to_string(),
+                version: version.to_string(),
+                user_agent: headers.user_agent.map(<[u8]>::to_vec),
+                referer: headers.referer.map(<[u8]>::to_vec),
+            });
+            unsent.push(Unsent { start: started, method, sent, logged });
+        }
+        buffer.consume(consumed);
+        buffer.discard(body);
+        if close {
+            break;
         }
     }
 
     Ok(())
 }
 
-// Helper function for sending precompiled responses efficiently
-async fn send_precompiled_response(
-    stream: &mut TcpStream,
-    response: &[u8],
-) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    stream.write_all(response).await?;
-    stream.flush().await?;
+// Queued responses up to this size wait for the next pipelined request before being
+// written; anything larger is written immediately
+const COALESCE_LIMIT: usize = 64 * 1024;
+
+// Admin endpoints - disabled unless an admin token is configured, in which case
+// POST /admin/reload rebuilds the cache and reports what changed
+async fn handle_admin_request(
+    output: &mut ResponseQueue,
+    path: &str,
+    authorization: Option<&[u8]>,
+) -> Result<Sent, Box<dyn std::error::Error + Send + Sync>> {
+    let templates = HEADER_TEMPLATES.get().unwrap();
+    let config = CONFIG.get().unwrap();
+    
+    let token = match &config.admin_token {
+        Some(token) if path.split('?').next() == Some("/admin/reload") => token,
+        _ => return Ok(send_error(output, &templates.method_not_allowed, false)),
+    };
+    
+    let authorized = authorization
+        .and_then(|value| value.strip_prefix(b"Bearer "))
+        .is_some_and(|presented| constant_time_eq(presented, token.as_bytes()));
+    if !authorized {
+        return Ok(send_error(output, &templates.unauthorized, false));
+    }
+    
+    let static_dir = config.static_dir.clone();
+    let report = tokio::task::spawn_blocking(move || reload_file_cache(&static_dir, "admin")).await?;
+    
+    let body = report.to_json();
+    let response = format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nCache-Control: no-store\r\n{}Connection: keep-alive\r\n\r\n{}",
+        body.len(),
+        templates.security_headers,
+        body
+    );
+    Ok(output.push_owned(response.into_bytes()))
+}
+
+// Permanent redirect; `location` must already be percent-encoded
+fn redirect_response(status: u16, location: &str) -> Vec<u8> {
+    format!(
+        "HTTP/1.1 {} {}\r\nLocation: {}\r\nContent-Length: 0\r\n{}Connection: keep-alive\r\n\r\n",
+        status,
+        status_text(status),
+        location,
+        HEADER_TEMPLATES.get().unwrap().security_headers
+    ).into_bytes()
+}
+
+// Error response from the current generation's `<status>.html` page when the content root
+// has one, else the built-in template
+fn send_error(output: &mut ResponseQueue, builtin: &ErrorResponse, is_head: bool) -> Sent {
+    match FILE_CACHE.get().and_then(|cache| cache.error_page(builtin.status, is_head)) {
+        Some(page) => output.push_shared(&page),
+        None => output.push_shared(builtin.for_method(is_head)),
+    }
+}
+
+fn send_redirect(output: &mut ResponseQueue, status: u16, location: &str) -> Sent {
+    output.push_owned(redirect_response(status, location))
+}
+
+fn serves_metrics_on_main_port() -> bool {
+    let config = CONFIG.get().unwrap();
+    config.metrics && config.metrics_port.is_none()
+}
+
+// Render metrics on demand - scrapes are rare, so the body is built per request
+fn send_metrics_response(output: &mut ResponseQueue, is_head: bool, connection: &str) -> Sent {
+    let file_cache = FILE_CACHE.get().unwrap();
+    let cache = CacheStats { entries: file_cache.entry_count(), bytes: file_cache.cached_bytes() };
+    let body = METRICS.get().map(|metrics| metrics.render(cache)).unwrap_or_default();
+    
+    let mut response = format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4; charset=utf-8\r\nContent-Length: {}\r\nCache-Control: no-store\r\n{}Connection: {}\r\n\r\n",
+        body.len(),
+        HEADER_TEMPLATES.get().unwrap().security_headers,
+        connection
+    );
+    if !is_head {
+        response.push_str(&body);
+    }
+    output.push_owned(response.into_bytes())
+}
+
+// Dedicated metrics listener - keeps /metrics off the public port. One request per
+// connection; anything but GET/HEAD /metrics gets a 404.
+async fn serve_metrics(listener: TcpListener) {
+    loop {
+        let stream = match listener.accept().await {
+            Ok((stream, _)) => stream,
+            Err(_) => continue,
+        };
+        tokio::spawn(async move {
+            let config = CONFIG.get().unwrap();
+            let _ = timeout(config.connection_timeout, handle_metrics_connection(stream)).await;
+        });
+    }
+}
+
+async fn handle_metrics_connection(mut stream: TcpStream) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let config = CONFIG.get().unwrap();
+    let mut buffer = RequestBuffer::with_capacity(1024);
+    let mut output = ResponseQueue::new(|| CLOCK.date_header(), draining);
+    
+    let head = loop {
+        match buffer.next_head(&config.head_limits()) {
+            HeadStatus::Complete { head, .. } => break head,
+            HeadStatus::Rejected(_) => return Ok(()),
+            HeadStatus::Partial => {
+                if stream.read_buf(buffer.spare()).await? == 0 {
+                    return Ok(());
+                }
+            }
+        }
+    };
+    
+    let request_line = trim_header_line(head_lines(buffer.get(head)).next().unwrap_or_default());
+    match parse_request_line_fast(request_line) {
+        Some((method @ (b"GET" | b"HEAD"), path, _)) if path.split('?').next() == Some("/metrics") => {
+            send_metrics_response(&mut output, method == b"HEAD", "close");
+        }
+        parsed => {
+            let is_head = matches!(parsed, Some((b"HEAD", _, _)));
+            send_error(&mut output, &HEADER_TEMPLATES.get().unwrap().not_found, is_head);
+        }
+    }
+    output.flush(&mut stream).await?;
     Ok(())
 }
 
-async fn handle_request(
-    stream: &mut TcpStream,
-    path: &str,
-    is_head: bool,
-    if_modified_since: Option<&[u8]>,
-    if_none_match: Option<&[u8]>,
-) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+// Serve a Range request from the cached body; returns None when the header should
+// be ignored and the full representation sent instead
+fn send_range_response(output: &mut ResponseQueue, cache_entry: &CacheEntry, range_header: &[u8]) -> Option<Sent> {
+    // complete_response is headers_only followed by the body; parts are queued as slices of it
+    let body_start = cache_entry.headers_only.len();
+    let total = (cache_entry.complete_response.len() - body_start) as u64;
+    let part = |range: &ByteRange| {
+        let range = range.to_index_range();
+        Chunk::shared(&cache_entry.complete_response, body_start + range.start..body_start + range.end)
+    };
+    
+    match parse_range(range_header, total) {
+        RangeRequest::Ignore => None,
+        RangeRequest::Unsatisfiable => {
+            let headers = derive_headers(
+                &cache_entry.headers_only,
+                b"HTTP/1.1 416 Range Not Satisfiable",
+                &[b"content-type:", b"content-length:"],
+                &format!("Content-Range: bytes */{}\r\nContent-Length: 0\r\n", total),
+            );
+            Some(output.push_owned(headers))
+        }
+        RangeRequest::Satisfiable(ranges) if ranges.len() == 1 => {
+            let range = ranges[0];
+            let headers = derive_headers(
+                &cache_entry.headers_only,
+                b"HTTP/1.1 206 Partial Content",
+                &[b"content-length:"],
+                &format!("Content-Range: {}\r\nContent-Length: {}\r\n", range.content_range(total), range.to_index_range().len()),
+            );
+            Some(output.push(Chunk::Owned(headers), Some(part(&range))))
+        }
+        RangeRequest::Satisfiable(ranges) => {
+            let content_type = header_value(&cache_entry.headers_only, b"content-type:").unwrap_or(b"application/octet-stream");
+            let content_type = String::from_utf8_lossy(content_type);
+            let boundary = multipart_boundary();
+            
+            // Part headers are small; bodies are borrowed straight from the cache
+            let part_headers: Vec<String> = ranges
+                .iter()
+                .map(|range| format!("\r\n--{}\r\nContent-Type: {}\r\nContent-Range: {}\r\n\r\n", boundary, content_type, range.content_range(total)))
+                .collect();
+            let closing = format!("\r\n--{}--\r\n", boundary);
+            let content_length = part_headers.iter().map(String::len).sum::<usize>()
+                + ranges.iter().map(|range| range.to_index_range().len()).sum::<usize>()
+                + closing.len();
+            
+            let headers = derive_headers(
+                &cache_entry.headers_only,
+                b"HTTP/1.1 206 Partial Content",
+                &[b"content-type:", b"content-length:"],
+                &format!("Content-Type: multipart/byteranges; boundary={}\r\nContent-Length: {}\r\n", boundary, content_length),
+            );
+            
+            let parts = part_headers
+                .into_iter()
+                .zip(&ranges)
+                .flat_map(|(part_header, range)| [Chunk::Owned(part_header.into_bytes()), part(range)])
+                .chain(std::iter::once(Chunk::Owned(closing.into_bytes())));
+            Some(output.push(Chunk::Owned(headers), parts))
+        }
+    }
+}
+
+// Derive a response header block from pre-generated 200 headers: swap the status line,
+// drop the named headers and append replacements, keeping every other header consistent
+fn derive_headers(base: &[u8], status_line: &[u8], drop: &[&[u8]], extra: &str) -> Vec<u8> {
+    let mut headers = Vec::with_capacity(base.len() + extra.len());
+    headers.extend_from_slice(status_line);
+    headers.extend_from_slice(b"\r\n");
+    
+    for line in base.split(|&b| b == b'\n').skip(1) {
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        if line.is_empty() {
+            break;
+        }
+        if drop.iter().any(|name| header_starts_with(line, name)) {
+            continue;
+        }
+        headers.extend_from_slice(line);
+        headers.extend_from_slice(b"\r\n");
+    }
+    
+    headers.extend_from_slice(extra.as_bytes());
+    headers.extend_from_slice(b"\r\n");
+    headers
+}
+
+// Look up a header value in a pre-generated header block
+fn header_value<'a>(headers: &'a [u8], name: &[u8]) -> Option<&'a [u8]> {
+    headers
+        .split(|&b| b == b'\n')
+        .map(|line| line.strip_suffix(b"\r").unwrap_or(line))
+        .find(|line| header_starts_with(line, name))
+        .and_then(|line| extract_header_value(line, name))
+}
+
+// Unpredictable boundary so it can't collide with file content by construction
+fn multipart_boundary() -> String {
+    use std::hash::{BuildHasher, Hasher};
+    let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+    hasher.write_u64(SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default().as_nanos() as u64);
+    format!("kiss-{:016x}", hasher.finish())
+}
+
+// Token comparison that doesn't leak the matching prefix length through timing
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+fn handle_request(output: &mut ResponseQueue, path: &str, is_head: bool, headers: &RequestHeaders<'_>) -> Sent {
     // Handle health check endpoints using unified response pattern
     let templates = HEADER_TEMPLATES.get().unwrap();
     
     // Unified single-write pattern for health endpoints
     if path == "/health" {
-        if is_head {
-            stream.write_all(&templates.health_headers_only).await?;
-        } else {
-            stream.write_all(&templates.health_complete).await?;
-        }
-        stream.flush().await?;
-        return Ok(());
+        let response = if is_head { &templates.health_headers_only } else { &templates.health_complete };
+        return output.push_static(response);
+    }
+
+    if path == "/metrics" && serves_metrics_on_main_port() {
+        return send_metrics_response(output, is_head, "keep-alive");
     }
 
     if path == "/ready" {
-        if is_head {
-            stream.write_all(&templates.ready_headers_only).await?;
-        } else {
-            stream.write_all(&templates.ready_complete).await?;
-        }
-        stream.flush().await?;
-        return Ok(());
+        let response = match (draining(), is_head) {
+            (true, true) => &templates.draining_headers_only,
+            (true, false) => &templates.draining_complete,
+            (false, true) => &templates.ready_headers_only,
+            (false, false) => &templates.ready_complete,
+        };
+        return output.push_static(response);
     }
 
     // Inline static file serving for zero function call overhead
     
-    // Fast path lookup with integrated query handling in PathTrie
+    // Canonical form: percent-decoded, dot-segments removed, slashes merged. Clean paths
+    // are borrowed as they are.
+    let target = match parse_target(path) {
+        Ok(target) => target,
+        Err(_) => return send_error(output, &templates.bad_request, is_head),
+    };
     let file_cache = FILE_CACHE.get().unwrap();
+    let redirect_status = CONFIG.get().map_or(301, |config| config.redirect_status);
     
-    // Direct path lookup - query parameters handled in hash computation
-    let cache_entry = file_cache.get(path);
+    // Trailing-slash and clean-URL spellings: a resource may live under another path
+    let located = match URL_STYLE.get() {
+        Some(style) => style.locate(&target.path, |path| file_cache.lookup(path)),
+        None => file_cache.lookup(&target.path).map_or(Located::Missing, Located::Found),
+    };
+    
+    // `_redirects` rules: forced ones apply even over a file, the others only where
+    // there is none. Rewrites serve another file's entry under this URL.
+    let shadowed = !matches!(located, Located::Missing);
+    let cache_entry = match file_cache.resolve_redirect(&target.path, target.query, shadowed) {
+        Some(Resolution::Prebuilt(response)) => return output.push_shared(&response),
+        Some(Resolution::Redirect { status, location }) => return send_redirect(output, status, &location),
+        Some(Resolution::Rewrite(path)) => file_cache.lookup(&path),
+        // Other spellings of an existing file are sent to its canonical URL, so caches
+        // and analytics see one URL per resource
+        None => match located {
+            Located::Found(_) if !target.is_canonical() => {
+                return send_redirect(output, redirect_status, &target.canonical_uri());
+            }
+            Located::Found(cache_entry) => Some(cache_entry),
+            Located::Redirect(path) => return send_redirect(output, redirect_status, &target.canonical_uri_for(&path)),
+            Located::Missing => None,
+        },
+    };
+    
+    // Client-side routes get the app's entry document, validators and all
+    let cache_entry = cache_entry.or_else(|| {
+        let document = SPA_FALLBACK.get()?.document_for(&target.path)?;
+        file_cache.lookup(document)
+    });
 
     // Handle file from cache or 404
     if let Some(cache_entry) = cache_entry {
-        // Fast conditional request handling with If-Modified-Since check first
-        if let Some(if_modified_since_bytes) = if_modified_since {
-            // Convert bytes to string only when needed for parsing
-            if let Ok(if_modified_since_str) = std::str::from_utf8(if_modified_since_bytes) {
-                if let Ok(client_time) = httpdate::parse_http_date(if_modified_since_str) {
-                    if cache_entry.last_modified_timestamp <= client_time {
-                        // Fast path: Use pre-generated 304 response
-                        stream.write_all(&cache_entry.not_modified_response).await?;
-                        stream.flush().await?;
-                        return Ok(());
+        // Pick the precompressed variant the client prefers - validators, ranges and
+        // the response itself all apply to the selected representation
+        let cache_entry = cache_entry.negotiate(headers.accept_encoding);
+        
+        // Preconditions apply to the selected representation, before any range
+        match cache_entry.evaluate_preconditions(&headers.conditions) {
+            Precondition::Proceed => {}
+            Precondition::NotModified => return output.push_shared(&cache_entry.not_modified_response),
+            Precondition::Failed => return send_error(output, &templates.precondition_failed, is_head),
+        }
+
+        // Range requests apply to GET only, and only while If-Range still validates
+        if !is_head {
+            if let Some(range) = headers.range {
+                let if_range_ok = match headers.if_range {
+                    Some(value) => if_range_matches(value, &cache_entry.etag, cache_entry.last_modified_timestamp),
+                    None => true,
+                };
+                if if_range_ok {
+                    if let Some(sent) = send_range_response(output, cache_entry, range) {
+                        return sent;
                     }
                 }
             }
         }
-        
-        // Fast conditional request handling (immutable files = simple ETag check)
-        if let Some(client_etag_bytes) = if_none_match {
-            // Perform direct byte comparison for ETag matching
-            let etag_bytes = cache_entry.etag.as_bytes();
-            if client_etag_bytes == b"*" || 
-               (client_etag_bytes.windows(etag_bytes.len()).any(|window| window == etag_bytes)) {
-                // Fast path: Use pre-generated 304 response
-                stream.write_all(&cache_entry.not_modified_response).await?;
-                stream.flush().await?;
-                return Ok(());
-            }
-        }
 
         // Single write operation - minimal system calls
         if is_head {
             // HEAD request: Send headers only (pre-generated, single write)
-            stream.write_all(&cache_entry.headers_only).await?;
+            output.push_shared(&cache_entry.headers_only)
         } else {
             // GET request: Send complete response (headers + content in single write!)
-            stream.write_all(&cache_entry.complete_response).await?;
+            output.push_shared(&cache_entry.complete_response)
         }
-        stream.flush().await?;
     } else {
         // File not in cache - return 404
-        stream.write_all(&HEADER_TEMPLATES.get().unwrap().not_found).await?;
-        stream.flush().await?;
+        send_error(output, &templates.not_found, is_head)
     }
-
-    Ok(())
 }
 
@@ -0,0 +1,174 @@
+use crate::request::Rejection;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+// Status codes KISS can produce; anything else is counted under `other`
+const STATUS_CODES: [u16; 20] = [
+    200, 206, 301, 302, 304, 307, 308, 400, 401, 403, 404, 405, 408, 412, 413, 414, 416, 431, 500, 503,
+];
+const METHODS: [&str; 4] = ["GET", "HEAD", "POST", "OTHER"];
+
+// Latency bucket upper bounds in microseconds (100µs .. 2.5s)
+const LATENCY_BUCKETS_US: [u64; 14] = [
+    100, 250, 500, 1_000, 2_500, 5_000, 10_000, 25_000, 50_000, 100_000, 250_000, 500_000, 1_000_000, 2_500_000,
+];
+
+// Cache figures sampled at scrape time
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct CacheStats {
+    pub entries: usize,
+    pub bytes: usize,
+}
+
+// Process-wide counters. Every update is a single relaxed atomic add - no locks on the
+// request path; consistency across counters is only needed at scrape granularity.
+pub struct Metrics {
+    // [method][status index], last status slot is `other`
+    requests: [[AtomicU64; STATUS_CODES.len() + 1]; METHODS.len()],
+    response_bytes: AtomicU64,
+    // Non-cumulative bucket counts, last slot is +Inf; cumulated when rendered
+    latency_buckets: [AtomicU64; LATENCY_BUCKETS_US.len() + 1],
+    latency_sum_us: AtomicU64,
+    connections_active: AtomicU64,
+    connections_total: AtomicU64,
+    keepalive_requests: AtomicU64,
+    conditional_requests: AtomicU64,
+    not_modified: AtomicU64,
+    // Indexed by Rejection
+    rejected: [AtomicU64; Rejection::ALL.len()],
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self {
+            requests: std::array::from_fn(|_| std::array::from_fn(|_| AtomicU64::new(0))),
+            response_bytes: AtomicU64::new(0),
+            latency_buckets: std::array::from_fn(|_| AtomicU64::new(0)),
+            latency_sum_us: AtomicU64::new(0),
+            connections_active: AtomicU64::new(0),
+            connections_total: AtomicU64::new(0),
+            keepalive_requests: AtomicU64::new(0),
+            conditional_requests: AtomicU64::new(0),
+            not_modified: AtomicU64::new(0),
+            rejected: std::array::from_fn(|_| AtomicU64::new(0)),
+        }
+    }
+
+    #[inline]
+    pub fn record_request(&self, method: &[u8], status: u16, bytes: u64, duration: Duration) {
+        let method_index = match method {
+            b"GET" => 0,
+            b"HEAD" => 1,
+            b"POST" => 2,
+            _ => 3,
+        };
+        let status_index = STATUS_CODES.iter().position(|&code| code == status).unwrap_or(STATUS_CODES.len());
+        self.requests[method_index][status_index].fetch_add(1, Ordering::Relaxed);
+        self.response_bytes.fetch_add(bytes, Ordering::Relaxed);
+        if status == 304 {
+            self.not_modified.fetch_add(1, Ordering::Relaxed);
+        }
+
+        let micros = duration.as_micros().min(u64::MAX as u128) as u64;
+        let bucket = LATENCY_BUCKETS_US.iter().position(|&bound| micros <= bound).unwrap_or(LATENCY_BUCKETS_US.len());
+        self.latency_buckets[bucket].fetch_add(1, Ordering::Relaxed);
+        self.latency_sum_us.fetch_add(micros, Ordering::Relaxed);
+    }
+
+    pub fn connection_opened(&self) {
+        self.connections_active.fetch_add(1, Ordering::Relaxed);
+        self.connections_total.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn connection_closed(&self) {
+        self.connections_active.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    // A request served on a connection that already carried one
+    pub fn record_keepalive_reuse(&self) {
+        self.keepalive_requests.fetch_add(1, Ordering::Relaxed);
+    }
+
+    // A request carrying If-None-Match or If-Modified-Since - the 304 ratio denominator
+    pub fn record_conditional(&self) {
+        self.conditional_requests.fetch_add(1, Ordering::Relaxed);
+    }
+
+    // A request head refused by the parser - over a limit or malformed framing
+    pub fn record_rejected(&self, reason: Rejection) {
+        self.rejected[reason as usize].fetch_add(1, Ordering::Relaxed);
+    }
+
+    // Prometheus text exposition format 0.0.4
+    pub fn render(&self, cache: CacheStats) -> String {
+        let mut out = String::with_capacity(4096);
+
+        header(&mut out, "kiss_requests_total", "counter", "HTTP requests served, by method and status code.");
+        for (method, row) in METHODS.iter().zip(&self.requests) {
+            for (i, counter) in row.iter().enumerate() {
+                let count = counter.load(Ordering::Relaxed);
+                if count == 0 {
+                    continue;
+                }
+                let code = STATUS_CODES.get(i).map_or_else(|| "other".to_string(), u16::to_string);
+                let _ = writeln!(out, "kiss_requests_total{{method=\"{}\",code=\"{}\"}} {}", method, code, count);
+            }
+        }
+
+        header(&mut out, "kiss_response_bytes_total", "counter", "Bytes written in responses, headers included.");
+        let _ = writeln!(out, "kiss_response_bytes_total {}", self.response_bytes.load(Ordering::Relaxed));
+
+        header(&mut out, "kiss_request_duration_seconds", "histogram", "Time from request line to response written.");
+        let mut cumulative = 0;
+        for (i, counter) in self.latency_buckets.iter().enumerate() {
+            cumulative += counter.load(Ordering::Relaxed);
+            let bound = LATENCY_BUCKETS_US.get(i).map_or_else(|| "+Inf".to_string(), |&us| seconds(us));
+            let _ = writeln!(out, "kiss_request_duration_seconds_bucket{{le=\"{}\"}} {}", bound, cumulative);
+        }
+        let _ = writeln!(out, "kiss_request_duration_seconds_sum {}", seconds(self.latency_sum_us.load(Ordering::Relaxed)));
+        let _ = writeln!(out, "kiss_request_duration_seconds_count {}", cumulative);
+
+        header(&mut out, "kiss_connections_active", "gauge", "Open client connections.");
+        let _ = writeln!(out, "kiss_connections_active {}", self.connections_active.load(Ordering::Relaxed));
+        header(&mut out, "kiss_connections_total", "counter", "Client connections accepted.");
+        let _ = writeln!(out, "kiss_connections_total {}", self.connections_total.load(Ordering::Relaxed));
+        header(&mut out, "kiss_keepalive_requests_total", "counter", "Requests served on a reused keep-alive connection.");
+        let _ = writeln!(out, "kiss_keepalive_requests_total {}", self.keepalive_requests.load(Ordering::Relaxed));
+
+        let conditional = self.conditional_requests.load(Ordering::Relaxed);
+        let not_modified = self.not_modified.load(Ordering::Relaxed);
+        header(&mut out, "kiss_conditional_requests_total", "counter", "Requests carrying If-None-Match or If-Modified-Since.");
+        let _ = writeln!(out, "kiss_conditional_requests_total {}", conditional);
+        header(&mut out, "kiss_not_modified_ratio", "gauge", "Share of conditional requests answered with 304 Not Modified.");
+        let ratio = if conditional == 0 { 0.0 } else { not_modified as f64 / conditional as f64 };
+        let _ = writeln!(out, "kiss_not_modified_ratio {}", ratio);
+
+        header(&mut out, "kiss_rejected_requests_total", "counter", "Requests refused by the parser for size limits or malformed framing, by reason.");
+        for (reason, counter) in Rejection::ALL.iter().zip(&self.rejected) {
+            let _ = writeln!(out, "kiss_rejected_requests_total{{reason=\"{}\"}} {}", reason.as_str(), counter.load(Ordering::Relaxed));
+        }
+
+        header(&mut out, "kiss_cache_entries", "gauge", "Files in the current cache generation.");
+        let _ = writeln!(out, "kiss_cache_entries {}", cache.entries);
+        header(&mut out, "kiss_cache_bytes", "gauge", "Pre-built response bytes held by the current cache generation.");
+        let _ = writeln!(out, "kiss_cache_bytes {}", cache.bytes);
+
+        out
+    }
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+fn seconds(micros: u64) -> String {
+    format!("{}", micros as f64 / 1_000_000.0)
+}
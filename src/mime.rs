@@ -0,0 +1,245 @@
+use crate::sniff::detect_charset;
+use crate::{get_mime_type_enum, MimeType};
+use rustc_hash::FxHashMap;
+use std::borrow::Cow;
+use std::fmt;
+use std::io;
+use std::path::Path;
+
+const DEFAULT_CHARSET: &str = "; charset=utf-8";
+
+// Content-Type of a file, resolved once per cache build: a built-in type, or one from a
+// mime.types file or a single override
+#[derive(Clone, Debug, PartialEq)]
+pub struct ContentType {
+    value: Cow<'static, str>,
+    compressible: bool,
+    // The charset is the default `utf-8` rather than one given by the user
+    default_charset: bool,
+}
+
+impl ContentType {
+    pub fn as_str(&self) -> &str {
+        &self.value
+    }
+
+    pub fn is_compressible(&self) -> bool {
+        self.compressible
+    }
+
+    // Replace a default charset with the one the content is actually in. Only types that
+    // carry a default charset pay for the scan - images and archives are never read.
+    pub fn with_charset_of(self, content: &[u8]) -> Self {
+        if !self.default_charset {
+            return self;
+        }
+        let charset = detect_charset(content).as_str();
+        match self.value.strip_suffix(DEFAULT_CHARSET) {
+            Some(media_type) if charset != "utf-8" => Self {
+                value: Cow::Owned(format!("{}; charset={}", media_type, charset)),
+                ..self
+            },
+            _ => self,
+        }
+    }
+
+    // A configured media type. Textual types without parameters get the same
+    // `charset=utf-8` the built-in ones carry.
+    fn custom(media_type: &str) -> Self {
+        let essence = media_type.split(';').next().unwrap_or_default().trim().to_ascii_lowercase();
+        let textual = is_textual(&essence);
+        let default_charset = textual && !media_type.contains(';');
+        let value = if default_charset {
+            format!("{}{}", media_type, DEFAULT_CHARSET)
+        } else {
+            media_type.to_string()
+        };
+        let compressible = textual || essence == "image/svg+xml" || essence == "application/wasm";
+        Self { value: Cow::Owned(value), compressible, default_charset }
+    }
+}
+
+impl From<MimeType> for ContentType {
+    fn from(mime_type: MimeType) -> Self {
+        Self {
+            value: Cow::Borrowed(mime_type.as_str()),
+            compressible: mime_type.is_compressible(),
+            default_charset: mime_type.as_str().ends_with(DEFAULT_CHARSET),
+        }
+    }
+}
+
+fn is_textual(essence: &str) -> bool {
+    match essence.split_once('/') {
+        Some(("text", _)) => true,
+        Some(("application", subtype)) => {
+            matches!(subtype, "json" | "xml" | "javascript" | "ecmascript" | "yaml" | "toml")
+                || subtype.ends_with("+json")
+                || subtype.ends_with("+xml")
+        }
+        _ => false,
+    }
+}
+
+// Extension overrides on top of the built-in table. Loaded from an Apache-style
+// mime.types file:
+//
+//   # type            extensions
+//   application/wasm  wasm
+//   text/x-rst        rst
+//
+// or nginx's `types { application/wasm wasm; }` block, then single `EXT=TYPE` options.
+// Lookups stay O(1): one hash probe, then the built-in match.
+#[derive(Clone, Debug, Default)]
+pub struct MimeTypes {
+    by_extension: FxHashMap<String, ContentType>,
+}
+
+#[derive(Debug)]
+pub enum MimeTypesError {
+    Io(io::Error),
+    Syntax { line: usize, message: String },
+}
+
+impl fmt::Display for MimeTypesError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MimeTypesError::Io(e) => write!(f, "{}", e),
+            MimeTypesError::Syntax { line, message } => write!(f, "line {}: {}", line, message),
+        }
+    }
+}
+
+impl std::error::Error for MimeTypesError {}
+
+impl MimeTypes {
+    pub fn load(path: &Path) -> Result<Self, MimeTypesError> {
+        let text = std::fs::read_to_string(path).map_err(MimeTypesError::Io)?;
+        Self::parse(&text)
+    }
+
+    pub fn parse(text: &str) -> Result<Self, MimeTypesError> {
+        let mut types = Self::default();
+        // Words of the current entry and the line it started on
+        let mut entry: Vec<&str> = Vec::new();
+        let mut entry_line = 0;
+        let mut in_block = false;
+
+        for (index, line) in text.lines().enumerate() {
+            let number = index + 1;
+            let syntax = |message: &str| MimeTypesError::Syntax { line: number, message: message.to_string() };
+            let line = line.split('#').next().unwrap_or_default();
+
+            for word in line.split_whitespace() {
+                // nginx punctuation may be attached to a word: `htm;` or `types{`
+                for piece in word.split_inclusive([';', '{', '}']) {
+                    let (name, delimiter) = match piece.chars().last() {
+                        Some(c @ (';' | '{' | '}')) => (&piece[..piece.len() - 1], Some(c)),
+                        _ => (piece, None),
+                    };
+                    if !name.is_empty() {
+                        if entry.is_empty() {
+                            entry_line = number;
+                        }
+                        entry.push(name);
+                    }
+                    match delimiter {
+                        Some('{') if !in_block && entry == ["types"] => {
+                            entry.clear();
+                            in_block = true;
+                        }
+                        Some('{') => return Err(syntax("expected 'types {'")),
+                        Some(';') => types.add_entry(&entry, entry_line)?,
+                        Some(_) if !in_block => return Err(syntax("unexpected '}'")),
+                        Some(_) if !entry.is_empty() => return Err(syntax("missing ';' before '}'")),
+                        Some(_) => in_block = false,
+                        None => {}
+                    }
+                    if delimiter == Some(';') {
+                        entry.clear();
+                    }
+                }
+            }
+
+            // Apache entries end with the line, nginx ones with `;`
+            if !in_block {
+                types.add_entry(&entry, entry_line)?;
+                entry.clear();
+            }
+        }
+
+        if in_block {
+            return Err(MimeTypesError::Syntax { line: text.lines().count(), message: "unclosed 'types {'".to_string() });
+        }
+        Ok(types)
+    }
+
+    // `type ext...` - a type listed without extensions is valid and maps nothing
+    fn add_entry(&mut self, entry: &[&str], line: usize) -> Result<(), MimeTypesError> {
+        let Some((media_type, extensions)) = entry.split_first() else {
+            return Ok(());
+        };
+        let syntax = |message| MimeTypesError::Syntax { line, message };
+        validate_media_type(media_type).map_err(syntax)?;
+        for extension in extensions {
+            self.insert(extension, media_type).map_err(syntax)?;
+        }
+        Ok(())
+    }
+
+    // Map an extension (with or without its leading dot) to a media type, replacing any
+    // earlier mapping
+    pub fn insert(&mut self, extension: &str, media_type: &str) -> Result<(), String> {
+        let extension = validate_extension(extension)?;
+        validate_media_type(media_type)?;
+        self.by_extension.insert(extension.to_ascii_lowercase(), ContentType::custom(media_type));
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_extension.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.by_extension.len()
+    }
+
+    pub fn lookup(&self, file_path: &Path) -> ContentType {
+        if !self.by_extension.is_empty() {
+            let extension = file_path.extension().and_then(|s| s.to_str()).map(str::to_ascii_lowercase);
+            if let Some(content_type) = extension.and_then(|extension| self.by_extension.get(&extension)) {
+                return content_type.clone();
+            }
+        }
+        ContentType::from(get_mime_type_enum(file_path))
+    }
+}
+
+// A single `EXT=TYPE` override, as given on the command line
+pub fn parse_override(value: &str) -> Result<(String, String), String> {
+    let (extension, media_type) = value.split_once('=').ok_or_else(|| "expected EXT=TYPE".to_string())?;
+    let (extension, media_type) = (extension.trim(), media_type.trim());
+    validate_extension(extension)?;
+    validate_media_type(media_type)?;
+    Ok((extension.to_string(), media_type.to_string()))
+}
+
+fn validate_extension(extension: &str) -> Result<&str, String> {
+    let stripped = extension.strip_prefix('.').unwrap_or(extension);
+    if stripped.is_empty() || stripped.contains(['.', '/', '\\']) || stripped.chars().any(|c| c.is_whitespace() || c.is_control()) {
+        return Err(format!("invalid extension '{}'", extension));
+    }
+    Ok(stripped)
+}
+
+// `type/subtype`, optionally followed by `; parameters`
+fn validate_media_type(media_type: &str) -> Result<(), String> {
+    let invalid = || format!("invalid media type '{}'", media_type);
+    let (essence, parameters) = media_type.split_once(';').unwrap_or((media_type, ""));
+    let (top, sub) = essence.trim().split_once('/').ok_or_else(invalid)?;
+    let is_token = |part: &str| !part.is_empty() && part.chars().all(|c| c.is_ascii_alphanumeric() || "!#$%&'*+-.^_`|~".contains(c));
+    if !is_token(top) || !is_token(sub) || parameters.chars().any(|c| c.is_control()) {
+        return Err(invalid());
+    }
+    Ok(())
+}
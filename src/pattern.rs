@@ -0,0 +1,190 @@
+use regex::{Regex, RegexBuilder};
+use std::fmt;
+
+// URL path patterns for rule files: globs, and regular expressions for what globs can't
+// express. Globs are translated to regexes, so both run on the `regex` crate's engine,
+// which matches in linear time whatever the pattern. Patterns are only matched while the
+// cache is built, never per request.
+#[derive(Clone, Debug)]
+pub struct Pattern {
+    regex: Regex,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PatternError {
+    pub pattern: String,
+    pub reason: String,
+}
+
+impl fmt::Display for PatternError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid pattern '{}': {}", self.pattern, self.reason)
+    }
+}
+
+impl std::error::Error for PatternError {}
+
+// Bound on the compiled size of one pattern - path patterns never come near it, but a
+// large counted repeat would otherwise be accepted and take the memory it asks for
+const SIZE_LIMIT: usize = 1 << 20;
+
+impl Pattern {
+    // Glob over the whole path: `*` and `?` stay within a segment, `**` crosses them,
+    // `[a-z]`/`[!a-z]` are classes and `{a,b}` alternatives. A pattern without a `/`
+    // matches the file name in any directory (`*.html`); otherwise it is rooted.
+    pub fn glob(pattern: &str) -> Result<Self, PatternError> {
+        let error = |reason: &str| PatternError { pattern: pattern.to_string(), reason: reason.to_string() };
+        if pattern.is_empty() {
+            return Err(error("empty pattern"));
+        }
+
+        let mut regex = String::from("^");
+        if !pattern.contains('/') {
+            regex.push_str("(?:.*/)?");
+        } else if !pattern.starts_with('/') {
+            regex.push('/');
+        }
+        let mut parser = GlobParser { chars: pattern.chars().collect(), pos: 0 };
+        regex.push_str(&parser.sequence(false).map_err(error)?);
+        if parser.pos < parser.chars.len() {
+            return Err(error("unbalanced '}'"));
+        }
+        regex.push('$');
+        Self::compile(pattern, &regex)
+    }
+
+    // Regular expression in the `regex` crate's syntax, matched anywhere in the path
+    // unless anchored with `^`/`$`
+    pub fn regex(pattern: &str) -> Result<Self, PatternError> {
+        Self::compile(pattern, pattern)
+    }
+
+    fn compile(pattern: &str, regex: &str) -> Result<Self, PatternError> {
+        let regex = RegexBuilder::new(regex).size_limit(SIZE_LIMIT).build().map_err(|e| PatternError {
+            pattern: pattern.to_string(),
+            reason: error_reason(&e),
+        })?;
+        Ok(Self { regex })
+    }
+
+    pub fn is_match(&self, path: &str) -> bool {
+        self.regex.is_match(path)
+    }
+}
+
+// Syntax errors render over several lines with a caret under the offending spot; rule
+// file errors are one line, so only the final description is kept
+fn error_reason(error: &regex::Error) -> String {
+    match error {
+        regex::Error::CompiledTooBig(_) => "pattern too large".to_string(),
+        error => {
+            let message = error.to_string();
+            let last = message.lines().last().unwrap_or_default();
+            last.strip_prefix("error: ").unwrap_or(last).to_string()
+        }
+    }
+}
+
+struct GlobParser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl GlobParser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        let matched = self.peek() == Some(c);
+        if matched {
+            self.pos += 1;
+        }
+        matched
+    }
+
+    // Glob up to the end, or up to `,`/`}` inside braces, as a regex
+    fn sequence(&mut self, in_braces: bool) -> Result<String, &'static str> {
+        let mut translated = String::new();
+        while let Some(c) = self.peek() {
+            match c {
+                '}' => break,
+                ',' if in_braces => break,
+                '*' => {
+                    self.pos += 1;
+                    if self.eat('*') {
+                        // `**/` also matches no directory at all
+                        translated.push_str(if self.eat('/') { "(?:.*/)?" } else { ".*" });
+                    } else {
+                        translated.push_str("[^/]*");
+                    }
+                }
+                '?' => {
+                    self.pos += 1;
+                    translated.push_str("[^/]");
+                }
+                '[' => {
+                    self.pos += 1;
+                    translated.push_str(&self.class()?);
+                }
+                '{' => {
+                    self.pos += 1;
+                    let mut alternatives = vec![self.sequence(true)?];
+                    while self.eat(',') {
+                        alternatives.push(self.sequence(true)?);
+                    }
+                    if !self.eat('}') {
+                        return Err("unterminated '{'");
+                    }
+                    translated.push_str(&format!("(?:{})", alternatives.join("|")));
+                }
+                '\\' => {
+                    self.pos += 1;
+                    let c = self.peek().ok_or("trailing '\\'")?;
+                    self.pos += 1;
+                    translated.push_str(&regex::escape(c.encode_utf8(&mut [0; 4])));
+                }
+                c => {
+                    self.pos += 1;
+                    translated.push_str(&regex::escape(c.encode_utf8(&mut [0; 4])));
+                }
+            }
+        }
+        Ok(translated)
+    }
+
+    // Bracket expression, after the `[`, as a regex class. A leading `]` is literal, and
+    // like `?`, a class never matches the separator.
+    fn class(&mut self) -> Result<String, &'static str> {
+        let negated = self.eat('!') || self.eat('^');
+        let mut items = String::new();
+        let mut first = true;
+        loop {
+            let c = self.peek().ok_or("unterminated '['")?;
+            self.pos += 1;
+            if c == ']' && !first {
+                break;
+            }
+            first = false;
+            let c = match c {
+                '\\' => {
+                    let escaped = self.peek().ok_or("unterminated '['")?;
+                    self.pos += 1;
+                    escaped
+                }
+                c => c,
+            };
+            items.push_str(&regex::escape(c.encode_utf8(&mut [0; 4])));
+            // A range, unless the `-` is last: `[a-]`
+            if self.peek() == Some('-') && self.chars.get(self.pos + 1).is_some_and(|&next| next != ']') {
+                self.pos += 1;
+                items.push('-');
+            }
+        }
+        if negated {
+            Ok(format!("[^/{}]", items))
+        } else {
+            Ok(format!("[[{}]&&[^/]]", items))
+        }
+    }
+}
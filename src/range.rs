@@ -0,0 +1,148 @@
+use std::time::SystemTime;
+
+// Upper bound on ranges per request - many tiny ranges are a known amplification vector
+pub const MAX_RANGES: usize = 16;
+
+// Inclusive byte range, already clamped to the representation length
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ByteRange {
+    pub start: u64,
+    pub end: u64,
+}
+
+impl ByteRange {
+    // Index range into the in-memory body
+    pub fn to_index_range(&self) -> std::ops::Range<usize> {
+        self.start as usize..self.end as usize + 1
+    }
+
+    // `Content-Range` value for this range of a `total`-byte representation
+    pub fn content_range(&self, total: u64) -> String {
+        format!("bytes {}-{}/{}", self.start, self.end, total)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum RangeRequest {
+    // Serve these ranges with 206 (sorted, overlapping or adjacent ranges coalesced)
+    Satisfiable(Vec<ByteRange>),
+    // Well-formed, but nothing overlaps the representation - 416
+    Unsatisfiable,
+    // Malformed or unsupported unit - ignore the header and serve 200 (RFC 9110 14.2)
+    Ignore,
+}
+
+// Parse a `Range` header value against a representation of `total` bytes
+pub fn parse_range(header: &[u8], total: u64) -> RangeRequest {
+    let header = match std::str::from_utf8(header) {
+        Ok(header) => header.trim(),
+        Err(_) => return RangeRequest::Ignore,
+    };
+
+    let spec = match header.split_once('=') {
+        Some((unit, spec)) if unit.trim().eq_ignore_ascii_case("bytes") => spec,
+        _ => return RangeRequest::Ignore,
+    };
+
+    let mut ranges = Vec::new();
+    let mut count = 0;
+    for part in spec.split(',') {
+        let part = part.trim();
+        // Empty list elements are allowed by the list syntax
+        if part.is_empty() {
+            continue;
+        }
+        count += 1;
+        if count > MAX_RANGES {
+            return RangeRequest::Ignore;
+        }
+
+        let (first, last) = match part.split_once('-') {
+            Some(bounds) => bounds,
+            None => return RangeRequest::Ignore,
+        };
+        let (first, last) = (first.trim(), last.trim());
+
+        let range = if first.is_empty() {
+            // Suffix range: the final N bytes
+            let suffix: u64 = match parse_digits(last) {
+                Some(suffix) => suffix,
+                None => return RangeRequest::Ignore,
+            };
+            if suffix == 0 || total == 0 {
+                continue;
+            }
+            ByteRange { start: total.saturating_sub(suffix), end: total - 1 }
+        } else {
+            let start = match parse_digits(first) {
+                Some(start) => start,
+                None => return RangeRequest::Ignore,
+            };
+            let end = if last.is_empty() {
+                u64::MAX
+            } else {
+                match parse_digits(last) {
+                    Some(end) if end >= start => end,
+                    _ => return RangeRequest::Ignore,
+                }
+            };
+            if start >= total {
+                continue;
+            }
+            ByteRange { start, end: end.min(total - 1) }
+        };
+        ranges.push(range);
+    }
+
+    if count == 0 {
+        return RangeRequest::Ignore;
+    }
+    if ranges.is_empty() {
+        return RangeRequest::Unsatisfiable;
+    }
+
+    RangeRequest::Satisfiable(coalesce(ranges))
+}
+
+// Merge overlapping or adjacent ranges so a client can't request the same bytes repeatedly
+fn coalesce(mut ranges: Vec<ByteRange>) -> Vec<ByteRange> {
+    if ranges.len() < 2 {
+        return ranges;
+    }
+    ranges.sort_by_key(|range| range.start);
+
+    let mut merged: Vec<ByteRange> = Vec::with_capacity(ranges.len());
+    for range in ranges {
+        match merged.last_mut() {
+            Some(last) if range.start <= last.end.saturating_add(1) => last.end = last.end.max(range.end),
+            _ => merged.push(range),
+        }
+    }
+    merged
+}
+
+fn parse_digits(value: &str) -> Option<u64> {
+    if value.is_empty() || !value.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    value.parse().ok()
+}
+
+// If-Range evaluation (RFC 9110 13.1.5): the range applies only if the validator
+// still matches. Entity-tags use strong comparison, so weak ETags never match;
+// dates must equal Last-Modified exactly.
+pub fn if_range_matches(value: &[u8], etag: &str, last_modified: SystemTime) -> bool {
+    let value = match std::str::from_utf8(value) {
+        Ok(value) => value.trim(),
+        Err(_) => return false,
+    };
+
+    if value.starts_with('"') {
+        return !etag.starts_with("W/") && value == etag;
+    }
+    if value.starts_with("W/") {
+        return false;
+    }
+
+    httpdate::parse_http_date(value).is_ok_and(|date| date == last_modified)
+}
@@ -0,0 +1,361 @@
+use crate::uri::{encode_path, encode_query, parse_target};
+use rustc_hash::FxHashMap;
+use std::fmt;
+use std::sync::Arc;
+
+// Rules file read from the content root on every cache build; never served itself
+pub const REDIRECTS_FILE: &str = "_redirects";
+
+// Netlify-style redirect and rewrite rules, one per line:
+//
+//   # from              to                      [status][!]
+//   /home               /                       301
+//   /blog/*             /news/:splat            302
+//   /news/:year/:slug   /articles/:year-:slug
+//   /app/*              /app/index.html         200
+//   /docs/*             https://docs.example.com/:splat  301!
+//
+// The status defaults to 301; 302, 303, 307 and 308 also redirect, while 200 serves the
+// target's content under the requested URL. A trailing `*` captures the rest of the path
+// as `:splat`, and `:name` captures one segment. The first matching rule wins. Rules
+// don't apply to paths that match a file unless forced with `!`. Exact paths are found
+// with one hash probe; only rules with placeholders are matched in turn.
+#[derive(Clone, Debug, Default)]
+pub struct Redirects {
+    rules: Vec<RedirectRule>,
+    // Rule indices by exact path, in file order
+    exact: FxHashMap<String, Vec<usize>>,
+    // Indices of rules with placeholders or a splat, in file order
+    patterns: Vec<usize>,
+}
+
+#[derive(Clone, Debug)]
+struct RedirectRule {
+    // Segments of the `from` path; a trailing `*` is kept in `splat`
+    segments: Vec<Segment>,
+    splat: bool,
+    to: Vec<Part>,
+    status: u16,
+    force: bool,
+    // Complete response of a redirect whose Location never varies
+    prebuilt: Option<Arc<[u8]>>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Segment {
+    Literal(String),
+    Placeholder(String),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Part {
+    Literal(String),
+    Splat,
+    // Index of a `from` segment
+    Placeholder(usize),
+}
+
+// What a matching rule asks for
+#[derive(Clone, Debug, PartialEq)]
+pub enum Resolution {
+    Prebuilt(Arc<[u8]>),
+    Redirect { status: u16, location: String },
+    // Canonical path whose content is served instead
+    Rewrite(String),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RedirectError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for RedirectError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for RedirectError {}
+
+pub fn status_text(status: u16) -> &'static str {
+    match status {
+        301 => "Moved Permanently",
+        302 => "Found",
+        303 => "See Other",
+        307 => "Temporary Redirect",
+        308 => "Permanent Redirect",
+        _ => "",
+    }
+}
+
+impl Redirects {
+    // Invalid or unsupported lines are skipped and reported, as Netlify does, so one bad
+    // rule can't take the others down on a reload
+    pub fn parse(text: &str) -> (Self, Vec<RedirectError>) {
+        let mut redirects = Self::default();
+        let mut errors = Vec::new();
+
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            match parse_rule(line) {
+                Ok(rule) => redirects.push(rule),
+                Err(message) => errors.push(RedirectError { line: index + 1, message }),
+            }
+        }
+        (redirects, errors)
+    }
+
+    fn push(&mut self, rule: RedirectRule) {
+        let index = self.rules.len();
+        if rule.splat || rule.segments.iter().any(|segment| matches!(segment, Segment::Placeholder(_))) {
+            self.patterns.push(index);
+        } else {
+            self.exact.entry(join_segments(&rule.segments)).or_default().push(index);
+        }
+        self.rules.push(rule);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    // Render the response of every redirect with a fixed Location once, at build time
+    pub fn prebuild(&mut self, render: impl Fn(u16, &str) -> Vec<u8>) {
+        for rule in &mut self.rules {
+            if rule.status != 200 {
+                if let [Part::Literal(location)] = rule.to.as_slice() {
+                    rule.prebuilt = Some(Arc::from(render(rule.status, location)));
+                }
+            }
+        }
+    }
+
+    // First rule matching a canonical path. `shadowed` means a file exists at the path,
+    // leaving only forced rules.
+    pub fn resolve(&self, path: &str, query: Option<&str>, shadowed: bool) -> Option<Resolution> {
+        if self.rules.is_empty() {
+            return None;
+        }
+        let trimmed = path.strip_suffix('/').unwrap_or(path);
+        let segments: Vec<&str> = trimmed.split('/').skip(1).collect();
+        let applies = |index: &usize| !shadowed || self.rules[*index].force;
+
+        let exact = self.exact.get(trimmed).and_then(|indices| indices.iter().copied().find(applies));
+        let pattern = self
+            .patterns
+            .iter()
+            .copied()
+            .take_while(|&index| exact.map_or(true, |exact| index < exact))
+            .filter(applies)
+            .find_map(|index| self.rules[index].captures(&segments).map(|captures| (index, captures)));
+
+        let (rule, captures) = match (pattern, exact) {
+            (Some((index, captures)), _) => (&self.rules[index], captures),
+            (None, Some(index)) => (&self.rules[index], Captures::default()),
+            (None, None) => return None,
+        };
+        let target = rule.target(&captures);
+
+        if rule.status == 200 {
+            return parse_target(&target).ok().map(|target| Resolution::Rewrite(target.path.into_owned()));
+        }
+        // The request's query carries over unless the target sets its own, escaped so
+        // no byte of it can end the Location header
+        match query.filter(|query| !query.is_empty() && !target.contains('?')) {
+            Some(query) => Some(Resolution::Redirect {
+                status: rule.status,
+                location: format!("{}?{}", target, encode_query(query)),
+            }),
+            None => match &rule.prebuilt {
+                Some(response) => Some(Resolution::Prebuilt(Arc::clone(response))),
+                None => Some(Resolution::Redirect { status: rule.status, location: target }),
+            },
+        }
+    }
+}
+
+// Values captured from a request path, still decoded
+#[derive(Default)]
+struct Captures<'a> {
+    segments: Vec<&'a str>,
+    splat: String,
+}
+
+impl RedirectRule {
+    fn captures<'a>(&self, segments: &[&'a str]) -> Option<Captures<'a>> {
+        let fixed = self.segments.len();
+        let length_ok = if self.splat { segments.len() >= fixed } else { segments.len() == fixed };
+        if !length_ok {
+            return None;
+        }
+        let matched = self.segments.iter().zip(segments).all(|(pattern, segment)| match pattern {
+            Segment::Literal(literal) => literal == segment,
+            Segment::Placeholder(_) => !segment.is_empty(),
+        });
+        matched.then(|| Captures { segments: segments[..fixed].to_vec(), splat: segments[fixed..].join("/") })
+    }
+
+    // The target with captures substituted, percent-encoded for use as a URL
+    fn target(&self, captures: &Captures<'_>) -> String {
+        let mut target = String::new();
+        for part in &self.to {
+            match part {
+                Part::Literal(literal) => target.push_str(literal),
+                Part::Splat => target.push_str(&encode_path(&captures.splat)),
+                Part::Placeholder(index) => target.push_str(&encode_path(captures.segments[*index])),
+            }
+        }
+        target
+    }
+}
+
+// `from [to] [status][!]` - Netlify's query and condition matching isn't supported
+fn parse_rule(line: &str) -> Result<RedirectRule, String> {
+    let line = line.split(" #").next().unwrap_or(line);
+    let mut fields = line.split_whitespace();
+    let from = fields.next().unwrap_or_default();
+    let to = fields.next().ok_or_else(|| format!("'{}' has no target", from))?;
+    if !from.starts_with('/') {
+        return Err(format!("'{}' is not a path - domain rules are not supported", from));
+    }
+    if to.contains('=') && !to.starts_with('/') && !is_absolute_url(to) {
+        return Err("query parameter matching is not supported".to_string());
+    }
+
+    let (status, force) = match fields.next() {
+        None => (301, false),
+        Some(field) => {
+            let (code, force) = match field.strip_suffix('!') {
+                Some(code) => (code, true),
+                None => (field, false),
+            };
+            let status = match code.parse::<u16>() {
+                Ok(status @ (200 | 301 | 302 | 303 | 307 | 308)) => status,
+                Ok(status) => return Err(format!("status {} is not supported", status)),
+                Err(_) => return Err(format!("invalid status '{}'", field)),
+            };
+            (status, force)
+        }
+    };
+    if fields.next().is_some() {
+        return Err("conditions are not supported".to_string());
+    }
+
+    let (segments, splat) = parse_from(from)?;
+    if status == 200 && !to.starts_with('/') {
+        return Err("rewrites must target a local path - proxying is not supported".to_string());
+    }
+    if !to.starts_with('/') && !is_absolute_url(to) {
+        return Err(format!("'{}' is neither a path nor an http(s) URL", to));
+    }
+    let to = parse_to(to, &segments, splat);
+    Ok(RedirectRule { segments, splat, to, status, force, prebuilt: None })
+}
+
+fn is_absolute_url(value: &str) -> bool {
+    value.starts_with("http://") || value.starts_with("https://")
+}
+
+fn parse_from(from: &str) -> Result<(Vec<Segment>, bool), String> {
+    if from.contains('?') {
+        return Err("query parameter matching is not supported".to_string());
+    }
+    let path = parse_target(from).map_err(|e| format!("invalid path '{}': {}", from, e))?.path;
+    let trimmed = path.strip_suffix('/').unwrap_or(&path);
+    let (trimmed, splat) = match trimmed.strip_suffix("/*") {
+        Some(prefix) => (prefix, true),
+        None => (trimmed, false),
+    };
+
+    let mut segments = Vec::new();
+    for segment in trimmed.split('/').skip(1) {
+        if segment.contains('*') {
+            return Err("'*' is only supported as the last segment".to_string());
+        }
+        match segment.strip_prefix(':') {
+            Some(name) if name.is_empty() || !name.bytes().all(is_name_byte) => {
+                return Err(format!("invalid placeholder '{}'", segment));
+            }
+            Some(name) => segments.push(Segment::Placeholder(name.to_string())),
+            None => segments.push(Segment::Literal(segment.to_string())),
+        }
+    }
+    Ok((segments, splat))
+}
+
+// Split the target around `:splat` and the placeholders `from` defines; any other `:name`
+// (a port, say) stays literal. Non-ASCII characters are percent-encoded.
+fn parse_to(to: &str, segments: &[Segment], splat: bool) -> Vec<Part> {
+    let mut parts = Vec::new();
+    let mut literal = String::new();
+    let mut rest = to;
+    while let Some(colon) = rest.find(':') {
+        literal.push_str(&rest[..colon]);
+        let after = &rest[colon + 1..];
+        let length = after.bytes().take_while(|&b| is_name_byte(b)).count();
+        let name = &after[..length];
+        let placeholder = segments.iter().position(|segment| *segment == Segment::Placeholder(name.to_string()));
+        let part = match placeholder {
+            Some(index) => Some(Part::Placeholder(index)),
+            None if splat && name == "splat" => Some(Part::Splat),
+            None => None,
+        };
+        match part {
+            Some(part) => {
+                if !literal.is_empty() {
+                    parts.push(Part::Literal(encode_non_ascii(&literal)));
+                    literal.clear();
+                }
+                parts.push(part);
+            }
+            None => {
+                literal.push(':');
+                literal.push_str(name);
+            }
+        }
+        rest = &after[length..];
+    }
+    literal.push_str(rest);
+    if !literal.is_empty() {
+        parts.push(Part::Literal(encode_non_ascii(&literal)));
+    }
+    parts
+}
+
+fn is_name_byte(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || byte == b'_'
+}
+
+fn join_segments(segments: &[Segment]) -> String {
+    let mut path = String::new();
+    for segment in segments {
+        if let Segment::Literal(literal) = segment {
+            path.push('/');
+            path.push_str(literal);
+        }
+    }
+    path
+}
+
+fn encode_non_ascii(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for c in value.chars() {
+        if c.is_ascii() {
+            encoded.push(c);
+        } else {
+            let mut buffer = [0; 4];
+            for byte in c.encode_utf8(&mut buffer).bytes() {
+                encoded.push_str(&format!("%{:02X}", byte));
+            }
+        }
+    }
+    encoded
+}
@@ -0,0 +1,285 @@
+use std::ops::Range;
+
+const READ_CHUNK: usize = 4096;
+
+// Request bodies are never used, only skipped so the next request parses from the right
+// offset. Anything larger is refused rather than read just to be thrown away.
+pub const MAX_DISCARDED_BODY: u64 = 64 * 1024;
+
+// Size and count limits for a request head, enforced while it is still arriving so an
+// oversized head is rejected before it is buffered whole
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct HeadLimits {
+    pub request_line: usize,
+    pub header_line: usize,
+    pub header_block: usize,
+    pub header_count: usize,
+}
+
+// Why a request head was refused. None of these can be answered and then skipped - the
+// connection closes after the error response.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Rejection {
+    RequestLine,
+    HeaderLine,
+    HeaderBlock,
+    HeaderCount,
+    // Framing a proxy in front could read differently (RFC 9112 2.2, 5.1, 5.2, 6.1-6.3)
+    BareLf,
+    ObsFold,
+    HeaderName,
+    TransferEncoding,
+    ContentLength,
+    BodyTooLarge,
+}
+
+impl Rejection {
+    pub const ALL: [Rejection; 10] = [
+        Rejection::RequestLine,
+        Rejection::HeaderLine,
+        Rejection::HeaderBlock,
+        Rejection::HeaderCount,
+        Rejection::BareLf,
+        Rejection::ObsFold,
+        Rejection::HeaderName,
+        Rejection::TransferEncoding,
+        Rejection::ContentLength,
+        Rejection::BodyTooLarge,
+    ];
+
+    // 414 for an over-long request line (in practice, the target), 431 for headers,
+    // 413 for a body too large to skip, 400 for malformed framing
+    pub fn status(self) -> u16 {
+        match self {
+            Rejection::RequestLine => 414,
+            Rejection::HeaderLine | Rejection::HeaderBlock | Rejection::HeaderCount => 431,
+            Rejection::BodyTooLarge => 413,
+            _ => 400,
+        }
+    }
+
+    // Label used in logs and metrics
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Rejection::RequestLine => "request_line",
+            Rejection::HeaderLine => "header_line",
+            Rejection::HeaderBlock => "header_block",
+            Rejection::HeaderCount => "header_count",
+            Rejection::BareLf => "bare_lf",
+            Rejection::ObsFold => "obs_fold",
+            Rejection::HeaderName => "header_name",
+            Rejection::TransferEncoding => "transfer_encoding",
+            Rejection::ContentLength => "content_length",
+            Rejection::BodyTooLarge => "body_too_large",
+        }
+    }
+}
+
+// Where the next request stands in the buffered bytes
+#[derive(Debug, PartialEq)]
+pub enum HeadStatus {
+    // A complete head; the range spans the request line and headers without the final
+    // CRLF, and `consumed` bytes (blank line included) belong to this request. `body`
+    // bytes follow and are discarded before the next request.
+    Complete { head: Range<usize>, consumed: usize, body: u64 },
+    // More bytes are needed
+    Partial,
+    // The head broke a limit or is malformed; the connection must close
+    Rejected(Rejection),
+}
+
+// Progress through the pending head, kept between reads so a head trickling in a few
+// bytes at a time is scanned once rather than from the start on every read
+#[derive(Clone, Copy, Default)]
+struct Scan {
+    // Offset of the first line not yet complete, relative to the pending bytes
+    line_start: usize,
+    // Start of the request line, past any leading empty lines
+    request_start: usize,
+    // Where the header block starts, once the request line is complete
+    headers_start: Option<usize>,
+    header_count: usize,
+    content_length: Option<u64>,
+}
+
+// One persistent buffer per connection. Reads append to it, request heads are parsed in
+// place, and bytes past a head - the start of a pipelined request - stay buffered for the
+// next one instead of being discarded.
+pub struct RequestBuffer {
+    buf: Vec<u8>,
+    start: usize,
+    scan: Scan,
+    // Body bytes of the previous request still to be skipped
+    discard: u64,
+}
+
+impl RequestBuffer {
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self { buf: Vec::with_capacity(capacity), start: 0, scan: Scan::default(), discard: 0 }
+    }
+
+    // Unconsumed bytes
+    pub fn pending(&self) -> &[u8] {
+        &self.buf[self.start..]
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.buf.len()
+    }
+
+    // Locate the next request head, checking limits and framing line by line. Every line
+    // must end in CRLF. Empty lines before a request line are skipped (RFC 9112 2.2) and
+    // count towards the request line limit.
+    pub fn next_head(&mut self, limits: &HeadLimits) -> HeadStatus {
+        if self.discard > 0 {
+            let skipped = self.discard.min(self.pending().len() as u64);
+            self.consume(skipped as usize);
+            self.discard -= skipped;
+            if self.discard > 0 {
+                return HeadStatus::Partial;
+            }
+        }
+
+        let pending = &self.buf[self.start..];
+        let scan = &mut self.scan;
+
+        loop {
+            let rest = &pending[scan.line_start..];
+            let newline = rest.iter().position(|&b| b == b'\n');
+            let line = &rest[..newline.unwrap_or(rest.len())];
+            let line = line.strip_suffix(b"\r").unwrap_or(line);
+            if let Some(newline) = newline {
+                if newline == 0 || rest[newline - 1] != b'\r' {
+                    return HeadStatus::Rejected(Rejection::BareLf);
+                }
+            }
+
+            match scan.headers_start {
+                None if newline.is_some() && line.is_empty() => {
+                    scan.line_start += newline.unwrap_or_default() + 1;
+                    scan.request_start = scan.line_start;
+                    if scan.line_start > limits.request_line {
+                        return HeadStatus::Rejected(Rejection::RequestLine);
+                    }
+                    continue;
+                }
+                None if line.len() > limits.request_line => return HeadStatus::Rejected(Rejection::RequestLine),
+                None => {}
+                Some(headers_start) => {
+                    if newline.is_some() && line.is_empty() {
+                        // Blank line: the head is complete
+                        let body = scan.content_length.unwrap_or(0);
+                        if body > MAX_DISCARDED_BODY {
+                            return HeadStatus::Rejected(Rejection::BodyTooLarge);
+                        }
+                        let head = self.start + scan.request_start..self.start + scan.line_start - 2;
+                        let consumed = scan.line_start + 2;
+                        self.scan = Scan::default();
+                        return HeadStatus::Complete { head, consumed, body };
+                    }
+                    if line.len() > limits.header_line {
+                        return HeadStatus::Rejected(Rejection::HeaderLine);
+                    }
+                    if scan.line_start + line.len() - headers_start > limits.header_block {
+                        return HeadStatus::Rejected(Rejection::HeaderBlock);
+                    }
+                    if newline.is_some() {
+                        scan.header_count += 1;
+                        if scan.header_count > limits.header_count {
+                            return HeadStatus::Rejected(Rejection::HeaderCount);
+                        }
+                        if let Err(rejection) = check_header_line(line, &mut scan.content_length) {
+                            return HeadStatus::Rejected(rejection);
+                        }
+                    }
+                }
+            }
+
+            match newline {
+                Some(newline) => {
+                    scan.line_start += newline + 1;
+                    scan.headers_start.get_or_insert(scan.line_start);
+                }
+                None => return HeadStatus::Partial,
+            }
+        }
+    }
+
+    pub fn get(&self, range: Range<usize>) -> &[u8] {
+        &self.buf[range]
+    }
+
+    // Mark `len` pending bytes as handled
+    pub fn consume(&mut self, len: usize) {
+        self.start = (self.start + len).min(self.buf.len());
+        self.scan = Scan::default();
+        if self.start == self.buf.len() {
+            self.buf.clear();
+            self.start = 0;
+        }
+    }
+
+    // Skip a request body, whether it is already buffered or still to arrive
+    pub fn discard(&mut self, len: u64) {
+        self.discard += len;
+    }
+
+    // Space to read into: consumed bytes are dropped first so the buffer doesn't grow
+    // across a long-lived connection, and at least READ_CHUNK bytes are free
+    pub fn spare(&mut self) -> &mut Vec<u8> {
+        if self.start > 0 {
+            self.buf.drain(..self.start);
+            self.start = 0;
+        }
+        self.buf.reserve(READ_CHUNK);
+        &mut self.buf
+    }
+}
+
+// Framing checks for one complete header line, CRLF already stripped. Only the headers
+// that decide where the request ends are interpreted here.
+fn check_header_line(line: &[u8], content_length: &mut Option<u64>) -> Result<(), Rejection> {
+    // Continuation lines (obs-fold) are unfolded differently by different intermediaries
+    if line.first().is_some_and(|&b| b == b' ' || b == b'\t') {
+        return Err(Rejection::ObsFold);
+    }
+
+    // A field name is a token, directly followed by the colon
+    let colon = line.iter().position(|&b| b == b':').ok_or(Rejection::HeaderName)?;
+    let name = &line[..colon];
+    if name.is_empty() || name.iter().any(|&b| b.is_ascii_whitespace()) {
+        return Err(Rejection::HeaderName);
+    }
+
+    if name.eq_ignore_ascii_case(b"transfer-encoding") {
+        // Request bodies are never used, so there is no reason to accept chunked framing
+        // - and every reason not to guess where it ends
+        return Err(Rejection::TransferEncoding);
+    }
+    if name.eq_ignore_ascii_case(b"content-length") {
+        // Exactly one plain decimal value: repeats and lists are refused, even when the
+        // values agree
+        let value = trim_ows(&line[colon + 1..]);
+        let parsed = match value {
+            [] => None,
+            value if value.iter().all(u8::is_ascii_digit) => std::str::from_utf8(value).ok().and_then(|value| value.parse().ok()),
+            _ => None,
+        };
+        match (parsed, &content_length) {
+            (Some(length), None) => *content_length = Some(length),
+            _ => return Err(Rejection::ContentLength),
+        }
+    }
+    Ok(())
+}
+
+fn trim_ows(value: &[u8]) -> &[u8] {
+    let start = value.iter().position(|&b| b != b' ' && b != b'\t').unwrap_or(value.len());
+    let end = value.iter().rposition(|&b| b != b' ' && b != b'\t').map_or(start, |end| end + 1);
+    &value[start..end]
+}
+
+// Split a request head into the request line and its header lines, line endings trimmed
+pub fn head_lines(head: &[u8]) -> impl Iterator<Item = &[u8]> {
+    head.split(|&b| b == b'\n').map(|line| line.strip_suffix(b"\r").unwrap_or(line))
+}
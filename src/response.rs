@@ -0,0 +1,201 @@
+use crate::clock::DateHeader;
+use std::io::IoSlice;
+use std::sync::Arc;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+
+// Status and size of a response - recorded in metrics and the access log
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sent {
+    pub status: u16,
+    pub bytes: u64,
+}
+
+impl Sent {
+    // Every response starts with a pre-built `HTTP/1.1 NNN` status line
+    pub fn of(response: &[u8], bytes: usize) -> Self {
+        let status = response
+            .get(9..12)
+            .and_then(|code| std::str::from_utf8(code).ok())
+            .and_then(|code| code.parse().ok())
+            .unwrap_or(0);
+        Sent { status, bytes: bytes as u64 }
+    }
+}
+
+// Part of a queued response: a pre-built template, a slice of a cached response, bytes
+// built for this request, or the `Date` header line
+#[derive(Debug)]
+pub enum Chunk {
+    Static(&'static [u8]),
+    Shared(Arc<[u8]>, std::ops::Range<usize>),
+    Owned(Vec<u8>),
+    Date(DateHeader),
+}
+
+impl Chunk {
+    pub fn shared(bytes: &Arc<[u8]>, range: std::ops::Range<usize>) -> Self {
+        Chunk::Shared(Arc::clone(bytes), range)
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        match self {
+            Chunk::Static(bytes) => bytes,
+            Chunk::Shared(bytes, range) => &bytes[range.clone()],
+            Chunk::Owned(bytes) => bytes,
+            Chunk::Date(header) => header,
+        }
+    }
+
+    // Part of a template or cached response, still borrowed rather than copied
+    fn slice(&self, range: std::ops::Range<usize>) -> Self {
+        match self {
+            Chunk::Static(bytes) => Chunk::Static(&bytes[range]),
+            Chunk::Shared(bytes, outer) => Chunk::shared(bytes, outer.start + range.start..outer.start + range.end),
+            chunk => Chunk::Owned(chunk.as_bytes()[range].to_vec()),
+        }
+    }
+}
+
+// Responses waiting to be written. Chunks reference cached responses rather than copying
+// them, and a flush hands the whole batch to one vectored write.
+pub struct ResponseQueue {
+    chunks: Vec<Chunk>,
+    len: usize,
+    // The connection closes after the next response, which then says so
+    pub closing: bool,
+    // The current `Date` header, and whether the server is draining - read per response
+    date: fn() -> DateHeader,
+    draining: fn() -> bool,
+}
+
+impl ResponseQueue {
+    pub fn new(date: fn() -> DateHeader, draining: fn() -> bool) -> Self {
+        Self { chunks: Vec::with_capacity(8), len: 0, closing: false, date, draining }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    // Queued bytes in write order
+    pub fn chunks(&self) -> impl Iterator<Item = &[u8]> {
+        self.chunks.iter().map(Chunk::as_bytes)
+    }
+
+    // Queue one response: the head (status line first), then any body parts
+    pub fn push(&mut self, head: Chunk, body: impl IntoIterator<Item = Chunk>) -> Sent {
+        let queued = self.len;
+        let bytes = head.as_bytes();
+        let status = Sent::of(bytes, 0).status;
+
+        // The current `Date` goes right after the status line, and on a closing connection
+        // (or while draining) `keep-alive` becomes `close`. Pre-built heads are split around
+        // both edits - the cached response is never copied. Only a closing connection pays
+        // for the header scan.
+        let status_end = bytes.windows(2).position(|w| w == b"\r\n").map_or(bytes.len(), |end| end + 2);
+        let keep_alive = if self.closing || (self.draining)() { keep_alive_value(bytes) } else { None };
+        let date = (self.date)();
+        match head {
+            Chunk::Owned(mut bytes) => {
+                if let Some(at) = keep_alive {
+                    bytes.splice(at..at + b"keep-alive".len(), b"close".iter().copied());
+                }
+                bytes.splice(status_end..status_end, date);
+                self.append(Chunk::Owned(bytes));
+            }
+            head => {
+                let end = head.as_bytes().len();
+                self.append(head.slice(0..status_end));
+                self.append(Chunk::Date(date));
+                match keep_alive {
+                    Some(at) => {
+                        self.append(head.slice(status_end..at));
+                        self.append(Chunk::Static(b"close"));
+                        self.append(head.slice(at + b"keep-alive".len()..end));
+                    }
+                    None => self.append(head.slice(status_end..end)),
+                }
+            }
+        }
+        for chunk in body {
+            self.append(chunk);
+        }
+
+        Sent { status, bytes: (self.len - queued) as u64 }
+    }
+
+    pub fn push_static(&mut self, response: &'static [u8]) -> Sent {
+        self.push(Chunk::Static(response), None)
+    }
+
+    // Last response on this connection - error paths that close it afterwards
+    pub fn push_final(&mut self, response: &'static [u8]) -> Sent {
+        self.closing = true;
+        self.push_static(response)
+    }
+
+    pub fn push_shared(&mut self, response: &Arc<[u8]>) -> Sent {
+        self.push(Chunk::shared(response, 0..response.len()), None)
+    }
+
+    pub fn push_owned(&mut self, response: Vec<u8>) -> Sent {
+        self.push(Chunk::Owned(response), None)
+    }
+
+    fn append(&mut self, chunk: Chunk) {
+        self.len += chunk.as_bytes().len();
+        self.chunks.push(chunk);
+    }
+
+    pub async fn flush<W: AsyncWrite + Unpin>(&mut self, stream: &mut W) -> std::io::Result<()> {
+        if self.chunks.is_empty() {
+            return Ok(());
+        }
+        let bufs: Vec<&[u8]> = self.chunks().collect();
+        write_all_vectored(stream, &bufs).await?;
+        self.chunks.clear();
+        self.len = 0;
+        Ok(())
+    }
+}
+
+// Write several buffers with as few syscalls as possible - keeps multi-part
+// responses zero-copy without giving up the single-write behaviour for small ones
+async fn write_all_vectored<W: AsyncWrite + Unpin>(stream: &mut W, bufs: &[&[u8]]) -> std::io::Result<()> {
+    let mut index = 0;
+    let mut offset = 0;
+
+    while index < bufs.len() {
+        let slices: Vec<IoSlice<'_>> = std::iter::once(IoSlice::new(&bufs[index][offset..]))
+            .chain(bufs[index + 1..].iter().map(|buf| IoSlice::new(buf)))
+            .collect();
+        let mut written = stream.write_vectored(&slices).await?;
+        if written == 0 {
+            return Err(std::io::Error::from(std::io::ErrorKind::WriteZero));
+        }
+
+        // Advance past fully written buffers, then into the partially written one
+        while index < bufs.len() && written >= bufs[index].len() - offset {
+            written -= bufs[index].len() - offset;
+            index += 1;
+            offset = 0;
+        }
+        offset += written;
+    }
+
+    stream.flush().await
+}
+
+// Offset of the `keep-alive` value of the Connection header, searched in the head only
+fn keep_alive_value(response: &[u8]) -> Option<usize> {
+    const NEEDLE: &[u8] = b"\r\nConnection: keep-alive\r\n";
+    let head_end = response.windows(4).position(|w| w == b"\r\n\r\n").map_or(response.len(), |end| end + 2);
+    response[..head_end]
+        .windows(NEEDLE.len())
+        .position(|w| w == NEEDLE)
+        .map(|at| at + b"\r\nConnection: ".len())
+}
@@ -0,0 +1,196 @@
+use crate::pattern::Pattern;
+use std::fmt;
+use std::io;
+use std::path::Path;
+
+// Headers derived from the file itself, the request or the connection. Rules can't
+// override them without breaking framing, negotiation or validation.
+const RESERVED_HEADERS: &[&str] = &[
+    "accept-ranges",
+    "connection",
+    "content-encoding",
+    "content-length",
+    "content-range",
+    "content-type",
+    "date",
+    "etag",
+    "keep-alive",
+    "last-modified",
+    "transfer-encoding",
+    "vary",
+];
+
+// Per-path response headers, applied when the cache is built. The file lists patterns,
+// each followed by indented header lines:
+//
+//   # Entry points must always revalidate
+//   *.html
+//     Cache-Control: no-cache
+//
+//   @hashed
+//     Cache-Control: public, max-age=31536000, immutable
+//
+//   ~ ^/downloads/.*\.(zip|tar\.gz)$
+//     Content-Disposition: attachment
+//
+// Patterns are globs, regexes after `~`, or `@hashed` for content-hashed file names.
+// Every matching rule applies in file order; a later rule replaces an earlier value for
+// the same header.
+#[derive(Clone, Debug, Default)]
+pub struct HeaderRules {
+    rules: Vec<HeaderRule>,
+}
+
+#[derive(Clone, Debug)]
+struct HeaderRule {
+    matcher: Matcher,
+    headers: Vec<(String, String)>,
+}
+
+#[derive(Clone, Debug)]
+enum Matcher {
+    Pattern(Pattern),
+    Hashed,
+}
+
+impl Matcher {
+    fn is_match(&self, path: &str) -> bool {
+        match self {
+            Matcher::Pattern(pattern) => pattern.is_match(path),
+            Matcher::Hashed => is_content_hashed(path),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum RuleError {
+    Io(io::Error),
+    Syntax { line: usize, message: String },
+}
+
+impl fmt::Display for RuleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RuleError::Io(e) => write!(f, "{}", e),
+            RuleError::Syntax { line, message } => write!(f, "line {}: {}", line, message),
+        }
+    }
+}
+
+impl std::error::Error for RuleError {}
+
+impl HeaderRules {
+    pub fn load(path: &Path) -> Result<Self, RuleError> {
+        let text = std::fs::read_to_string(path).map_err(RuleError::Io)?;
+        Self::parse(&text)
+    }
+
+    pub fn parse(text: &str) -> Result<Self, RuleError> {
+        let mut rules: Vec<HeaderRule> = Vec::new();
+        let mut pattern_line = 0;
+
+        for (index, line) in text.lines().enumerate() {
+            let number = index + 1;
+            let syntax = |message: String| RuleError::Syntax { line: number, message };
+            let trimmed = line.trim();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+
+            // Indented lines are headers of the pattern above
+            if line.starts_with([' ', '\t']) {
+                let rule = rules.last_mut().ok_or_else(|| syntax("header line before any pattern".to_string()))?;
+                let (name, value) = parse_header(trimmed).map_err(syntax)?;
+                rule.headers.push((name.to_string(), value.to_string()));
+                continue;
+            }
+
+            if let Some(rule) = rules.last() {
+                if rule.headers.is_empty() {
+                    if trimmed.contains(": ") {
+                        return Err(syntax("header lines must be indented".to_string()));
+                    }
+                    return Err(RuleError::Syntax { line: pattern_line, message: "pattern has no headers".to_string() });
+                }
+            }
+            let matcher = match trimmed.strip_prefix('~') {
+                Some(regex) => Matcher::Pattern(Pattern::regex(regex.trim()).map_err(|e| syntax(e.to_string()))?),
+                None if trimmed == "@hashed" => Matcher::Hashed,
+                None if trimmed.starts_with('@') => return Err(syntax(format!("unknown matcher '{}'", trimmed))),
+                None => Matcher::Pattern(Pattern::glob(trimmed).map_err(|e| syntax(e.to_string()))?),
+            };
+            rules.push(HeaderRule { matcher, headers: Vec::new() });
+            pattern_line = number;
+        }
+
+        if rules.last().is_some_and(|rule| rule.headers.is_empty()) {
+            return Err(RuleError::Syntax { line: pattern_line, message: "pattern has no headers".to_string() });
+        }
+        Ok(Self { rules })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    // Headers for a URL path, in the order they were first set
+    pub fn headers_for(&self, path: &str) -> Vec<(&str, &str)> {
+        let mut headers: Vec<(&str, &str)> = Vec::new();
+        for rule in self.rules.iter().filter(|rule| rule.matcher.is_match(path)) {
+            for (name, value) in &rule.headers {
+                match headers.iter_mut().find(|(existing, _)| existing.eq_ignore_ascii_case(name)) {
+                    Some(header) => header.1 = value,
+                    None => headers.push((name, value)),
+                }
+            }
+        }
+        headers
+    }
+}
+
+// `Name: value` with a token name and a non-empty value free of control characters
+fn parse_header(line: &str) -> Result<(&str, &str), String> {
+    let (name, value) = line.split_once(':').ok_or_else(|| format!("expected 'Name: value', got '{}'", line))?;
+    let (name, value) = (name.trim_end(), value.trim());
+
+    let is_tchar = |c: char| c.is_ascii_alphanumeric() || "!#$%&'*+-.^_`|~".contains(c);
+    if name.is_empty() || !name.chars().all(is_tchar) {
+        return Err(format!("invalid header name '{}'", name));
+    }
+    if RESERVED_HEADERS.iter().any(|reserved| name.eq_ignore_ascii_case(reserved)) {
+        return Err(format!("{} is set by the server", name));
+    }
+    if value.is_empty() {
+        return Err(format!("empty value for {}", name));
+    }
+    if value.chars().any(|c| c.is_control() && c != '\t') {
+        return Err(format!("control character in the value of {}", name));
+    }
+    Ok((name, value))
+}
+
+// Whether a file name carries a content hash, so its content can never change under that
+// name: `app.3f9a2c.js`, `main-8e2b1c0d4f.css`, `0a1b2c3d4e5f6a7b.js`. Only a hex digest of
+// at least six lower-case characters, mixing letters and digits, counts - as the whole
+// stem or a part of it between `.` and `-` - so mixed-case names like `Report22.pdf` or
+// `Invoice2024A.pdf` stay ordinary files. Heuristic by nature - only the file name is
+// inspected, and the extension is ignored.
+pub fn is_content_hashed(path: &str) -> bool {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    let stem = match name.rfind('.') {
+        Some(0) | None => name,
+        Some(dot) => &name[..dot],
+    };
+    stem.split(['.', '-']).any(is_hex_hash)
+}
+
+fn is_hex_hash(segment: &str) -> bool {
+    segment.len() >= 6
+        && segment.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+        && segment.bytes().any(|b| b.is_ascii_digit())
+        && segment.bytes().any(|b| b.is_ascii_lowercase())
+}
@@ -0,0 +1,108 @@
+// Security headers baked into every pre-generated response: cached files, their 304s and
+// the built-in templates. A profile gives the starting set; individual options then
+// replace or remove single headers.
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SecurityProfile {
+    // No security headers - for deployments where a proxy adds them
+    None,
+    // Safe for any site: nosniff, a referrer policy browsers already default to, and no
+    // framing by other origins
+    Baseline,
+    // Locked down: same-origin CSP, no framing, cross-origin isolation (needed for
+    // SharedArrayBuffer in WASM pages) and HSTS
+    Strict,
+    // Only `X-Content-Type-Options: nosniff` plus the individually configured headers -
+    // the default, as every response carried before profiles existed
+    Custom,
+}
+
+const BASELINE: &[(&str, &str)] = &[
+    ("X-Content-Type-Options", "nosniff"),
+    ("Referrer-Policy", "strict-origin-when-cross-origin"),
+    ("X-Frame-Options", "SAMEORIGIN"),
+];
+
+const STRICT: &[(&str, &str)] = &[
+    ("X-Content-Type-Options", "nosniff"),
+    ("Referrer-Policy", "no-referrer"),
+    ("X-Frame-Options", "DENY"),
+    (
+        "Content-Security-Policy",
+        "default-src 'self'; object-src 'none'; base-uri 'self'; form-action 'self'; frame-ancestors 'none'",
+    ),
+    ("Permissions-Policy", "camera=(), microphone=(), geolocation=(), payment=(), usb=()"),
+    ("Cross-Origin-Opener-Policy", "same-origin"),
+    ("Cross-Origin-Embedder-Policy", "require-corp"),
+    ("Cross-Origin-Resource-Policy", "same-origin"),
+    ("Strict-Transport-Security", "max-age=63072000; includeSubDomains"),
+];
+
+const CUSTOM: &[(&str, &str)] = &[("X-Content-Type-Options", "nosniff")];
+
+// Options that set a single security header, on top of any profile
+pub const SECURITY_HEADER_OPTIONS: &[(&str, &str)] = &[
+    ("csp", "Content-Security-Policy"),
+    ("hsts", "Strict-Transport-Security"),
+    ("referrer-policy", "Referrer-Policy"),
+    ("permissions-policy", "Permissions-Policy"),
+    ("frame-options", "X-Frame-Options"),
+    ("coop", "Cross-Origin-Opener-Policy"),
+    ("coep", "Cross-Origin-Embedder-Policy"),
+];
+
+impl SecurityProfile {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "none" | "off" => Some(SecurityProfile::None),
+            "baseline" => Some(SecurityProfile::Baseline),
+            "strict" => Some(SecurityProfile::Strict),
+            "custom" => Some(SecurityProfile::Custom),
+            _ => None,
+        }
+    }
+
+    pub fn headers(self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            SecurityProfile::None => &[],
+            SecurityProfile::Baseline => BASELINE,
+            SecurityProfile::Strict => STRICT,
+            SecurityProfile::Custom => CUSTOM,
+        }
+    }
+}
+
+// Resolved header set: the profile's headers, with `overrides` replacing a header of the
+// same name (or removing it when the value is None) and adding any the profile lacks
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SecurityHeaders {
+    headers: Vec<(&'static str, String)>,
+}
+
+impl SecurityHeaders {
+    pub fn new(profile: SecurityProfile, overrides: &[(&'static str, Option<String>)]) -> Self {
+        let mut headers: Vec<(&'static str, String)> =
+            profile.headers().iter().map(|&(name, value)| (name, value.to_string())).collect();
+        for (name, value) in overrides {
+            let existing = headers.iter().position(|(existing, _)| existing.eq_ignore_ascii_case(name));
+            match (existing, value) {
+                (Some(index), Some(value)) => headers[index].1 = value.clone(),
+                (Some(index), None) => {
+                    headers.remove(index);
+                }
+                (None, Some(value)) => headers.push((name, value.clone())),
+                (None, None) => {}
+            }
+        }
+        Self { headers }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&'static str, &str)> + '_ {
+        self.headers.iter().map(|(name, value)| (*name, value.as_str()))
+    }
+
+    // `Name: value\r\n` lines, ready to be placed in a response head
+    pub fn render(&self) -> String {
+        self.headers.iter().map(|(name, value)| format!("{}: {}\r\n", name, value)).collect()
+    }
+}
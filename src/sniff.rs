@@ -0,0 +1,130 @@
+use crate::MimeType;
+
+// Content sniffing for files whose name says nothing about their type, run once per cache
+// build. Signatures follow the WHATWG MIME Sniffing standard, plus the web-relevant
+// formats it leaves out (WASM, compressed archives, modern media). Short signatures that
+// ordinary text can start with, such as BMP's `BM`, are left out.
+
+// Signatures at offset 0
+const MAGIC: &[(&[u8], MimeType)] = &[
+    (b"%PDF-", MimeType::Pdf),
+    (b"\x89PNG\r\n\x1a\n", MimeType::Png),
+    (b"\xff\xd8\xff", MimeType::Jpeg),
+    (b"GIF87a", MimeType::Gif),
+    (b"GIF89a", MimeType::Gif),
+    (b"\x00\x00\x01\x00", MimeType::Icon),
+    (b"\x00asm", MimeType::Wasm),
+    (b"wOFF", MimeType::Woff),
+    (b"wOF2", MimeType::Woff2),
+    (b"\x00\x01\x00\x00", MimeType::Ttf),
+    (b"OTTO\x00", MimeType::Otf),
+    (b"\x1f\x8b\x08", MimeType::Gzip),
+    (b"PK\x03\x04", MimeType::Zip),
+    (b"BZh", MimeType::Bzip2),
+    (b"\xfd7zXZ\x00", MimeType::Xz),
+    (b"\x28\xb5\x2f\xfd", MimeType::Zstd),
+    (b"7z\xbc\xaf\x27\x1c", MimeType::SevenZip),
+    (b"ID3", MimeType::Mp3),
+    (b"OggS\x00", MimeType::OggAudio),
+    (b"fLaC", MimeType::Flac),
+    (b"\x1a\x45\xdf\xa3", MimeType::Webm),
+];
+
+// Tags that open an HTML document; a space or `>` must follow
+const HTML_TAGS: &[&[u8]] = &[
+    b"<!DOCTYPE HTML", b"<HTML", b"<HEAD", b"<SCRIPT", b"<IFRAME", b"<H1", b"<DIV", b"<FONT", b"<TABLE", b"<A",
+    b"<STYLE", b"<TITLE", b"<B", b"<BODY", b"<BR", b"<P", b"<!--",
+];
+
+pub fn sniff(content: &[u8]) -> Option<MimeType> {
+    if let Some(&(_, mime_type)) = MAGIC.iter().find(|(magic, _)| content.starts_with(magic)) {
+        return Some(mime_type);
+    }
+    if let Some(mime_type) = sniff_container(content) {
+        return Some(mime_type);
+    }
+
+    let text = content.strip_prefix(b"\xef\xbb\xbf").unwrap_or(content);
+    let start = text.iter().position(|b| !b.is_ascii_whitespace()).unwrap_or(text.len());
+    let text = &text[start..];
+    let is_html = HTML_TAGS.iter().any(|tag| {
+        text.len() > tag.len() && text[..tag.len()].eq_ignore_ascii_case(tag) && matches!(text[tag.len()], b' ' | b'>')
+    });
+    if is_html {
+        return Some(MimeType::Html);
+    }
+    if text.len() > 4 && text[..4].eq_ignore_ascii_case(b"<svg") && matches!(text[4], b' ' | b'>' | b'\n' | b'\r' | b'\t') {
+        return Some(MimeType::Svg);
+    }
+    if text.starts_with(b"<?xml") {
+        return Some(MimeType::Xml);
+    }
+
+    // Anything else is text unless it holds bytes text never contains
+    let has_utf16_bom = content.starts_with(b"\xfe\xff") || content.starts_with(b"\xff\xfe");
+    let is_binary = content.iter().any(|&b| matches!(b, 0x00..=0x08 | 0x0b | 0x0e..=0x1a | 0x1c..=0x1f));
+    if has_utf16_bom || !is_binary {
+        return Some(MimeType::PlainText);
+    }
+    None
+}
+
+// RIFF and ISO base media files name their format a few bytes in
+fn sniff_container(content: &[u8]) -> Option<MimeType> {
+    if content.len() >= 12 && content.starts_with(b"RIFF") {
+        return match &content[8..12] {
+            b"WEBP" => Some(MimeType::Webp),
+            b"WAVE" => Some(MimeType::Wav),
+            _ => None,
+        };
+    }
+    if content.len() >= 12 && &content[4..8] == b"ftyp" {
+        return match &content[8..12] {
+            b"avif" | b"avis" => Some(MimeType::Avif),
+            b"M4A " => Some(MimeType::Mp4Audio),
+            b"qt  " => Some(MimeType::QuickTime),
+            _ => Some(MimeType::Mp4),
+        };
+    }
+    None
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Charset {
+    Utf8,
+    // With a byte order mark, which tells the byte order
+    Utf16,
+    Latin1,
+    // Latin-1 plus printable characters in 0x80-0x9F, as legacy Windows editors write
+    Windows1252,
+}
+
+impl Charset {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Charset::Utf8 => "utf-8",
+            Charset::Utf16 => "utf-16",
+            Charset::Latin1 => "iso-8859-1",
+            Charset::Windows1252 => "windows-1252",
+        }
+    }
+}
+
+// A byte order mark wins; otherwise valid UTF-8 is UTF-8 (ASCII included), and anything
+// else is taken as a single-byte legacy encoding
+pub fn detect_charset(content: &[u8]) -> Charset {
+    if content.starts_with(b"\xef\xbb\xbf") {
+        return Charset::Utf8;
+    }
+    if content.starts_with(b"\xfe\xff") || content.starts_with(b"\xff\xfe") {
+        return Charset::Utf16;
+    }
+    if std::str::from_utf8(content).is_ok() {
+        return Charset::Utf8;
+    }
+    if content.iter().any(|b| (0x80..=0x9f).contains(b)) {
+        Charset::Windows1252
+    } else {
+        Charset::Latin1
+    }
+}
@@ -0,0 +1,31 @@
+// Single-page app fallback: client-side routes such as `/dashboard/settings` have no file
+// of their own, so a miss is answered with the app's entry document instead of a 404.
+// Only paths under one of the `include` prefixes and none of the `exclude` ones fall
+// back, and never a path that looks like an asset - a missing `/assets/app.js` must
+// stay a 404 rather than become HTML.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SpaFallback {
+    // Canonical path of the document served for client-side routes
+    pub document: String,
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+}
+
+impl SpaFallback {
+    // The document to serve for a canonical path that matched no file
+    pub fn document_for(&self, path: &str) -> Option<&str> {
+        let included = self.include.iter().any(|prefix| path.starts_with(prefix.as_str()));
+        let excluded = self.exclude.iter().any(|prefix| path.starts_with(prefix.as_str()));
+        if included && !excluded && !looks_like_asset(path) {
+            Some(&self.document)
+        } else {
+            None
+        }
+    }
+}
+
+// Assets have an extension in their last segment; routes don't
+pub fn looks_like_asset(path: &str) -> bool {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    name.rfind('.').is_some_and(|dot| dot > 0 && dot + 1 < name.len())
+}
@@ -0,0 +1,205 @@
+use std::borrow::Cow;
+use std::fmt;
+
+// Request targets are matched against the cache in one canonical form: percent-decoded,
+// with dot-segments removed and repeated slashes merged (RFC 3986 5.2.4 and 6.2.2). Cache
+// keys are built from file names with the same normalization, so `/my%20file.html`,
+// `/a//b.css` and `/docs/../index.html` all find the file they name.
+#[derive(Debug, PartialEq)]
+pub struct Target<'a> {
+    // Decoded and normalized, without the query
+    pub path: Cow<'a, str>,
+    pub query: Option<&'a str>,
+    canonical: bool,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum TargetError {
+    // Not a path starting with `/` (absolute-form, authority-form or `*`)
+    NotOriginForm,
+    BadPercentEncoding,
+    // `%00` - never part of a file name
+    EncodedNul,
+    // `%2F` - would make one segment look like two
+    EncodedSlash,
+    InvalidUtf8,
+    // A control byte, space or DEL, in the path or the query - never valid unescaped
+    // (RFC 3986 2), and a bare CR could end a header the target is copied into
+    InvalidCharacter,
+}
+
+impl fmt::Display for TargetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reason = match self {
+            TargetError::NotOriginForm => "target is not an absolute path",
+            TargetError::BadPercentEncoding => "malformed percent-encoding",
+            TargetError::EncodedNul => "encoded NUL byte",
+            TargetError::EncodedSlash => "encoded slash",
+            TargetError::InvalidUtf8 => "path is not UTF-8",
+            TargetError::InvalidCharacter => "control character or space in target",
+        };
+        write!(f, "{}", reason)
+    }
+}
+
+impl std::error::Error for TargetError {}
+
+impl Target<'_> {
+    // Whether the client already sent the canonical spelling - anything else gets a
+    // redirect to `canonical_uri`
+    pub fn is_canonical(&self) -> bool {
+        self.canonical
+    }
+
+    // The canonical spelling: the path re-encoded with uppercase escapes, only where
+    // RFC 3986 requires them, and the query with unsafe bytes escaped
+    pub fn canonical_uri(&self) -> String {
+        self.canonical_uri_for(&self.path)
+    }
+
+    // Canonical spelling of another path with this target's query, for redirects that
+    // also move the resource
+    pub fn canonical_uri_for(&self, path: &str) -> String {
+        let mut uri = encode_path(path);
+        if let Some(query) = self.query {
+            uri.push('?');
+            uri.push_str(&encode_query(query));
+        }
+        uri
+    }
+}
+
+pub fn parse_target(target: &str) -> Result<Target<'_>, TargetError> {
+    if target.bytes().any(is_forbidden_byte) {
+        return Err(TargetError::InvalidCharacter);
+    }
+    let (raw_path, query) = match target.split_once('?') {
+        Some((path, query)) => (path, Some(query)),
+        None => (target, None),
+    };
+    if !raw_path.starts_with('/') {
+        return Err(TargetError::NotOriginForm);
+    }
+
+    // Fast path: nothing to decode, merge or remove
+    if is_canonical_path(raw_path) {
+        return Ok(Target { path: Cow::Borrowed(raw_path), query, canonical: true });
+    }
+
+    let decoded = String::from_utf8(percent_decode(raw_path)?).map_err(|_| TargetError::InvalidUtf8)?;
+    let path = normalize_path(&decoded).into_owned();
+    let canonical = encode_path(&path) == raw_path;
+    Ok(Target { path: Cow::Owned(path), query, canonical })
+}
+
+// Bytes a request target may never contain as they are, only percent-encoded
+pub fn is_forbidden_byte(byte: u8) -> bool {
+    byte < 0x21 || byte == 0x7f
+}
+
+fn percent_decode(path: &str) -> Result<Vec<u8>, TargetError> {
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] != b'%' {
+            decoded.push(bytes[i]);
+            i += 1;
+            continue;
+        }
+        let hex = |offset: usize| bytes.get(i + offset).and_then(|&b| (b as char).to_digit(16));
+        let byte = match (hex(1), hex(2)) {
+            (Some(high), Some(low)) => (high * 16 + low) as u8,
+            _ => return Err(TargetError::BadPercentEncoding),
+        };
+        match byte {
+            0 => return Err(TargetError::EncodedNul),
+            b'/' => return Err(TargetError::EncodedSlash),
+            _ => decoded.push(byte),
+        }
+        i += 3;
+    }
+    Ok(decoded)
+}
+
+// Remove `.` and `..` segments and merge repeated slashes. `..` never climbs above the
+// root, and a trailing slash (or a final dot-segment) keeps the path directory-style.
+pub fn normalize_path(path: &str) -> Cow<'_, str> {
+    if is_normalized(path) {
+        return Cow::Borrowed(path);
+    }
+
+    let mut segments: Vec<&str> = Vec::new();
+    let mut trailing_slash = false;
+    let mut parts = path.split('/').skip(1).peekable();
+    while let Some(segment) = parts.next() {
+        let is_last = parts.peek().is_none();
+        match segment {
+            "" | "." => trailing_slash = is_last,
+            ".." => {
+                segments.pop();
+                trailing_slash = is_last;
+            }
+            segment => {
+                segments.push(segment);
+                trailing_slash = false;
+            }
+        }
+    }
+
+    let mut normalized = String::with_capacity(path.len());
+    for segment in &segments {
+        normalized.push('/');
+        normalized.push_str(segment);
+    }
+    if trailing_slash || segments.is_empty() {
+        normalized.push('/');
+    }
+    Cow::Owned(normalized)
+}
+
+fn is_normalized(path: &str) -> bool {
+    let mut segments = path.split('/').skip(1).peekable();
+    while let Some(segment) = segments.next() {
+        let is_last = segments.peek().is_none();
+        if (segment.is_empty() && !is_last) || segment == "." || segment == ".." {
+            return false;
+        }
+    }
+    path.starts_with('/')
+}
+
+fn is_canonical_path(path: &str) -> bool {
+    path.bytes().all(|b| is_pchar(b) || b == b'/') && is_normalized(path)
+}
+
+// RFC 3986 `pchar` without `%`: unreserved, sub-delims, `:` and `@`
+fn is_pchar(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b"-._~!$&'()*+,;=:@".contains(&b)
+}
+
+pub fn encode_path(path: &str) -> String {
+    let mut encoded = String::with_capacity(path.len());
+    encode_into(&mut encoded, path, |b| is_pchar(b) || b == b'/');
+    encoded
+}
+
+// A query with every byte outside RFC 3986 `query` escaped; existing escapes are kept
+pub fn encode_query(query: &str) -> String {
+    let mut encoded = String::with_capacity(query.len());
+    encode_into(&mut encoded, query, |b| is_pchar(b) || b == b'/' || b == b'?' || b == b'%');
+    encoded
+}
+
+fn encode_into(out: &mut String, text: &str, keep: impl Fn(u8) -> bool) {
+    const HEX: &[u8; 16] = b"0123456789ABCDEF";
+    for b in text.bytes() {
+        if keep(b) {
+            out.push(b as char);
+        } else {
+            out.push('%');
+            out.push(HEX[usize::from(b >> 4)] as char);
+            out.push(HEX[usize::from(b & 0xf)] as char);
+        }
+    }
+}
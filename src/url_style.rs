@@ -0,0 +1,108 @@
+// How directory and HTML URLs are spelled. The cache serves a directory's index for
+// `/docs/` only; these options pick one canonical spelling for each resource and redirect
+// the others to it, so every page has a single URL.
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TrailingSlash {
+    // `/docs/` serves the index and `/docs` is a 404, as without canonicalization
+    Off,
+    // `/docs` redirects to `/docs/`, which keeps relative links working
+    Add,
+    // `/docs` serves the index and `/docs/` redirects to it
+    Remove,
+}
+
+impl TrailingSlash {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "off" => Some(TrailingSlash::Off),
+            "add" | "always" => Some(TrailingSlash::Add),
+            "remove" | "never" => Some(TrailingSlash::Remove),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct UrlStyle {
+    pub trailing_slash: TrailingSlash,
+    // `/about` serves `about.html`, and `/about.html` redirects to `/about`
+    pub clean_urls: bool,
+    // Names that serve as a directory's index, in order of preference
+    pub index_files: Vec<String>,
+}
+
+// Outcome of resolving a canonical path against the cache
+#[derive(Debug, PartialEq)]
+pub enum Located<E> {
+    Found(E),
+    // The resource exists under this other canonical path
+    Redirect(String),
+    Missing,
+}
+
+impl UrlStyle {
+    // `lookup` is the cache, which already serves indexes for directory-style paths
+    pub fn locate<E>(&self, path: &str, lookup: impl Fn(&str) -> Option<E>) -> Located<E> {
+        if path == "/" {
+            return lookup(path).map_or(Located::Missing, Located::Found);
+        }
+
+        if let Some(directory) = path.strip_suffix('/') {
+            return match lookup(path) {
+                Some(_) if self.trailing_slash == TrailingSlash::Remove => Located::Redirect(directory.to_string()),
+                Some(entry) => Located::Found(entry),
+                None => Located::Missing,
+            };
+        }
+
+        if let Some(entry) = lookup(path) {
+            if self.clean_urls {
+                if let Some(clean) = self.clean_path(path, &lookup) {
+                    return Located::Redirect(clean);
+                }
+            }
+            return Located::Found(entry);
+        }
+
+        if self.clean_urls && !path.ends_with(".html") {
+            if let Some(entry) = lookup(&format!("{}.html", path)) {
+                return Located::Found(entry);
+            }
+        }
+        if self.trailing_slash != TrailingSlash::Off {
+            if let Some(entry) = lookup(&format!("{}/", path)) {
+                return match self.trailing_slash {
+                    TrailingSlash::Add => Located::Redirect(format!("{}/", path)),
+                    _ => Located::Found(entry),
+                };
+            }
+        }
+        Located::Missing
+    }
+
+    // The clean spelling of an existing `.html` file, unless another file already
+    // answers there: `/about.html` becomes `/about`, and `/docs/index.html` the
+    // directory URL
+    fn clean_path<E>(&self, path: &str, lookup: &impl Fn(&str) -> Option<E>) -> Option<String> {
+        let stem = path.strip_suffix(".html").filter(|stem| !stem.is_empty() && !stem.ends_with('/'))?;
+        let (directory, name) = path.split_at(path.rfind('/')? + 1);
+
+        if let Some(rank) = self.index_files.iter().position(|index| index == name) {
+            // Only the index the directory URL actually serves
+            let preferred = &self.index_files[..rank];
+            if preferred.iter().any(|index| lookup(&format!("{}{}", directory, index)).is_some()) {
+                return None;
+            }
+            let directory = match self.trailing_slash {
+                TrailingSlash::Remove if directory != "/" => directory.trim_end_matches('/'),
+                _ => directory,
+            };
+            return Some(directory.to_string());
+        }
+        match lookup(stem) {
+            Some(_) => None,
+            None => Some(stem.to_string()),
+        }
+    }
+}
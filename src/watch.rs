@@ -0,0 +1,85 @@
+use std::io;
+use std::path::PathBuf;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+// Content directory watcher - calls `on_change` once the tree has been quiet for
+// `debounce` after a burst of changes (ConfigMap updates touch several entries at once).
+// Runs on its own OS thread so rebuilds never compete with the async request path.
+#[cfg(target_os = "linux")]
+pub fn spawn_watcher<F>(root: PathBuf, debounce: Duration, on_change: F) -> io::Result<JoinHandle<()>>
+where
+    F: Fn() + Send + 'static,
+{
+    use inotify::Inotify;
+
+    let mut inotify = Inotify::init()?;
+    watch_tree(&inotify, &root)?;
+
+    std::thread::Builder::new()
+        .name("kiss-watcher".into())
+        .spawn(move || {
+            let mut buffer = [0u8; 4096];
+            loop {
+                if let Err(e) = inotify.read_events_blocking(&mut buffer) {
+                    eprintln!("Warning: content watcher stopped: {}", e);
+                    return;
+                }
+
+                // Debounce: keep draining until no new events arrive within the window
+                loop {
+                    std::thread::sleep(debounce);
+                    match inotify.read_events(&mut buffer) {
+                        Ok(_) => continue,
+                        Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                        Err(e) => {
+                            eprintln!("Warning: content watcher stopped: {}", e);
+                            return;
+                        }
+                    }
+                }
+
+                // New directories need their own watch - inotify is not recursive
+                if let Err(e) = watch_tree(&inotify, &root) {
+                    eprintln!("Warning: failed to refresh content watches: {}", e);
+                }
+                on_change();
+            }
+        })
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn spawn_watcher<F>(_root: PathBuf, _debounce: Duration, _on_change: F) -> io::Result<JoinHandle<()>>
+where
+    F: Fn() + Send + 'static,
+{
+    Err(io::Error::new(io::ErrorKind::Unsupported, "content watching requires inotify (Linux)"))
+}
+
+// Register a watch on `dir` and every subdirectory; re-adding an existing watch is a no-op
+#[cfg(target_os = "linux")]
+fn watch_tree(inotify: &inotify::Inotify, dir: &std::path::Path) -> io::Result<()> {
+    use inotify::WatchMask;
+
+    // Only mutations - reading files during a rebuild must not trigger another one
+    let mask = WatchMask::CREATE
+        | WatchMask::DELETE
+        | WatchMask::MODIFY
+        | WatchMask::CLOSE_WRITE
+        | WatchMask::MOVED_FROM
+        | WatchMask::MOVED_TO
+        | WatchMask::DELETE_SELF
+        | WatchMask::MOVE_SELF
+        | WatchMask::ONLYDIR;
+
+    inotify.watches().add(dir, mask)?;
+
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        // Symlinked directories are not followed, matching cache discovery
+        if entry.file_type()?.is_dir() {
+            watch_tree(inotify, &entry.path())?;
+        }
+    }
+    Ok(())
+}
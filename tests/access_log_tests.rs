@@ -0,0 +1,144 @@
+use kiss::access_log::{AccessLogEntry, AccessLogFormat, AccessLogger};
+use std::io::{self, Write};
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+fn entry<'a>(path: &'a str, user_agent: Option<&'a [u8]>) -> AccessLogEntry<'a> {
+    AccessLogEntry {
+        client_ip: IpAddr::from([10, 0, 0, 7]),
+        timestamp: SystemTime::UNIX_EPOCH + Duration::from_millis(971_186_136_250), // 10 Oct 2000 13:55:36.250
+        method: "GET",
+        path,
+        version: "HTTP/1.1",
+        status: 200,
+        bytes: 2326,
+        duration: Duration::from_micros(1500),
+        user_agent,
+        referer: None,
+    }
+}
+
+#[cfg(test)]
+mod format_tests {
+    use super::*;
+
+    #[test]
+    fn test_common_log_format() {
+        let line = entry("/index.html", Some(b"curl/8.0")).format(AccessLogFormat::Common);
+        assert_eq!(line, r#"10.0.0.7 - - [10/Oct/2000:13:55:36 +0000] "GET /index.html HTTP/1.1" 200 2326"#);
+    }
+
+    #[test]
+    fn test_combined_log_format() {
+        let line = entry("/index.html", Some(b"curl/8.0")).format(AccessLogFormat::Combined);
+        assert_eq!(
+            line,
+            r#"10.0.0.7 - - [10/Oct/2000:13:55:36 +0000] "GET /index.html HTTP/1.1" 200 2326 "-" "curl/8.0""#
+        );
+    }
+
+    #[test]
+    fn test_json_format() {
+        let line = entry("/a?b=1", None).format(AccessLogFormat::Json);
+        assert_eq!(
+            line,
+            r#"{"time":"2000-10-10T13:55:36.250Z","client_ip":"10.0.0.7","method":"GET","path":"/a?b=1","protocol":"HTTP/1.1","status":200,"bytes":2326,"duration_us":1500,"user_agent":null,"referer":null}"#
+        );
+    }
+
+    #[test]
+    fn test_client_values_cannot_break_lines() {
+        let hostile: &[u8] = b"evil\" \n200 \xff";
+        let combined = entry("/\"x", Some(hostile)).format(AccessLogFormat::Combined);
+        assert!(!combined.contains('\n'));
+        assert!(combined.contains(r#""GET /\"x HTTP/1.1""#));
+        assert!(combined.ends_with(r#""evil\" \x0A200 \xFF""#));
+
+        let json = entry("/", Some(hostile)).format(AccessLogFormat::Json);
+        assert!(json.contains(r#""user_agent":"evil\" \u000a200 �""#));
+    }
+
+    #[test]
+    fn test_format_names() {
+        assert_eq!(AccessLogFormat::parse("JSON"), Some(AccessLogFormat::Json));
+        assert_eq!(AccessLogFormat::parse("clf"), Some(AccessLogFormat::Common));
+        assert_eq!(AccessLogFormat::parse("combined"), Some(AccessLogFormat::Combined));
+        assert_eq!(AccessLogFormat::parse("off"), Some(AccessLogFormat::Off));
+        assert_eq!(AccessLogFormat::parse("xml"), None);
+    }
+}
+
+#[cfg(test)]
+mod logger_tests {
+    use super::*;
+
+    // Shared in-memory sink; `gate` blocks writes to simulate a stalled stdout
+    #[derive(Clone, Default)]
+    struct Sink {
+        lines: Arc<Mutex<Vec<u8>>>,
+        gate: Arc<Mutex<()>>,
+    }
+
+    impl Write for Sink {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            let _gate = self.gate.lock().unwrap();
+            self.lines.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn wait_for_lines(sink: &Sink, count: usize) -> Vec<String> {
+        for _ in 0..200 {
+            let text = String::from_utf8(sink.lines.lock().unwrap().clone()).unwrap();
+            if text.lines().count() >= count {
+                return text.lines().map(str::to_string).collect();
+            }
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        panic!("Timed out waiting for {} log lines", count);
+    }
+
+    #[test]
+    fn test_lines_are_written() {
+        let sink = Sink::default();
+        let logger = AccessLogger::spawn_with_writer(AccessLogFormat::Common, 1, 16, sink.clone()).unwrap();
+        logger.log(&entry("/one", None));
+        logger.log(&entry("/two", None));
+
+        let lines = wait_for_lines(&sink, 2);
+        assert!(lines[0].contains("/one"));
+        assert!(lines[1].contains("/two"));
+    }
+
+    #[test]
+    fn test_sampling() {
+        let logger = AccessLogger::spawn_with_writer(AccessLogFormat::Json, 4, 16, io::sink()).unwrap();
+        let sampled = (0..100).filter(|_| logger.sample()).count();
+        assert_eq!(sampled, 25);
+
+        let logger = AccessLogger::spawn_with_writer(AccessLogFormat::Json, 1, 16, io::sink()).unwrap();
+        assert!((0..10).all(|_| logger.sample()));
+    }
+
+    #[test]
+    fn test_stalled_writer_drops_instead_of_blocking() {
+        let sink = Sink::default();
+        let stalled = sink.gate.lock().unwrap();
+        let logger = AccessLogger::spawn_with_writer(AccessLogFormat::Common, 1, 4, sink.clone()).unwrap();
+
+        // Returns immediately even though nothing can be written
+        let started = std::time::Instant::now();
+        for _ in 0..100 {
+            logger.log(&entry("/", None));
+        }
+        assert!(started.elapsed() < Duration::from_secs(1));
+        assert!(logger.dropped() >= 90, "Only the buffer (plus one in flight) is kept: {}", logger.dropped());
+
+        drop(stalled);
+        assert!(!wait_for_lines(&sink, 1).is_empty());
+    }
+}
@@ -1,8 +1,24 @@
+#![allow(clippy::unnecessary_map_or)]
+
+use kiss::cache::CacheEntry;
 use std::collections::HashMap;
 use std::fs;
+use std::sync::Arc;
 use std::time::SystemTime;
 use tempfile::TempDir;
 
+// A cache entry whose body and ETag are both `body`, enough to tell entries apart
+fn entry(body: &str) -> CacheEntry {
+    CacheEntry {
+        complete_response: Arc::from(body.as_bytes()),
+        headers_only: Arc::from(&b""[..]),
+        not_modified_response: Arc::from(&b""[..]),
+        last_modified_timestamp: SystemTime::UNIX_EPOCH,
+        etag: Arc::from(body),
+        variants: None,
+    }
+}
+
 #[cfg(test)]
 mod file_cache_tests {
     use super::*;
@@ -446,4 +462,416 @@ mod conditional_request_tests {
         
         println!("✓ No conditional headers test passed");
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod path_trie_tests {
+    use super::entry;
+    use kiss::cache::PathTrie;
+
+    // These two paths share the same 32-bit FNV-1a hash
+    const COLLIDING_A: &str = "/aavlo.html";
+    const COLLIDING_B: &str = "/a9pda.html";
+
+    fn body_of(trie: &PathTrie, path: &str) -> Option<String> {
+        trie.get(path).map(|e| String::from_utf8(e.complete_response.to_vec()).unwrap())
+    }
+
+    #[test]
+    fn test_colliding_paths_are_both_served() {
+        let (_, hash_a, _) = PathTrie::normalize_path_hash(COLLIDING_A);
+        let (_, hash_b, _) = PathTrie::normalize_path_hash(COLLIDING_B);
+        assert_eq!(hash_a, hash_b, "Fixture paths must collide");
+
+        let mut trie = PathTrie::new();
+        trie.insert(COLLIDING_A, entry("a"));
+        trie.insert(COLLIDING_B, entry("b"));
+
+        assert_eq!(body_of(&trie, COLLIDING_A).as_deref(), Some("a"));
+        assert_eq!(body_of(&trie, COLLIDING_B).as_deref(), Some("b"));
+        assert_eq!(trie.entry_count(), 2);
+
+        let collisions = trie.collisions();
+        assert_eq!(collisions.len(), 1);
+        assert_eq!(&*collisions[0].existing, COLLIDING_A);
+        assert_eq!(&*collisions[0].inserted, COLLIDING_B);
+    }
+
+    #[test]
+    fn test_hash_match_with_different_path_misses() {
+        let mut trie = PathTrie::new();
+        trie.insert(COLLIDING_A, entry("a"));
+
+        assert_eq!(body_of(&trie, COLLIDING_B), None, "Same hash must not serve the wrong file");
+        assert!(trie.collisions().is_empty());
+    }
+
+    #[test]
+    fn test_reinsert_replaces_without_collision() {
+        let mut trie = PathTrie::new();
+        trie.insert("/app.js", entry("v1"));
+        trie.insert("/app.js", entry("v2"));
+
+        assert_eq!(body_of(&trie, "/app.js").as_deref(), Some("v2"));
+        assert_eq!(trie.entry_count(), 1);
+        assert!(trie.collisions().is_empty());
+    }
+
+    #[test]
+    fn test_index_and_query_handling() {
+        let mut trie = PathTrie::new();
+        trie.insert("/index.html", entry("root"));
+        trie.insert("/docs/index.html", entry("docs"));
+
+        assert_eq!(body_of(&trie, "/").as_deref(), Some("root"));
+        assert_eq!(body_of(&trie, "/?v=1").as_deref(), Some("root"));
+        assert_eq!(body_of(&trie, "/docs/").as_deref(), Some("docs"));
+        assert_eq!(body_of(&trie, "/docs/index.html?x").as_deref(), Some("docs"));
+        assert_eq!(body_of(&trie, "/docs"), None);
+    }
+
+    #[test]
+    fn test_configured_index_files() {
+        let index_files = vec!["index.htm".to_string(), "index.html".to_string()];
+        let mut trie = PathTrie::with_index_files(index_files.clone());
+        trie.insert("/docs/index.html", entry("html"));
+        trie.insert("/docs/index.htm", entry("htm"));
+        trie.insert("/blog/index.html", entry("blog"));
+        assert_eq!(body_of(&trie, "/docs/").as_deref(), Some("htm"), "The first listed name wins");
+        assert_eq!(body_of(&trie, "/blog/").as_deref(), Some("blog"));
+
+        // Insertion order doesn't matter
+        let mut trie = PathTrie::with_index_files(index_files);
+        trie.insert("/docs/index.htm", entry("htm"));
+        trie.insert("/docs/index.html", entry("html"));
+        assert_eq!(body_of(&trie, "/docs/").as_deref(), Some("htm"));
+        assert_eq!(body_of(&trie, "/docs/index.html").as_deref(), Some("html"));
+
+        let mut trie = PathTrie::with_index_files(vec!["default.html".to_string()]);
+        trie.insert("/index.html", entry("plain"));
+        assert_eq!(body_of(&trie, "/"), None, "Only configured names are indexes");
+    }
+
+    #[test]
+    fn test_targets_are_canonicalized_like_keys() {
+        let mut trie = PathTrie::new();
+        trie.insert("/my file.html", entry("space"));
+        trie.insert("/caf\u{e9}/menu.txt", entry("utf8"));
+        trie.insert("/what?.txt", entry("question"));
+        trie.insert("/docs/index.html", entry("docs"));
+
+        assert_eq!(body_of(&trie, "/my%20file.html").as_deref(), Some("space"));
+        assert_eq!(body_of(&trie, "/caf%C3%A9/menu.txt").as_deref(), Some("utf8"));
+        assert_eq!(body_of(&trie, "/what%3F.txt").as_deref(), Some("question"), "? in a file name is not a query");
+        assert_eq!(body_of(&trie, "/what?.txt"), None);
+        assert_eq!(body_of(&trie, "/a/../docs//./").as_deref(), Some("docs"));
+        assert_eq!(body_of(&trie, "/docs%2Findex.html"), None, "Encoded slashes are rejected");
+    }
+}
+
+#[cfg(test)]
+mod generation_swap_tests {
+    use super::entry;
+    use kiss::cache::{GenerationDiff, OptimizedCache, PathTrie};
+    use kiss::redirects::{Redirects, Resolution};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+
+    fn trie_with(path: &str, body: &str) -> PathTrie {
+        let mut trie = PathTrie::new();
+        trie.insert(path, entry(body));
+        trie
+    }
+
+    #[test]
+    fn test_replace_publishes_new_generation() {
+        let cache = OptimizedCache::from_trie(trie_with("/a.txt", "old"));
+        assert!(cache.get("/a.txt").is_some());
+
+        let replacement = trie_with("/b.txt", "newer");
+        let replacement_bytes = replacement.cached_bytes();
+        cache.replace(replacement);
+
+        assert!(cache.get("/a.txt").is_none(), "Removed file should no longer be served");
+        assert_eq!(&*cache.get("/b.txt").unwrap().etag, "newer");
+        assert_eq!(cache.entry_count(), 1);
+        assert_eq!(cache.cached_bytes(), replacement_bytes, "Cached bytes follow the generation");
+    }
+
+    #[test]
+    fn test_replace_reports_diff() {
+        let mut old = trie_with("/same.txt", "same");
+        old.insert("/changed.txt", trie_with("/changed.txt", "v1").get("/changed.txt").unwrap());
+        old.insert("/removed.txt", trie_with("/removed.txt", "gone").get("/removed.txt").unwrap());
+        let cache = OptimizedCache::from_trie(old);
+
+        let mut new = trie_with("/same.txt", "same");
+        new.insert("/changed.txt", trie_with("/changed.txt", "v2").get("/changed.txt").unwrap());
+        new.insert("/added.txt", trie_with("/added.txt", "new").get("/added.txt").unwrap());
+
+        let diff = cache.replace(new);
+        assert_eq!(diff, GenerationDiff { added: 1, removed: 1, changed: 1 });
+    }
+
+    #[test]
+    fn test_redirects_swap_with_their_generation() {
+        let mut old = trie_with("/a.txt", "old");
+        old.set_redirects(Redirects::parse("/home  /a.txt  302\n").0);
+        let cache = OptimizedCache::from_trie(old);
+        let expected = Resolution::Redirect { status: 302, location: "/a.txt".to_string() };
+        assert_eq!(cache.resolve_redirect("/home", None, false), Some(expected));
+
+        cache.replace(trie_with("/a.txt", "new"));
+        assert_eq!(cache.resolve_redirect("/home", None, false), None, "Rules removed with the file go too");
+    }
+
+    #[test]
+    fn test_entries_outlive_their_generation() {
+        let cache = OptimizedCache::from_trie(trie_with("/a.txt", "old"));
+        let held = cache.get("/a.txt").unwrap();
+
+        // The generation is freed here, but the in-flight response bytes are not
+        cache.replace(PathTrie::new());
+        assert_eq!(&*held.complete_response, b"old");
+    }
+
+    #[test]
+    fn test_concurrent_reads_during_swaps() {
+        let cache = Arc::new(OptimizedCache::from_trie(trie_with("/x", "0")));
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let readers: Vec<_> = (0..4)
+            .map(|_| {
+                let cache = Arc::clone(&cache);
+                let stop = Arc::clone(&stop);
+                thread::spawn(move || {
+                    let mut reads = 0u64;
+                    loop {
+                        let entry = cache.get("/x").expect("Path present in every generation");
+                        // Body and ETag come from the same generation
+                        assert_eq!(&*entry.complete_response, entry.etag.as_bytes());
+                        reads += 1;
+                        if stop.load(Ordering::Relaxed) {
+                            break reads;
+                        }
+                    }
+                })
+            })
+            .collect();
+
+        for generation in 1..200 {
+            cache.replace(trie_with("/x", &generation.to_string()));
+        }
+        stop.store(true, Ordering::Relaxed);
+
+        let total: u64 = readers.into_iter().map(|r| r.join().unwrap()).sum();
+        assert!(total > 0);
+        assert_eq!(&*cache.get("/x").unwrap().etag, "199");
+        println!("✓ {} concurrent reads across 199 generation swaps", total);
+    }
+}
+
+#[cfg(test)]
+mod encoding_variant_tests {
+    use super::entry;
+    use kiss::cache::{CacheEntry, EncodedVariant, PathTrie};
+    use kiss::encoding::ContentEncoding;
+    use std::sync::Arc;
+
+    fn with_variants(body: &str, variants: &[(ContentEncoding, &str)]) -> CacheEntry {
+        let variants: Vec<EncodedVariant> =
+            variants.iter().map(|&(encoding, body)| EncodedVariant { encoding, entry: entry(body) }).collect();
+        CacheEntry { variants: Some(Arc::from(variants)), ..entry(body) }
+    }
+
+    #[test]
+    fn test_negotiate_selects_variant() {
+        let entry = with_variants("plain", &[(ContentEncoding::Brotli, "br"), (ContentEncoding::Gzip, "gz")]);
+
+        assert_eq!(&*entry.negotiate(None).etag, "plain", "No Accept-Encoding means identity");
+        assert_eq!(&*entry.negotiate(Some(b"gzip, deflate, br")).etag, "br");
+        assert_eq!(&*entry.negotiate(Some(b"gzip")).etag, "gz");
+        assert_eq!(&*entry.negotiate(Some(b"br;q=0.5, gzip;q=0.9")).etag, "gz");
+        assert_eq!(&*entry.negotiate(Some(b"zstd")).etag, "plain", "Unavailable coding falls back to identity");
+        assert_eq!(&*entry.negotiate(Some(b"br;q=0, gzip;q=0")).etag, "plain");
+    }
+
+    #[test]
+    fn test_cached_bytes_include_variants() {
+        let mut trie = PathTrie::new();
+        trie.insert("/docs/index.html", with_variants("12345", &[(ContentEncoding::Gzip, "123")]));
+        // The directory alias shares the entry and is not counted twice
+        assert_eq!(trie.cached_bytes(), 8);
+    }
+
+    #[test]
+    fn test_diff_detects_changed_variant() {
+        let mut before = PathTrie::new();
+        before.insert("/app.js", with_variants("js", &[(ContentEncoding::Brotli, "old")]));
+        let mut after = PathTrie::new();
+        after.insert("/app.js", with_variants("js", &[(ContentEncoding::Brotli, "new")]));
+
+        assert_eq!(after.diff(&before).changed, 1);
+        assert_eq!(before.diff(&before).changed, 0);
+    }
+}
+
+#[cfg(test)]
+mod precondition_tests {
+    use super::entry;
+    use kiss::cache::{CacheEntry, Precondition, Preconditions};
+    use std::time::{Duration, SystemTime};
+
+    // Last-Modified: Sun, 09 Sep 2001 01:46:40 GMT
+    const MODIFIED: &str = "Sun, 09 Sep 2001 01:46:40 GMT";
+    const EARLIER: &str = "Sat, 08 Sep 2001 01:46:40 GMT";
+    const LATER: &str = "Mon, 10 Sep 2001 01:46:40 GMT";
+
+    fn evaluate(etag: &str, headers: &[(&str, &str)]) -> Precondition {
+        let mut conditions = Preconditions::default();
+        for &(name, value) in headers {
+            let value = Some(value.as_bytes());
+            match name {
+                "If-Match" => conditions.if_match = value,
+                "If-None-Match" => conditions.if_none_match = value,
+                "If-Modified-Since" => conditions.if_modified_since = value,
+                "If-Unmodified-Since" => conditions.if_unmodified_since = value,
+                _ => unreachable!(),
+            }
+        }
+        let entry = CacheEntry {
+            last_modified_timestamp: SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000_000),
+            ..entry(etag)
+        };
+        entry.evaluate_preconditions(&conditions)
+    }
+
+    const WEAK: &str = "W/\"10-1000000000\"";
+    const STRONG: &str = "\"abc\"";
+
+    #[test]
+    fn test_unconditional_requests_proceed() {
+        assert_eq!(evaluate(WEAK, &[]), Precondition::Proceed);
+    }
+
+    #[test]
+    fn test_if_none_match_list_uses_weak_comparison() {
+        assert_eq!(evaluate(WEAK, &[("If-None-Match", "W/\"10-1000000000\"")]), Precondition::NotModified);
+        assert_eq!(evaluate(WEAK, &[("If-None-Match", "\"10-1000000000\"")]), Precondition::NotModified);
+        assert_eq!(evaluate(STRONG, &[("If-None-Match", "W/\"abc\"")]), Precondition::NotModified);
+        assert_eq!(evaluate(WEAK, &[("If-None-Match", "\"x\", W/\"10-1000000000\"")]), Precondition::NotModified);
+        assert_eq!(evaluate(WEAK, &[("If-None-Match", " , \"x\" ,, \"10-1000000000\" ")]), Precondition::NotModified);
+        assert_eq!(evaluate(WEAK, &[("If-None-Match", "*")]), Precondition::NotModified);
+
+        assert_eq!(evaluate(WEAK, &[("If-None-Match", "\"x\", \"y\"")]), Precondition::Proceed);
+        // Substrings and unquoted tags are not matches
+        assert_eq!(evaluate(WEAK, &[("If-None-Match", "\"10-1000000000-br\"")]), Precondition::Proceed);
+        assert_eq!(evaluate(WEAK, &[("If-None-Match", "10-1000000000")]), Precondition::Proceed);
+        assert_eq!(evaluate(WEAK, &[("If-None-Match", "\"x\" W/\"10-1000000000\"")]), Precondition::Proceed);
+    }
+
+    #[test]
+    fn test_if_none_match_takes_precedence_over_if_modified_since() {
+        // A changed ETag wins over a date that would have produced 304
+        assert_eq!(
+            evaluate(WEAK, &[("If-Modified-Since", LATER), ("If-None-Match", "\"other\"")]),
+            Precondition::Proceed
+        );
+        // A matching ETag wins over a date that would have produced 200
+        assert_eq!(
+            evaluate(WEAK, &[("If-Modified-Since", EARLIER), ("If-None-Match", WEAK)]),
+            Precondition::NotModified
+        );
+    }
+
+    #[test]
+    fn test_if_modified_since() {
+        assert_eq!(evaluate(WEAK, &[("If-Modified-Since", MODIFIED)]), Precondition::NotModified);
+        assert_eq!(evaluate(WEAK, &[("If-Modified-Since", LATER)]), Precondition::NotModified);
+        assert_eq!(evaluate(WEAK, &[("If-Modified-Since", EARLIER)]), Precondition::Proceed);
+        assert_eq!(evaluate(WEAK, &[("If-Modified-Since", "yesterday")]), Precondition::Proceed, "Invalid dates are ignored");
+    }
+
+    #[test]
+    fn test_if_match_uses_strong_comparison() {
+        assert_eq!(evaluate(STRONG, &[("If-Match", "\"abc\"")]), Precondition::Proceed);
+        assert_eq!(evaluate(STRONG, &[("If-Match", "\"x\", \"abc\"")]), Precondition::Proceed);
+        assert_eq!(evaluate(STRONG, &[("If-Match", "*")]), Precondition::Proceed);
+        assert_eq!(evaluate(STRONG, &[("If-Match", "W/\"abc\"")]), Precondition::Failed);
+        assert_eq!(evaluate(STRONG, &[("If-Match", "\"x\"")]), Precondition::Failed);
+        // A weak ETag never matches strongly, even itself
+        assert_eq!(evaluate(WEAK, &[("If-Match", WEAK)]), Precondition::Failed);
+        assert_eq!(evaluate(WEAK, &[("If-Match", "*")]), Precondition::Proceed);
+        assert_eq!(evaluate(STRONG, &[("If-Match", "\"abc")]), Precondition::Failed, "Malformed lists match nothing");
+    }
+
+    #[test]
+    fn test_if_unmodified_since() {
+        assert_eq!(evaluate(WEAK, &[("If-Unmodified-Since", MODIFIED)]), Precondition::Proceed);
+        assert_eq!(evaluate(WEAK, &[("If-Unmodified-Since", LATER)]), Precondition::Proceed);
+        assert_eq!(evaluate(WEAK, &[("If-Unmodified-Since", EARLIER)]), Precondition::Failed);
+        assert_eq!(evaluate(WEAK, &[("If-Unmodified-Since", "garbage")]), Precondition::Proceed);
+    }
+
+    #[test]
+    fn test_rfc_evaluation_order() {
+        // If-Match is evaluated first and makes If-Unmodified-Since irrelevant
+        assert_eq!(evaluate(STRONG, &[("If-Match", STRONG), ("If-Unmodified-Since", EARLIER)]), Precondition::Proceed);
+        assert_eq!(evaluate(STRONG, &[("If-Match", "\"x\""), ("If-Unmodified-Since", LATER)]), Precondition::Failed);
+
+        // 412 from step 1 or 2 wins over a 304 from step 3 or 4
+        assert_eq!(evaluate(STRONG, &[("If-Match", "\"x\""), ("If-None-Match", STRONG)]), Precondition::Failed);
+        assert_eq!(
+            evaluate(WEAK, &[("If-Unmodified-Since", EARLIER), ("If-Modified-Since", LATER)]),
+            Precondition::Failed
+        );
+
+        // Passing the first pair still lets the second produce a 304
+        assert_eq!(evaluate(STRONG, &[("If-Match", STRONG), ("If-None-Match", STRONG)]), Precondition::NotModified);
+        assert_eq!(
+            evaluate(WEAK, &[("If-Unmodified-Since", LATER), ("If-Modified-Since", MODIFIED)]),
+            Precondition::NotModified
+        );
+    }
+}
+
+#[cfg(test)]
+mod content_etag_tests {
+    use kiss::cache::{content_etag, EtagMode};
+    use kiss::digest::{sha256, to_hex};
+
+    #[test]
+    fn test_sha256_vectors() {
+        assert_eq!(to_hex(&sha256(b"")), "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855");
+        assert_eq!(to_hex(&sha256(b"abc")), "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+        // Two-block padding (56 bytes) and a multi-block message
+        assert_eq!(
+            to_hex(&sha256(b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq")),
+            "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1"
+        );
+        assert_eq!(
+            to_hex(&sha256(&vec![b'a'; 1_000_000])),
+            "cdc76e5c9914fb9281a1c7e284d73e67f1809a48a497200e046d39ccc7112cd0"
+        );
+    }
+
+    #[test]
+    fn test_content_etag_depends_only_on_content() {
+        let etag = content_etag(b"<h1>Hello</h1>");
+        assert!(etag.starts_with('"') && etag.ends_with('"'), "Strong ETags are unprefixed quoted strings");
+        assert_eq!(etag.len(), 34);
+        assert_eq!(etag, content_etag(b"<h1>Hello</h1>"));
+
+        // Same size, different bytes - the case weak size-mtime ETags get wrong
+        assert_ne!(etag, content_etag(b"<h1>Howdy</h1>"));
+        assert_eq!(content_etag(b""), "\"e3b0c44298fc1c149afbf4c8996fb924\"");
+    }
+
+    #[test]
+    fn test_etag_mode_parsing() {
+        assert_eq!(EtagMode::parse("weak"), Some(EtagMode::Weak));
+        assert_eq!(EtagMode::parse("Strong"), Some(EtagMode::Strong));
+        assert_eq!(EtagMode::parse("hash"), Some(EtagMode::Strong));
+        assert_eq!(EtagMode::parse("sha1"), None);
+    }
+}
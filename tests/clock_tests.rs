@@ -0,0 +1,57 @@
+use kiss::clock::{Clock, DATE_HEADER_LEN};
+use std::time::{Duration, SystemTime};
+
+#[cfg(test)]
+mod clock_tests {
+    use super::*;
+
+    fn at(seconds: u64) -> SystemTime {
+        SystemTime::UNIX_EPOCH + Duration::from_secs(seconds)
+    }
+
+    #[test]
+    fn test_date_header_format() {
+        let clock = Clock::at(at(1_000_000_000));
+        assert_eq!(&clock.date_header(), b"Date: Sun, 09 Sep 2001 01:46:40 GMT\r\n");
+
+        let clock = Clock::at(at(0));
+        assert_eq!(&clock.date_header(), b"Date: Thu, 01 Jan 1970 00:00:00 GMT\r\n");
+        assert_eq!(clock.date_header().len(), DATE_HEADER_LEN);
+    }
+
+    #[test]
+    fn test_header_follows_the_clock() {
+        let clock = Clock::at(at(1_000_000_000));
+        assert_eq!(&clock.date_header(), b"Date: Sun, 09 Sep 2001 01:46:40 GMT\r\n");
+
+        // Sub-second changes don't alter the date; the next second does
+        clock.set(at(1_000_000_000) + Duration::from_millis(999));
+        assert_eq!(&clock.date_header(), b"Date: Sun, 09 Sep 2001 01:46:40 GMT\r\n");
+        clock.set(at(1_000_000_001));
+        assert_eq!(&clock.date_header(), b"Date: Sun, 09 Sep 2001 01:46:41 GMT\r\n");
+    }
+
+    #[test]
+    fn test_clocks_share_a_thread_without_mixing_dates() {
+        // The per-thread formatting cache is keyed by the second, not by the clock
+        let first = Clock::at(at(1_000_000_000));
+        let second = Clock::at(at(0));
+        assert_eq!(&first.date_header(), b"Date: Sun, 09 Sep 2001 01:46:40 GMT\r\n");
+        assert_eq!(&second.date_header(), b"Date: Thu, 01 Jan 1970 00:00:00 GMT\r\n");
+        assert_eq!(&first.date_header(), b"Date: Sun, 09 Sep 2001 01:46:40 GMT\r\n");
+    }
+
+    #[test]
+    fn test_tick_tracks_wall_clock() {
+        let clock = Clock::at(at(0));
+        clock.tick();
+        let expected = format!("Date: {}\r\n", httpdate::fmt_http_date(SystemTime::now()));
+        let header = clock.date_header();
+        // Allow for the second turning over between the tick and the comparison
+        let previous = format!("Date: {}\r\n", httpdate::fmt_http_date(SystemTime::now() - Duration::from_secs(1)));
+        assert!(header == expected.as_bytes() || header == previous.as_bytes());
+
+        let until_next = Clock::until_next_tick();
+        assert!(until_next > Duration::ZERO && until_next <= Duration::from_secs(1));
+    }
+}
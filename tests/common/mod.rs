@@ -0,0 +1,84 @@
+// End-to-end harness shared by the test files that run the real `kiss` binary. Each test
+// crate uses only part of it.
+#![allow(dead_code)]
+
+use std::net::{TcpListener, TcpStream};
+use std::path::Path;
+use std::process::{Child, Command};
+use std::thread;
+use std::time::{Duration, Instant};
+use tempfile::TempDir;
+
+// A server on its own port and content directory, killed when dropped so a failing test
+// never leaves it running. Its output goes to a file next to the content.
+pub struct Server {
+    child: Child,
+    port: u16,
+    dir: TempDir,
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+impl Server {
+    // `populate` writes the content directory before the server starts; `args` follow
+    // the address and content directory on the command line
+    pub fn start(populate: impl FnOnce(&Path), args: &[&str]) -> Self {
+        let dir = TempDir::new().unwrap();
+        let content = dir.path().join("content");
+        std::fs::create_dir(&content).unwrap();
+        populate(&content);
+        let stdout = std::fs::File::create(dir.path().join("stdout")).unwrap();
+
+        // A port the OS just handed out, free again once the listener is dropped
+        let port = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let child = Command::new(env!("CARGO_BIN_EXE_kiss"))
+            .args(["--bind", "127.0.0.1", "--port", &port.to_string()])
+            .arg("--static-dir")
+            .arg(&content)
+            .args(args)
+            .stdout(stdout.try_clone().unwrap())
+            .stderr(stdout)
+            .spawn()
+            .unwrap();
+        let server = Server { child, port, dir };
+
+        let start = Instant::now();
+        while TcpStream::connect(("127.0.0.1", port)).is_err() {
+            assert!(start.elapsed() < Duration::from_secs(10), "Server did not start");
+            thread::sleep(Duration::from_millis(20));
+        }
+        server
+    }
+
+    pub fn connect(&self) -> TcpStream {
+        let stream = TcpStream::connect(("127.0.0.1", self.port)).unwrap();
+        stream.set_read_timeout(Some(Duration::from_secs(10))).unwrap();
+        stream
+    }
+
+    pub fn signal(&self, signal: i32) {
+        unsafe {
+            libc::kill(self.child.id() as i32, signal);
+        }
+    }
+
+    // Time until the process exits, failing the test past `limit`
+    pub fn wait_for_exit(&mut self, limit: Duration) -> Duration {
+        let start = Instant::now();
+        while self.child.try_wait().unwrap().is_none() {
+            assert!(start.elapsed() < limit, "Server still running after {:?}: {}", limit, self.output());
+            thread::sleep(Duration::from_millis(10));
+        }
+        start.elapsed()
+    }
+
+    // Everything the server printed so far
+    pub fn output(&self) -> String {
+        std::fs::read_to_string(self.dir.path().join("stdout")).unwrap()
+    }
+}
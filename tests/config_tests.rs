@@ -0,0 +1,284 @@
+use kiss::access_log::AccessLogFormat;
+use kiss::cache::EtagMode;
+use kiss::config::{Config, ConfigError};
+use kiss::security::SecurityProfile;
+use kiss::url_style::TrailingSlash;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+#[cfg(test)]
+mod config_tests {
+    use super::*;
+
+    fn parse(args: &[&str], env: &[(&str, &str)]) -> Result<Config, ConfigError> {
+        let env: HashMap<String, String> = env.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        Config::parse(args.iter().copied(), |key| env.get(key).cloned())
+    }
+
+    #[test]
+    fn test_defaults_match_previous_constants() {
+        let config = parse(&[], &[]).unwrap();
+        assert_eq!(config.port, 8080);
+        assert_eq!(config.static_dir, PathBuf::from("./content"));
+        assert_eq!(config.max_request_size, 8192);
+        assert_eq!(config.connection_timeout, Duration::from_secs(30));
+        assert_eq!(config.keepalive_timeout, Duration::from_secs(5));
+        assert_eq!(config.bind_address.to_string(), "0.0.0.0");
+    }
+
+    #[test]
+    fn test_env_variables() {
+        let config = parse(&[], &[
+            ("KISS_PORT", "9090"),
+            ("KISS_STATIC_DIR", "/srv/www"),
+            ("KISS_MAX_REQUEST_SIZE", "4096"),
+            ("KISS_CONNECTION_TIMEOUT", "60"),
+            ("KISS_KEEPALIVE_TIMEOUT", "10"),
+        ]).unwrap();
+        assert_eq!(config.port, 9090);
+        assert_eq!(config.static_dir, PathBuf::from("/srv/www"));
+        assert_eq!(config.max_request_size, 4096);
+        assert_eq!(config.connection_timeout, Duration::from_secs(60));
+        assert_eq!(config.keepalive_timeout, Duration::from_secs(10));
+    }
+
+    #[test]
+    fn test_cli_flags_override_env() {
+        let config = parse(&["--port", "7000", "--static-dir=/data"], &[("KISS_PORT", "9090")]).unwrap();
+        assert_eq!(config.port, 7000);
+        assert_eq!(config.static_dir, PathBuf::from("/data"));
+    }
+
+    #[test]
+    fn test_invalid_values_rejected() {
+        assert!(matches!(parse(&["--port", "http"], &[]), Err(ConfigError::InvalidValue { .. })));
+        assert!(matches!(parse(&["--port", "0"], &[]), Err(ConfigError::InvalidValue { .. })));
+        assert!(matches!(parse(&["--max-request-size", "10"], &[]), Err(ConfigError::InvalidValue { .. })));
+        assert!(matches!(parse(&["--keepalive-timeout", "0"], &[]), Err(ConfigError::InvalidValue { .. })));
+        assert!(matches!(parse(&[], &[("KISS_BIND", "localhost")]), Err(ConfigError::InvalidValue { .. })));
+
+        // Keep-alive idle time can't outlive the whole connection
+        let err = parse(&["--connection-timeout", "5", "--keepalive-timeout", "10"], &[]).unwrap_err();
+        assert!(err.to_string().contains("keepalive-timeout"));
+    }
+
+    #[test]
+    fn test_unknown_and_incomplete_flags() {
+        assert_eq!(parse(&["--nope"], &[]), Err(ConfigError::UnknownOption("--nope".into())));
+        assert_eq!(parse(&["port"], &[]), Err(ConfigError::UnknownOption("port".into())));
+        assert_eq!(parse(&["--port"], &[]), Err(ConfigError::MissingValue("port".into())));
+        assert_eq!(parse(&["--help"], &[]), Err(ConfigError::HelpRequested));
+    }
+
+    #[test]
+    fn test_switch_flags() {
+        assert!(!parse(&[], &[]).unwrap().watch);
+        assert!(parse(&["--watch"], &[]).unwrap().watch);
+        assert!(parse(&[], &[("KISS_WATCH", "1")]).unwrap().watch);
+        assert!(!parse(&["--watch=false"], &[("KISS_WATCH", "true")]).unwrap().watch);
+        assert!(matches!(parse(&[], &[("KISS_WATCH", "maybe")]), Err(ConfigError::InvalidValue { .. })));
+        assert!(!parse(&[], &[]).unwrap().compress);
+        assert!(parse(&[], &[("KISS_COMPRESS", "on")]).unwrap().compress);
+
+        let config = parse(&["--watch", "--watch-debounce-ms", "250"], &[]).unwrap();
+        assert_eq!(config.watch_debounce, Duration::from_millis(250));
+    }
+
+    #[test]
+    fn test_admin_token() {
+        assert_eq!(parse(&[], &[]).unwrap().admin_token, None);
+        let config = parse(&[], &[("KISS_ADMIN_TOKEN", "0123456789abcdef")]).unwrap();
+        assert_eq!(config.admin_token.as_deref(), Some("0123456789abcdef"));
+
+        // Short tokens are rejected without echoing the secret
+        let err = parse(&["--admin-token", "hunter2"], &[]).unwrap_err();
+        assert!(!err.to_string().contains("hunter2"));
+    }
+
+    #[test]
+    fn test_access_log_options() {
+        let config = parse(&[], &[]).unwrap();
+        assert_eq!(config.access_log, AccessLogFormat::Off);
+        assert_eq!(config.access_log_sample, 1);
+
+        let config = parse(&["--access-log", "combined", "--access-log-sample=10"], &[]).unwrap();
+        assert_eq!(config.access_log, AccessLogFormat::Combined);
+        assert_eq!(config.access_log_sample, 10);
+
+        assert!(matches!(parse(&["--access-log", "xml"], &[]), Err(ConfigError::InvalidValue { .. })));
+        assert!(matches!(parse(&["--access-log-sample", "0"], &[]), Err(ConfigError::InvalidValue { .. })));
+    }
+
+    #[test]
+    fn test_metrics_options() {
+        let config = parse(&[], &[]).unwrap();
+        assert!(!config.metrics);
+        assert_eq!(config.metrics_port, None);
+
+        assert!(parse(&["--metrics"], &[]).unwrap().metrics);
+        assert_eq!(parse(&[], &[("KISS_METRICS_PORT", "9100")]).unwrap().metrics_port, Some(9100));
+        assert!(matches!(parse(&["--metrics-port", "8080"], &[]), Err(ConfigError::InvalidValue { .. })));
+        assert!(matches!(parse(&["--metrics-port", "0"], &[]), Err(ConfigError::InvalidValue { .. })));
+    }
+
+    #[test]
+    fn test_header_limits() {
+        let limits = parse(&[], &[]).unwrap().head_limits();
+        assert_eq!((limits.request_line, limits.header_line, limits.header_block, limits.header_count), (8192, 8192, 32768, 100));
+
+        let config = parse(&["--max-header-size", "4096", "--max-headers", "20"], &[("KISS_MAX_HEADERS_SIZE", "16384")]).unwrap();
+        assert_eq!((config.max_header_size, config.max_headers_size, config.max_headers), (4096, 16384, 20));
+        assert!(matches!(parse(&["--max-header-size", "65536"], &[]), Err(ConfigError::InvalidValue { .. })));
+        assert!(matches!(parse(&["--max-headers", "0"], &[]), Err(ConfigError::InvalidValue { .. })));
+        assert!(matches!(parse(&["--max-headers-size", "100"], &[]), Err(ConfigError::InvalidValue { .. })));
+    }
+
+    #[test]
+    fn test_shutdown_options() {
+        let config = parse(&[], &[]).unwrap();
+        assert_eq!(config.shutdown_delay, Duration::ZERO);
+        assert_eq!(config.shutdown_timeout, Duration::from_secs(20));
+
+        let config = parse(&["--shutdown-delay", "5"], &[("KISS_SHUTDOWN_TIMEOUT", "0")]).unwrap();
+        assert_eq!(config.shutdown_delay, Duration::from_secs(5));
+        assert_eq!(config.shutdown_timeout, Duration::ZERO);
+        assert!(matches!(parse(&["--shutdown-timeout", "3601"], &[]), Err(ConfigError::InvalidValue { .. })));
+        assert!(matches!(parse(&["--shutdown-delay", "-1"], &[]), Err(ConfigError::InvalidValue { .. })));
+    }
+
+    #[test]
+    fn test_etag_options() {
+        let config = parse(&[], &[]).unwrap();
+        assert_eq!(config.etag, EtagMode::Weak);
+        assert_eq!(config.source_date_epoch, None);
+
+        assert_eq!(parse(&["--etag", "strong"], &[]).unwrap().etag, EtagMode::Strong);
+        assert_eq!(parse(&[], &[("KISS_ETAG", "STRONG")]).unwrap().etag, EtagMode::Strong);
+        assert!(matches!(parse(&["--etag", "md5"], &[]), Err(ConfigError::InvalidValue { .. })));
+
+        // The standard variable, not a KISS_ one
+        let config = parse(&[], &[("SOURCE_DATE_EPOCH", "1700000000")]).unwrap();
+        assert_eq!(config.source_date_epoch, Some(SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000)));
+        assert_eq!(parse(&[], &[("SOURCE_DATE_EPOCH", "")]).unwrap().source_date_epoch, None);
+        assert!(matches!(parse(&[], &[("SOURCE_DATE_EPOCH", "2023-11-14")]), Err(ConfigError::InvalidValue { .. })));
+    }
+
+    #[test]
+    fn test_header_rules_option() {
+        assert_eq!(parse(&[], &[]).unwrap().header_rules, None);
+
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let path = file.path().to_str().unwrap();
+        assert_eq!(parse(&["--header-rules", path], &[]).unwrap().header_rules, Some(PathBuf::from(path)));
+
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path().to_str().unwrap();
+        assert!(matches!(parse(&["--header-rules", dir], &[]), Err(ConfigError::InvalidValue { .. })));
+        assert!(matches!(parse(&[], &[("KISS_HEADER_RULES", "/nonexistent/rules")]), Err(ConfigError::InvalidValue { .. })));
+    }
+
+    #[test]
+    fn test_content_type_options() {
+        let config = parse(&[], &[]).unwrap();
+        assert_eq!(config.mime_types, None);
+        assert!(config.mime_overrides.is_empty());
+
+        let config = parse(&["--mime-type", "wasm=application/wasm,rst=text/x-rst", "--mime-type", "ts=text/typescript"], &[]).unwrap();
+        assert_eq!(config.mime_overrides.len(), 3);
+        assert_eq!(config.mime_overrides[2], ("ts".to_string(), "text/typescript".to_string()));
+
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let path = file.path().to_str().unwrap();
+        assert_eq!(parse(&[], &[("KISS_MIME_TYPES", path)]).unwrap().mime_types, Some(PathBuf::from(path)));
+        assert!(matches!(parse(&["--mime-types", "/nonexistent/mime.types"], &[]), Err(ConfigError::InvalidValue { .. })));
+        assert!(matches!(parse(&["--mime-type", "wasm"], &[]), Err(ConfigError::InvalidValue { .. })));
+
+        assert!(!config.sniff);
+        assert!(parse(&["--sniff"], &[]).unwrap().sniff);
+        assert!(parse(&[], &[("KISS_SNIFF", "true")]).unwrap().sniff);
+    }
+
+    #[test]
+    fn test_spa_options() {
+        assert_eq!(parse(&[], &[]).unwrap().spa_fallback(), None);
+
+        let spa = parse(&["--spa"], &[]).unwrap().spa_fallback().unwrap();
+        assert_eq!(spa.document, "/index.html");
+        assert_eq!(spa.include, vec!["/"]);
+        assert!(spa.exclude.is_empty());
+
+        let config = parse(
+            &["--spa-fallback", "/app/shell.html", "--spa-exclude", "/api/, /assets/", "--spa-include", "/app/"],
+            &[("KISS_SPA", "true")],
+        )
+        .unwrap();
+        let spa = config.spa_fallback().unwrap();
+        assert_eq!(spa.document, "/app/shell.html");
+        assert_eq!(spa.include, vec!["/app/"]);
+        assert_eq!(spa.exclude, vec!["/api/", "/assets/"]);
+
+        assert!(matches!(parse(&["--spa-fallback", "index.html"], &[]), Err(ConfigError::InvalidValue { .. })));
+        assert!(matches!(parse(&["--spa-exclude", "/api/,assets"], &[]), Err(ConfigError::InvalidValue { .. })));
+    }
+
+    #[test]
+    fn test_url_style_options() {
+        let config = parse(&[], &[]).unwrap();
+        let style = config.url_style();
+        assert_eq!(style.trailing_slash, TrailingSlash::Off);
+        assert!(!style.clean_urls);
+        assert_eq!(style.index_files, vec!["index.html"]);
+        assert_eq!(config.redirect_status, 301);
+
+        let config = parse(
+            &["--trailing-slash", "remove", "--index-files", "index.htm, index.html", "--redirect-status", "308"],
+            &[("KISS_CLEAN_URLS", "1"), ("KISS_TRAILING_SLASH", "add")],
+        )
+        .unwrap();
+        let style = config.url_style();
+        assert_eq!(style.trailing_slash, TrailingSlash::Remove, "CLI overrides env");
+        assert!(style.clean_urls);
+        assert_eq!(style.index_files, vec!["index.htm", "index.html"]);
+        assert_eq!(config.redirect_status, 308);
+
+        assert!(matches!(parse(&["--trailing-slash", "sometimes"], &[]), Err(ConfigError::InvalidValue { .. })));
+        assert!(matches!(parse(&["--redirect-status", "302"], &[]), Err(ConfigError::InvalidValue { .. })));
+        assert!(matches!(parse(&["--index-files", "docs/index.html"], &[]), Err(ConfigError::InvalidValue { .. })));
+        assert!(matches!(parse(&["--index-files", " , "], &[]), Err(ConfigError::InvalidValue { .. })));
+    }
+
+    #[test]
+    fn test_security_header_options() {
+        let config = parse(&[], &[]).unwrap();
+        assert_eq!(config.security_profile, SecurityProfile::Custom);
+        assert_eq!(config.security_headers().render(), "X-Content-Type-Options: nosniff\r\n", "Only nosniff unless a profile is chosen");
+
+        let config = parse(&["--security-headers", "strict", "--csp", "default-src 'self' https://cdn.example", "--hsts", "off"], &[]).unwrap();
+        let rendered = config.security_headers().render();
+        assert!(rendered.contains("Content-Security-Policy: default-src 'self' https://cdn.example\r\n"));
+        assert!(!rendered.contains("Strict-Transport-Security"));
+
+        let config = parse(&[], &[("KISS_SECURITY_HEADERS", "none"), ("KISS_FRAME_OPTIONS", "DENY")]).unwrap();
+        assert_eq!(config.security_headers().render(), "X-Frame-Options: DENY\r\n");
+
+        assert!(matches!(parse(&["--security-headers", "paranoid"], &[]), Err(ConfigError::InvalidValue { .. })));
+        assert!(matches!(parse(&["--csp", " "], &[]), Err(ConfigError::InvalidValue { .. })));
+        assert!(matches!(parse(&["--coop", "same-origin\r\nX-Injected: 1"], &[]), Err(ConfigError::InvalidValue { .. })));
+    }
+
+    #[test]
+    fn test_static_dir_must_be_directory() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let path = file.path().to_str().unwrap();
+        assert!(matches!(parse(&["--static-dir", path], &[]), Err(ConfigError::InvalidValue { .. })));
+    }
+
+    #[test]
+    fn test_usage_lists_env_names() {
+        let usage = Config::usage();
+        assert!(usage.contains("--static-dir"));
+        assert!(usage.contains("KISS_KEEPALIVE_TIMEOUT"));
+        assert!(usage.contains("SOURCE_DATE_EPOCH"));
+    }
+}
@@ -1,3 +1,5 @@
+#![allow(clippy::manual_map)]
+
 use std::io::{Read, Write};
 use std::net::TcpStream;
 
@@ -0,0 +1,108 @@
+use kiss::encoding::{AcceptEncoding, ContentEncoding};
+
+#[cfg(test)]
+mod accept_encoding_tests {
+    use super::*;
+
+    const ALL: [ContentEncoding; 3] = ContentEncoding::ALL;
+
+    fn negotiate(header: &str) -> Option<ContentEncoding> {
+        AcceptEncoding::parse(header.as_bytes()).negotiate(ALL)
+    }
+
+    #[test]
+    fn test_quality_values() {
+        let accept = AcceptEncoding::parse(b"gzip;q=0.8, br, zstd;q=0.125, deflate");
+        assert_eq!(accept.quality(ContentEncoding::Brotli), 1000);
+        assert_eq!(accept.quality(ContentEncoding::Gzip), 800);
+        assert_eq!(accept.quality(ContentEncoding::Zstd), 125);
+        assert_eq!(accept.identity_quality(), 1000);
+
+        // Case-insensitive codings and parameters, x-gzip alias
+        let accept = AcceptEncoding::parse(b"X-GZIP ; Q=0.5");
+        assert_eq!(accept.quality(ContentEncoding::Gzip), 500);
+    }
+
+    #[test]
+    fn test_invalid_qvalues_are_skipped() {
+        for header in ["br;q=2", "br;q=1.5", "br;q=0.1234", "br;q=abc", "br;q=-1"] {
+            assert_eq!(AcceptEncoding::parse(header.as_bytes()).quality(ContentEncoding::Brotli), 0, "{}", header);
+        }
+        assert_eq!(AcceptEncoding::parse(b"br;q=1.000").quality(ContentEncoding::Brotli), 1000);
+    }
+
+    #[test]
+    fn test_negotiation_preference() {
+        assert_eq!(negotiate("gzip, br, zstd"), Some(ContentEncoding::Brotli), "Server preference breaks ties");
+        assert_eq!(negotiate("gzip, br;q=0.9"), Some(ContentEncoding::Gzip), "Client quality wins");
+        assert_eq!(negotiate("*"), Some(ContentEncoding::Brotli));
+        assert_eq!(negotiate("*, br;q=0"), Some(ContentEncoding::Zstd), "Explicit listing overrides the wildcard");
+        assert_eq!(negotiate("identity"), None);
+        assert_eq!(negotiate(""), None);
+        assert_eq!(negotiate("gzip;q=0.5, identity"), None, "Identity preferred over a lower-ranked coding");
+        assert_eq!(negotiate("gzip;q=0.5, identity;q=0.5"), Some(ContentEncoding::Gzip));
+    }
+
+    #[test]
+    fn test_sidecar_extensions() {
+        assert_eq!(ContentEncoding::strip_sidecar_extension("/app.js.br"), Some(("/app.js", ContentEncoding::Brotli)));
+        assert_eq!(ContentEncoding::strip_sidecar_extension("/app.js.zst"), Some(("/app.js", ContentEncoding::Zstd)));
+        assert_eq!(ContentEncoding::strip_sidecar_extension("/docs/index.html.gz"), Some(("/docs/index.html", ContentEncoding::Gzip)));
+        assert_eq!(ContentEncoding::strip_sidecar_extension("/app.js"), None);
+        assert_eq!(ContentEncoding::strip_sidecar_extension("/dir/.gz"), None);
+    }
+}
+
+#[cfg(test)]
+mod precompress_tests {
+    use kiss::encoding::{precompress, ContentEncoding};
+    use kiss::{get_mime_type_enum, MimeType};
+    use std::io::Read;
+    use std::path::Path;
+
+    fn compressible() -> Vec<u8> {
+        "body { margin: 0; padding: 0; }\n".repeat(200).into_bytes()
+    }
+
+    #[test]
+    fn test_gzip_round_trip() {
+        let content = compressible();
+        let compressed = precompress(&content, ContentEncoding::Gzip).expect("Repetitive CSS compresses");
+        assert!(compressed.len() < content.len());
+
+        let mut decoded = Vec::new();
+        flate2::read::GzDecoder::new(&compressed[..]).read_to_end(&mut decoded).unwrap();
+        assert_eq!(decoded, content);
+    }
+
+    #[test]
+    fn test_brotli_round_trip() {
+        let content = compressible();
+        let compressed = precompress(&content, ContentEncoding::Brotli).expect("Repetitive CSS compresses");
+        assert!(compressed.len() < content.len());
+
+        let mut decoded = Vec::new();
+        brotli::Decompressor::new(&compressed[..], 4096).read_to_end(&mut decoded).unwrap();
+        assert_eq!(decoded, content);
+    }
+
+    #[test]
+    fn test_variants_kept_only_when_smaller() {
+        // Tiny bodies grow once container overhead is added
+        assert_eq!(precompress(b"ok", ContentEncoding::Gzip), None);
+        assert_eq!(precompress(b"", ContentEncoding::Brotli), None);
+        // zstd is only served from sidecar files
+        assert_eq!(precompress(&compressible(), ContentEncoding::Zstd), None);
+    }
+
+    #[test]
+    fn test_only_text_types_are_compressible() {
+        for name in ["a.html", "a.css", "a.js", "a.json", "a.svg", "a.xml", "a.txt"] {
+            assert!(get_mime_type_enum(Path::new(name)).is_compressible(), "{}", name);
+        }
+        for name in ["a.png", "a.jpg", "a.woff2", "a.pdf", "a.bin"] {
+            assert!(!get_mime_type_enum(Path::new(name)).is_compressible(), "{}", name);
+        }
+        assert!(!MimeType::OctetStream.is_compressible());
+    }
+}
@@ -0,0 +1,112 @@
+use kiss::cache::{OptimizedCache, PathTrie};
+use kiss::error_pages::{error_status, ErrorPages, ErrorResponse, ERROR_STATUSES};
+use std::fs;
+use std::path::Path;
+use tempfile::TempDir;
+
+#[cfg(test)]
+mod error_pages_tests {
+    use super::*;
+
+    const SECURITY: &str = "X-Content-Type-Options: nosniff\r\n";
+
+    fn content_type(path: &Path, _body: &[u8]) -> String {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("html") => "text/html; charset=utf-8".to_string(),
+            _ => "application/octet-stream".to_string(),
+        }
+    }
+
+    fn load(files: &[(&str, &str)]) -> (ErrorPages, Vec<String>) {
+        let dir = TempDir::new().unwrap();
+        for (name, content) in files {
+            fs::write(dir.path().join(name), content).unwrap();
+        }
+        let mut errors = Vec::new();
+        let pages = ErrorPages::load(dir.path(), SECURITY, content_type, &mut errors);
+        (pages, errors)
+    }
+
+    fn text(bytes: &[u8]) -> &str {
+        std::str::from_utf8(bytes).unwrap()
+    }
+
+    #[test]
+    fn test_pages_for_known_statuses_are_loaded() {
+        let (pages, errors) = load(&[("404.html", "<h1>Gone</h1>"), ("500.html", "never sent"), ("4040.html", "no"), ("index.html", "home")]);
+        assert!(errors.is_empty());
+        assert_eq!(pages.codes().collect::<Vec<_>>(), vec![404]);
+
+        let page = pages.get(404).unwrap();
+        assert_eq!(
+            text(&page.complete),
+            "HTTP/1.1 404 Not Found\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: 13\r\nX-Content-Type-Options: nosniff\r\nConnection: keep-alive\r\n\r\n<h1>Gone</h1>"
+        );
+        assert!(pages.get(400).is_none(), "Other statuses keep the built-in response");
+    }
+
+    #[test]
+    fn test_pages_for_statuses_never_sent_are_reported() {
+        let (pages, errors) = load(&[("500.html", "oops"), ("403.html", "forbidden"), ("404.html", "gone"), ("999.html", "no"), ("40.html", "no")]);
+        assert!(errors.is_empty());
+        assert_eq!(pages.codes().collect::<Vec<_>>(), vec![404]);
+        assert_eq!(pages.ignored(), &[403, 500]);
+        assert!(pages.get(403).is_none() && pages.get(500).is_none());
+
+        let (pages, _) = load(&[("404.html", "gone")]);
+        assert!(pages.ignored().is_empty());
+    }
+
+    #[test]
+    fn test_pages_keep_their_status_headers() {
+        let (pages, _) = load(&[("401.html", "sign in"), ("431.html", "too many headers")]);
+        assert!(text(&pages.get(401).unwrap().complete).contains("\r\nWWW-Authenticate: Bearer\r\n"));
+        assert!(text(&pages.get(431).unwrap().complete).contains("\r\nConnection: close\r\n"));
+    }
+
+    #[test]
+    fn test_builtin_fallback() {
+        let not_found = ErrorResponse::builtin(error_status(404).unwrap(), SECURITY);
+        assert_eq!(not_found.status, 404);
+        assert_eq!(
+            text(&not_found.complete),
+            "HTTP/1.1 404 Not Found\r\nContent-Type: text/plain\r\nContent-Length: 14\r\nX-Content-Type-Options: nosniff\r\nConnection: keep-alive\r\n\r\nFile not found"
+        );
+        assert!(error_status(500).is_none());
+        for status in ERROR_STATUSES {
+            let response = ErrorResponse::builtin(status, "");
+            assert!(text(&response.complete).starts_with(&format!("HTTP/1.1 {}\r\n", status.status_line)));
+            assert_eq!(&status.status_line[..3], status.code.to_string());
+        }
+    }
+
+    #[test]
+    fn test_head_has_headers_without_body() {
+        let (pages, _) = load(&[("404.html", "<h1>Gone</h1>")]);
+        for response in [pages.get(404).unwrap().clone(), ErrorResponse::builtin(error_status(404).unwrap(), SECURITY)] {
+            let head = text(response.for_method(true));
+            assert!(head.ends_with("\r\n\r\n"));
+            assert_eq!(response.for_method(false).len() - head.len(), content_length(head));
+            assert!(response.for_method(false).starts_with(head.as_bytes()));
+        }
+    }
+
+    fn content_length(head: &str) -> usize {
+        let line = head.lines().find_map(|line| line.strip_prefix("Content-Length: ")).unwrap();
+        line.parse().unwrap()
+    }
+
+    #[test]
+    fn test_pages_swap_with_their_generation() {
+        let (pages, _) = load(&[("404.html", "<h1>Gone</h1>")]);
+        let mut trie = PathTrie::new();
+        trie.set_error_pages(pages);
+        let cache = OptimizedCache::from_trie(trie);
+        assert!(cache.error_page(404, false).unwrap().ends_with(b"<h1>Gone</h1>"));
+        assert!(cache.error_page(404, true).unwrap().ends_with(b"\r\n\r\n"));
+        assert!(cache.error_page(400, false).is_none());
+
+        cache.replace(PathTrie::new());
+        assert!(cache.error_page(404, false).is_none(), "A deleted page stops being used on reload");
+    }
+}
@@ -1,3 +1,5 @@
+#![allow(clippy::expect_fun_call)]
+
 use std::io::{Read, Write};
 use std::net::TcpStream;
 use std::time::Duration;
@@ -130,7 +132,7 @@ mod http_request_validation_tests {
         match send_raw_request(&oversized_request) {
             Ok(response) => {
                 // Should reject oversized requests with 4xx error
-                assert!(response.contains("413") || response.contains("400"));
+                assert!(response.contains("414"));
             }
             Err(_) => {
                 println!("Warning: Server not running, skipping oversized request test");
@@ -140,8 +140,8 @@ mod http_integration_tests {
         let mut response = String::new();
         stream.read_to_string(&mut response).unwrap();
         
-        assert!(response.contains("HTTP/1.1 413 Request Entity Too Large"));
-        assert!(response.contains("Request too large"));
+        assert!(response.contains("HTTP/1.1 414 URI Too Long"));
+        assert!(response.contains("URI too long"));
     }
 
     #[test]
@@ -0,0 +1,93 @@
+use kiss::metrics::{CacheStats, Metrics};
+use kiss::request::Rejection;
+use std::time::Duration;
+
+#[cfg(test)]
+mod metrics_tests {
+    use super::*;
+
+    fn value<'a>(output: &'a str, series: &str) -> &'a str {
+        output
+            .lines()
+            .find_map(|line| line.strip_prefix(series).and_then(|rest| rest.strip_prefix(' ')))
+            .unwrap_or_else(|| panic!("Series {} missing from:\n{}", series, output))
+    }
+
+    #[test]
+    fn test_requests_by_method_and_status() {
+        let metrics = Metrics::new();
+        metrics.record_request(b"GET", 200, 1000, Duration::from_micros(50));
+        metrics.record_request(b"GET", 200, 1000, Duration::from_micros(50));
+        metrics.record_request(b"HEAD", 404, 100, Duration::from_micros(50));
+        metrics.record_request(b"BREW", 418, 10, Duration::from_micros(50));
+
+        let output = metrics.render(CacheStats::default());
+        assert_eq!(value(&output, r#"kiss_requests_total{method="GET",code="200"}"#), "2");
+        assert_eq!(value(&output, r#"kiss_requests_total{method="HEAD",code="404"}"#), "1");
+        assert_eq!(value(&output, r#"kiss_requests_total{method="OTHER",code="other"}"#), "1");
+        assert_eq!(value(&output, "kiss_response_bytes_total"), "2110");
+        assert!(!output.contains(r#"code="206""#), "Zero series are omitted");
+    }
+
+    #[test]
+    fn test_latency_histogram_is_cumulative() {
+        let metrics = Metrics::new();
+        metrics.record_request(b"GET", 200, 0, Duration::from_micros(80));
+        metrics.record_request(b"GET", 200, 0, Duration::from_millis(3));
+        metrics.record_request(b"GET", 200, 0, Duration::from_secs(10));
+
+        let output = metrics.render(CacheStats::default());
+        assert_eq!(value(&output, r#"kiss_request_duration_seconds_bucket{le="0.0001"}"#), "1");
+        assert_eq!(value(&output, r#"kiss_request_duration_seconds_bucket{le="0.005"}"#), "2");
+        assert_eq!(value(&output, r#"kiss_request_duration_seconds_bucket{le="2.5"}"#), "2");
+        assert_eq!(value(&output, r#"kiss_request_duration_seconds_bucket{le="+Inf"}"#), "3");
+        assert_eq!(value(&output, "kiss_request_duration_seconds_count"), "3");
+        assert_eq!(value(&output, "kiss_request_duration_seconds_sum"), "10.00308");
+    }
+
+    #[test]
+    fn test_connections_and_ratios() {
+        let metrics = Metrics::new();
+        metrics.connection_opened();
+        metrics.connection_opened();
+        metrics.connection_closed();
+        metrics.record_keepalive_reuse();
+        for _ in 0..4 {
+            metrics.record_conditional();
+        }
+        metrics.record_request(b"GET", 304, 100, Duration::ZERO);
+
+        let output = metrics.render(CacheStats { entries: 12, bytes: 4096 });
+        assert_eq!(value(&output, "kiss_connections_active"), "1");
+        assert_eq!(value(&output, "kiss_connections_total"), "2");
+        assert_eq!(value(&output, "kiss_keepalive_requests_total"), "1");
+        assert_eq!(value(&output, "kiss_not_modified_ratio"), "0.25");
+        assert_eq!(value(&output, "kiss_cache_entries"), "12");
+        assert_eq!(value(&output, "kiss_cache_bytes"), "4096");
+    }
+
+    #[test]
+    fn test_every_series_has_help_and_type() {
+        let output = Metrics::new().render(CacheStats::default());
+        for line in output.lines().filter(|line| !line.starts_with('#')) {
+            let name = line.split(['{', ' ']).next().unwrap();
+            let family = name.trim_end_matches("_bucket").trim_end_matches("_sum").trim_end_matches("_count");
+            assert!(output.contains(&format!("# TYPE {} ", family)), "No TYPE for {}", name);
+        }
+    }
+
+    #[test]
+    fn test_rejected_requests_by_reason() {
+        let metrics = Metrics::new();
+        metrics.record_rejected(Rejection::RequestLine);
+        metrics.record_rejected(Rejection::HeaderCount);
+        metrics.record_rejected(Rejection::HeaderCount);
+        metrics.record_rejected(Rejection::TransferEncoding);
+
+        let output = metrics.render(CacheStats::default());
+        assert_eq!(value(&output, r#"kiss_rejected_requests_total{reason="request_line"}"#), "1");
+        assert_eq!(value(&output, r#"kiss_rejected_requests_total{reason="header_line"}"#), "0");
+        assert_eq!(value(&output, r#"kiss_rejected_requests_total{reason="header_count"}"#), "2");
+        assert_eq!(value(&output, r#"kiss_rejected_requests_total{reason="transfer_encoding"}"#), "1");
+    }
+}
@@ -1,3 +1,5 @@
+mod common;
+
 use std::io::{Read, Write};
 use std::net::TcpStream;
 use std::process::{Command, Child, Stdio};
@@ -336,4 +338,102 @@ mod startup_and_binding_tests {
         stream.read_to_string(&mut response)?;
         Ok(response)
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod drain_tests {
+    use crate::common::Server;
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    const BODY: &str = "drain test";
+
+    fn start(shutdown_timeout: &str) -> Server {
+        Server::start(
+            |content| std::fs::write(content.join("index.html"), BODY).unwrap(),
+            &["--keepalive-timeout", "30", "--connection-timeout", "60", "--shutdown-timeout", shutdown_timeout],
+        )
+    }
+
+    // A connection the server hasn't accepted yet is refused once the listener closes, so
+    // give it time to accept and read what was sent before signalling
+    fn terminate(server: &Server) {
+        thread::sleep(Duration::from_millis(200));
+        server.signal(libc::SIGTERM);
+        // Let the server stop accepting and start draining
+        thread::sleep(Duration::from_millis(200));
+    }
+
+    // One response, read by its Content-Length so a kept-alive connection can be read again
+    fn read_response(stream: &mut TcpStream) -> String {
+        let mut response = Vec::new();
+        let mut byte = [0; 1];
+        while !response.ends_with(b"\r\n\r\n") {
+            assert_eq!(stream.read(&mut byte).unwrap(), 1, "Connection closed mid-response");
+            response.push(byte[0]);
+        }
+        let head = String::from_utf8(response.clone()).unwrap();
+        let length: usize = head
+            .lines()
+            .find_map(|line| line.strip_prefix("Content-Length: "))
+            .map_or(0, |value| value.parse().unwrap());
+        let mut body = vec![0; length];
+        stream.read_exact(&mut body).unwrap();
+        response.extend_from_slice(&body);
+        String::from_utf8(response).unwrap()
+    }
+
+    fn closed(stream: &mut TcpStream) -> bool {
+        matches!(stream.read(&mut [0; 1]), Ok(0))
+    }
+
+    #[test]
+    fn test_in_flight_request_completes() {
+        let mut server = start("10");
+        let mut stream = server.connect();
+        stream.write_all(b"GET /index.html HTTP/1.1\r\nHost: localhost\r\n").unwrap();
+
+        terminate(&server);
+        stream.write_all(b"\r\n").unwrap();
+        let response = read_response(&mut stream);
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{}", response);
+        assert!(response.contains("\r\nConnection: close\r\n"), "The last response announces the close");
+        assert!(response.ends_with(BODY));
+        assert!(closed(&mut stream));
+
+        let elapsed = server.wait_for_exit(Duration::from_secs(5));
+        assert!(elapsed < Duration::from_secs(2), "Exit follows the last connection, took {:?}", elapsed);
+        assert!(server.output().contains("All connections drained"), "{}", server.output());
+    }
+
+    #[test]
+    fn test_idle_keep_alive_connection_closes() {
+        let mut server = start("10");
+        let mut stream = server.connect();
+        stream.write_all(b"GET /index.html HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+        assert!(read_response(&mut stream).contains("\r\nConnection: keep-alive\r\n"));
+
+        let start = Instant::now();
+        terminate(&server);
+        assert!(closed(&mut stream), "An idle connection is closed as draining starts");
+        assert!(start.elapsed() < Duration::from_secs(2), "Closed well before the keep-alive timeout");
+
+        server.wait_for_exit(Duration::from_secs(5));
+        assert!(server.output().contains("All connections drained"), "{}", server.output());
+    }
+
+    #[test]
+    fn test_drain_timeout_is_enforced() {
+        let mut server = start("1");
+        let mut stream = server.connect();
+        // A request that never completes keeps its connection in flight
+        stream.write_all(b"GET /index.html HTTP/1.1\r\n").unwrap();
+
+        terminate(&server);
+        let elapsed = server.wait_for_exit(Duration::from_secs(5));
+        assert!(elapsed < Duration::from_secs(2), "Exit at the drain timeout, took {:?}", elapsed);
+        let output = server.output();
+        assert!(output.contains("Shutdown timeout reached with 1 connection(s) still open"), "{}", output);
+    }
+}
@@ -0,0 +1,129 @@
+use kiss::range::{if_range_matches, parse_range, ByteRange, RangeRequest};
+use std::time::{Duration, SystemTime};
+
+#[cfg(test)]
+mod range_parsing_tests {
+    use super::*;
+
+    fn ranges(header: &str, total: u64) -> Vec<(u64, u64)> {
+        match parse_range(header.as_bytes(), total) {
+            RangeRequest::Satisfiable(ranges) => ranges.iter().map(|r| (r.start, r.end)).collect(),
+            other => panic!("Expected satisfiable ranges for {:?}, got {:?}", header, other),
+        }
+    }
+
+    #[test]
+    fn test_single_ranges() {
+        assert_eq!(ranges("bytes=0-499", 1000), vec![(0, 499)]);
+        assert_eq!(ranges("bytes=500-", 1000), vec![(500, 999)]);
+        assert_eq!(ranges("bytes=-200", 1000), vec![(800, 999)]);
+        assert_eq!(ranges("bytes=900-5000", 1000), vec![(900, 999)], "End is clamped to the length");
+        assert_eq!(ranges("bytes=-5000", 1000), vec![(0, 999)], "Oversized suffix covers the whole file");
+        assert_eq!(ranges("Bytes = 0-0", 10), vec![(0, 0)]);
+    }
+
+    #[test]
+    fn test_multiple_ranges_are_coalesced() {
+        assert_eq!(ranges("bytes=0-9,20-29", 100), vec![(0, 9), (20, 29)]);
+        assert_eq!(ranges("bytes=20-29,0-9", 100), vec![(0, 9), (20, 29)]);
+        assert_eq!(ranges("bytes=0-9,5-14,15-19", 100), vec![(0, 19)]);
+        assert_eq!(ranges("bytes=0-9, ,90-", 100), vec![(0, 9), (90, 99)]);
+    }
+
+    #[test]
+    fn test_unsatisfiable_ranges() {
+        assert_eq!(parse_range(b"bytes=1000-", 1000), RangeRequest::Unsatisfiable);
+        assert_eq!(parse_range(b"bytes=2000-3000,1500-", 1000), RangeRequest::Unsatisfiable);
+        assert_eq!(parse_range(b"bytes=-0", 1000), RangeRequest::Unsatisfiable);
+        assert_eq!(parse_range(b"bytes=0-", 0), RangeRequest::Unsatisfiable);
+
+        // One satisfiable range is enough for a 206
+        assert_eq!(ranges("bytes=2000-,0-0", 1000), vec![(0, 0)]);
+    }
+
+    #[test]
+    fn test_invalid_ranges_are_ignored() {
+        for header in ["items=0-10", "bytes=", "bytes=abc", "bytes=10-5", "bytes=1-2-3", "bytes=--5", "bytes 0-10", "bytes=+1-5"] {
+            assert_eq!(parse_range(header.as_bytes(), 1000), RangeRequest::Ignore, "{} should be ignored", header);
+        }
+        assert_eq!(parse_range(&[0xff, 0xfe], 1000), RangeRequest::Ignore);
+    }
+
+    #[test]
+    fn test_too_many_ranges_are_ignored() {
+        let many: Vec<String> = (0..17).map(|i| format!("{}-{}", i * 10, i * 10 + 1)).collect();
+        let header = format!("bytes={}", many.join(","));
+        assert_eq!(parse_range(header.as_bytes(), 10_000), RangeRequest::Ignore);
+    }
+
+    #[test]
+    fn test_content_range_format() {
+        let range = ByteRange { start: 0, end: 99 };
+        assert_eq!(range.content_range(1000), "bytes 0-99/1000");
+        assert_eq!(range.to_index_range(), 0..100);
+    }
+}
+
+#[cfg(test)]
+mod if_range_tests {
+    use super::*;
+
+    fn modified() -> SystemTime {
+        SystemTime::UNIX_EPOCH + Duration::from_secs(946684800) // Sat, 01 Jan 2000 00:00:00 GMT
+    }
+
+    #[test]
+    fn test_strong_etag_match() {
+        assert!(if_range_matches(b"\"abc\"", "\"abc\"", modified()));
+        assert!(!if_range_matches(b"\"xyz\"", "\"abc\"", modified()));
+    }
+
+    #[test]
+    fn test_weak_etags_never_match() {
+        // If-Range requires strong comparison
+        assert!(!if_range_matches(b"W/\"123-456\"", "W/\"123-456\"", modified()));
+        assert!(!if_range_matches(b"\"123-456\"", "W/\"123-456\"", modified()));
+    }
+
+    #[test]
+    fn test_date_must_match_exactly() {
+        assert!(if_range_matches(b"Sat, 01 Jan 2000 00:00:00 GMT", "W/\"1-2\"", modified()));
+        assert!(!if_range_matches(b"Sat, 01 Jan 2000 00:00:01 GMT", "W/\"1-2\"", modified()));
+        assert!(!if_range_matches(b"not a date", "W/\"1-2\"", modified()));
+    }
+}
+
+#[cfg(test)]
+mod range_integration_tests {
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+
+    fn send_range_request(path: &str, range: &str) -> Result<String, std::io::Error> {
+        let mut stream = TcpStream::connect("127.0.0.1:8080")?;
+        let request = format!("GET {} HTTP/1.1\r\nHost: localhost\r\nRange: {}\r\nConnection: close\r\n\r\n", path, range);
+        stream.write_all(request.as_bytes())?;
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response)?;
+        Ok(response)
+    }
+
+    #[test]
+    #[ignore] // Requires server to be running from tests/ directory
+    fn test_partial_content_responses() {
+        let response = send_range_request("/small.txt", "bytes=0-3").unwrap();
+        assert!(response.starts_with("HTTP/1.1 206 Partial Content"));
+        assert!(response.contains("Content-Range: bytes 0-3/"));
+        assert!(response.contains("Content-Length: 4\r\n"));
+        assert!(response.contains("Accept-Ranges: bytes"));
+
+        let response = send_range_request("/small.txt", "bytes=0-0,2-2").unwrap();
+        assert!(response.contains("Content-Type: multipart/byteranges; boundary="));
+
+        let response = send_range_request("/small.txt", "bytes=999999-").unwrap();
+        assert!(response.starts_with("HTTP/1.1 416 Range Not Satisfiable"));
+        assert!(response.contains("Content-Range: bytes */"));
+
+        println!("✓ Range request responses correct");
+    }
+}
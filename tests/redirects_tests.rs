@@ -0,0 +1,128 @@
+use kiss::redirects::{Redirects, Resolution};
+
+#[cfg(test)]
+mod redirect_rules_tests {
+    use super::*;
+
+    fn rules(text: &str) -> Redirects {
+        let (redirects, errors) = Redirects::parse(text);
+        assert!(errors.is_empty(), "Unexpected errors: {:?}", errors);
+        redirects
+    }
+
+    fn redirect(status: u16, location: &str) -> Option<Resolution> {
+        Some(Resolution::Redirect { status, location: location.to_string() })
+    }
+
+    #[test]
+    fn test_exact_redirects() {
+        let redirects = rules("# comment\n\n/home / 301\n/old /new\n/temp /elsewhere 302\n");
+        assert_eq!(redirects.len(), 3);
+        assert_eq!(redirects.resolve("/old", None, false), redirect(301, "/new"), "Status defaults to 301");
+        assert_eq!(redirects.resolve("/old/", None, false), redirect(301, "/new"), "Trailing slash is optional");
+        assert_eq!(redirects.resolve("/temp", None, false), redirect(302, "/elsewhere"));
+        assert_eq!(redirects.resolve("/other", None, false), None);
+    }
+
+    #[test]
+    fn test_splats_and_placeholders() {
+        let redirects = rules(
+            "/blog/*  /news/:splat  302\n/news/:year/:slug  /articles/:year-:slug  307\n/*  https://example.com:8443/:splat  308\n",
+        );
+        assert_eq!(redirects.resolve("/blog/2024/post", None, false), redirect(302, "/news/2024/post"));
+        assert_eq!(redirects.resolve("/blog", None, false), redirect(302, "/news/"));
+        assert_eq!(redirects.resolve("/news/2024/hello", None, false), redirect(307, "/articles/2024-hello"));
+        assert_eq!(
+            redirects.resolve("/news/2024", None, false),
+            redirect(308, "https://example.com:8443/news/2024"),
+            "Placeholders match exactly one segment each; unknown names like the port stay literal"
+        );
+    }
+
+    #[test]
+    fn test_captures_are_encoded() {
+        let redirects = rules("/files/*  /downloads/:splat\n");
+        assert_eq!(redirects.resolve("/files/my report.pdf", None, false), redirect(301, "/downloads/my%20report.pdf"));
+    }
+
+    #[test]
+    fn test_first_matching_rule_wins() {
+        let redirects = rules("/docs/*  /first  302\n/docs/intro  /second  302\n");
+        assert_eq!(redirects.resolve("/docs/intro", None, false), redirect(302, "/first"));
+
+        let redirects = rules("/docs/intro  /first  302\n/docs/*  /second  302\n");
+        assert_eq!(redirects.resolve("/docs/intro", None, false), redirect(302, "/first"));
+        assert_eq!(redirects.resolve("/docs/other", None, false), redirect(302, "/second"));
+    }
+
+    #[test]
+    fn test_files_shadow_rules_unless_forced() {
+        let redirects = rules("/page.html  /soft  302\n/page.html  /forced  302!\n");
+        assert_eq!(redirects.resolve("/page.html", None, false), redirect(302, "/soft"));
+        assert_eq!(redirects.resolve("/page.html", None, true), redirect(302, "/forced"));
+
+        let redirects = rules("/page.html  /soft  302\n");
+        assert_eq!(redirects.resolve("/page.html", None, true), None);
+    }
+
+    #[test]
+    fn test_query_carries_over() {
+        let redirects = rules("/search  /find  302\n/tagged  /find?tag=x  302\n");
+        assert_eq!(redirects.resolve("/search", Some("q=kiss"), false), redirect(302, "/find?q=kiss"));
+        assert_eq!(redirects.resolve("/tagged", Some("q=kiss"), false), redirect(302, "/find?tag=x"));
+    }
+
+    #[test]
+    fn test_passed_on_query_is_escaped() {
+        let redirects = rules("/home  /  302\n");
+        assert_eq!(
+            redirects.resolve("/home", Some("a\rSet-Cookie:evil=1"), false),
+            redirect(302, "/?a%0DSet-Cookie:evil=1")
+        );
+        assert_eq!(redirects.resolve("/home", Some("a\nb\x01c\x7fd e"), false), redirect(302, "/?a%0Ab%01c%7Fd%20e"));
+        assert_eq!(redirects.resolve("/home", Some("q=caf%C3%A9&x=/y?z"), false), redirect(302, "/?q=caf%C3%A9&x=/y?z"), "Valid queries are kept as sent");
+    }
+
+    #[test]
+    fn test_rewrites() {
+        let redirects = rules("/app/*  /app/index.html  200\n/p/:id  /posts/:id.html  200\n");
+        assert_eq!(redirects.resolve("/app/settings/profile", None, false), Some(Resolution::Rewrite("/app/index.html".to_string())));
+        assert_eq!(redirects.resolve("/p/caf\u{e9}", None, false), Some(Resolution::Rewrite("/posts/caf\u{e9}.html".to_string())), "Rewrites yield decoded cache paths");
+    }
+
+    #[test]
+    fn test_fixed_redirects_are_prebuilt() {
+        let mut redirects = rules("/old  /new  308\n/blog/*  /news/:splat\n");
+        redirects.prebuild(|status, location| format!("{} {}", status, location).into_bytes());
+        assert_eq!(redirects.resolve("/old", None, false), Some(Resolution::Prebuilt(b"308 /new".to_vec().into())));
+        assert_eq!(redirects.resolve("/old", Some("a=1"), false), redirect(308, "/new?a=1"), "A passed-on query needs its own response");
+        assert_eq!(redirects.resolve("/blog/x", None, false), redirect(301, "/news/x"));
+    }
+
+    #[test]
+    fn test_invalid_rules_are_skipped() {
+        let (redirects, errors) = Redirects::parse(concat!(
+            "/ok  /fine\n",
+            "/missing-target\n",
+            "/gone  /x  404\n",
+            "/weird  /x  abc\n",
+            "/store  id=:id  /blog/:id\n",
+            "/geo  /x  302  Country=us\n",
+            "/proxy/*  https://api.example.com/:splat  200\n",
+            "/a/*/b  /x\n",
+            "https://example.com/*  /x\n",
+            "/rel  relative/path\n",
+        ));
+        assert_eq!(redirects.len(), 1);
+        let lines: Vec<usize> = errors.iter().map(|error| error.line).collect();
+        assert_eq!(lines, vec![2, 3, 4, 5, 6, 7, 8, 9, 10]);
+        assert_eq!(errors[1].to_string(), "line 3: status 404 is not supported");
+    }
+
+    #[test]
+    fn test_empty_rules_resolve_nothing() {
+        let redirects = Redirects::default();
+        assert!(redirects.is_empty());
+        assert_eq!(redirects.resolve("/anything", None, false), None);
+    }
+}
@@ -0,0 +1,205 @@
+use kiss::request::{head_lines, HeadLimits, HeadStatus, Rejection, RequestBuffer, MAX_DISCARDED_BODY};
+
+#[cfg(test)]
+mod request_buffer_tests {
+    use super::*;
+
+    const LIMITS: HeadLimits = HeadLimits { request_line: 64, header_line: 32, header_block: 80, header_count: 4 };
+
+    fn buffer(bytes: &[u8]) -> RequestBuffer {
+        let mut buffer = RequestBuffer::with_capacity(64);
+        buffer.spare().extend_from_slice(bytes);
+        buffer
+    }
+
+    fn next_head(buffer: &mut RequestBuffer) -> (Vec<u8>, usize) {
+        match buffer.next_head(&LIMITS) {
+            HeadStatus::Complete { head, consumed, .. } => (buffer.get(head).to_vec(), consumed),
+            other => panic!("Expected a complete head, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_partial_heads() {
+        assert_eq!(buffer(b"").next_head(&LIMITS), HeadStatus::Partial);
+        assert_eq!(buffer(b"GET / HT").next_head(&LIMITS), HeadStatus::Partial);
+        assert_eq!(buffer(b"GET / HTTP/1.1\r\nHost: a\r\n").next_head(&LIMITS), HeadStatus::Partial);
+    }
+
+    #[test]
+    fn test_complete_head() {
+        let request = b"GET / HTTP/1.1\r\nHost: a\r\n\r\n";
+        let (head, consumed) = next_head(&mut buffer(request));
+        assert_eq!(head, b"GET / HTTP/1.1\r\nHost: a");
+        assert_eq!(consumed, request.len());
+
+        let lines: Vec<&[u8]> = head_lines(&head).collect();
+        assert_eq!(lines, vec![&b"GET / HTTP/1.1"[..], b"Host: a"]);
+
+    }
+
+    #[test]
+    fn test_leading_empty_lines_are_skipped() {
+        let (head, consumed) = next_head(&mut buffer(b"\r\n\r\nGET / HTTP/1.1\r\n\r\n"));
+        assert_eq!(head, b"GET / HTTP/1.1");
+        assert_eq!(consumed, 22);
+    }
+
+    #[test]
+    fn test_pipelined_requests_are_kept() {
+        let first = b"GET /a HTTP/1.1\r\nHost: a\r\n\r\n";
+        let mut bytes = first.to_vec();
+        bytes.extend_from_slice(b"HEAD /b HTTP/1.1\r\n\r\nGET /c HT");
+        let mut buffer = buffer(&bytes);
+
+        let (head, consumed) = next_head(&mut buffer);
+        assert!(head.starts_with(b"GET /a "));
+        assert_eq!(consumed, first.len());
+        buffer.consume(consumed);
+
+        let (head, consumed) = next_head(&mut buffer);
+        assert_eq!(head, b"HEAD /b HTTP/1.1");
+        buffer.consume(consumed);
+
+        assert_eq!(buffer.pending(), b"GET /c HT");
+        assert_eq!(buffer.next_head(&LIMITS), HeadStatus::Partial);
+
+        // The rest of the request arrives in a later read
+        buffer.spare().extend_from_slice(b"TP/1.1\r\n\r\n");
+        let (head, consumed) = next_head(&mut buffer);
+        assert_eq!(head, b"GET /c HTTP/1.1");
+        buffer.consume(consumed);
+        assert!(buffer.is_empty());
+    }
+
+    fn status(bytes: &[u8]) -> HeadStatus {
+        buffer(bytes).next_head(&LIMITS)
+    }
+
+    #[test]
+    fn test_limits() {
+        let long_target = format!("GET /{} HTTP/1.1\r\n\r\n", "a".repeat(64));
+        assert_eq!(status(long_target.as_bytes()), HeadStatus::Rejected(Rejection::RequestLine));
+        assert_eq!(status(&[b'a'; 65]), HeadStatus::Rejected(Rejection::RequestLine), "Rejected before the line ends");
+        assert_eq!(status(&b"\r\n".repeat(40)), HeadStatus::Rejected(Rejection::RequestLine));
+
+        let long_header = format!("GET / HTTP/1.1\r\nX-Long: {}\r\n\r\n", "a".repeat(32));
+        assert_eq!(status(long_header.as_bytes()), HeadStatus::Rejected(Rejection::HeaderLine));
+
+        let block = format!("GET / HTTP/1.1\r\n{}", "X-Header: 0123456789\r\n".repeat(4));
+        assert_eq!(status(block.as_bytes()), HeadStatus::Rejected(Rejection::HeaderBlock));
+
+        let count = format!("GET / HTTP/1.1\r\n{}\r\n", "A: 1\r\n".repeat(5));
+        assert_eq!(status(count.as_bytes()), HeadStatus::Rejected(Rejection::HeaderCount));
+
+        let at_limits = format!("GET / HTTP/1.1\r\n{}\r\n", "A: 1\r\n".repeat(4));
+        assert!(matches!(status(at_limits.as_bytes()), HeadStatus::Complete { .. }));
+    }
+
+    #[test]
+    fn test_head_arriving_in_pieces() {
+        let request = b"\r\nGET /a HTTP/1.1\r\nHost: a\r\nAccept: */*\r\n\r\n";
+        let mut buffer = RequestBuffer::with_capacity(64);
+        for &byte in &request[..request.len() - 1] {
+            buffer.spare().push(byte);
+            assert_eq!(buffer.next_head(&LIMITS), HeadStatus::Partial);
+        }
+        buffer.spare().push(b'\n');
+        let (head, consumed) = next_head(&mut buffer);
+        assert_eq!(head, b"GET /a HTTP/1.1\r\nHost: a\r\nAccept: */*");
+        assert_eq!(consumed, request.len());
+    }
+
+    #[test]
+    fn test_rejection_statuses() {
+        assert_eq!(Rejection::RequestLine.status(), 414);
+        assert_eq!(Rejection::HeaderCount.status(), 431);
+        assert_eq!(Rejection::BodyTooLarge.status(), 413);
+        assert_eq!(Rejection::TransferEncoding.status(), 400);
+    }
+}
+
+#[cfg(test)]
+mod framing_tests {
+    use super::*;
+
+    const LIMITS: HeadLimits = HeadLimits { request_line: 8192, header_line: 8192, header_block: 32768, header_count: 100 };
+
+    fn status(bytes: &[u8]) -> HeadStatus {
+        let mut buffer = RequestBuffer::with_capacity(64);
+        buffer.spare().extend_from_slice(bytes);
+        buffer.next_head(&LIMITS)
+    }
+
+    fn rejected(bytes: &[u8]) -> Rejection {
+        match status(bytes) {
+            HeadStatus::Rejected(reason) => reason,
+            other => panic!("Expected {:?} to be rejected, got {:?}", String::from_utf8_lossy(bytes), other),
+        }
+    }
+
+    #[test]
+    fn test_bare_lf_is_rejected() {
+        assert_eq!(rejected(b"GET / HTTP/1.1\n\n"), Rejection::BareLf);
+        assert_eq!(rejected(b"GET / HTTP/1.1\r\nHost: a\n\r\n"), Rejection::BareLf);
+        assert_eq!(rejected(b"GET / HTTP/1.1\r\nHost: a\r\n\n"), Rejection::BareLf);
+        assert_eq!(rejected(b"\nGET / HTTP/1.1\r\n\r\n"), Rejection::BareLf);
+    }
+
+    #[test]
+    fn test_malformed_header_lines_are_rejected() {
+        assert_eq!(rejected(b"GET / HTTP/1.1\r\nHost: a\r\n  folded\r\n\r\n"), Rejection::ObsFold);
+        assert_eq!(rejected(b"GET / HTTP/1.1\r\n\tHost: a\r\n\r\n"), Rejection::ObsFold);
+        assert_eq!(rejected(b"GET / HTTP/1.1\r\nHost : a\r\n\r\n"), Rejection::HeaderName);
+        assert_eq!(rejected(b"GET / HTTP/1.1\r\nNo colon here\r\n\r\n"), Rejection::HeaderName);
+        assert_eq!(rejected(b"GET / HTTP/1.1\r\n: empty name\r\n\r\n"), Rejection::HeaderName);
+    }
+
+    #[test]
+    fn test_transfer_encoding_is_rejected() {
+        assert_eq!(rejected(b"GET / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n"), Rejection::TransferEncoding);
+        assert_eq!(rejected(b"POST / HTTP/1.1\r\ntransfer-encoding: identity\r\nContent-Length: 3\r\n\r\n"), Rejection::TransferEncoding);
+    }
+
+    #[test]
+    fn test_content_length_must_be_single_and_numeric() {
+        for head in [
+            &b"GET / HTTP/1.1\r\nContent-Length: 5\r\nContent-Length: 5\r\n\r\n"[..],
+            b"GET / HTTP/1.1\r\nContent-Length: 5\r\nContent-Length: 6\r\n\r\n",
+            b"GET / HTTP/1.1\r\nContent-Length: 5, 5\r\n\r\n",
+            b"GET / HTTP/1.1\r\nContent-Length: +5\r\n\r\n",
+            b"GET / HTTP/1.1\r\nContent-Length: \r\n\r\n",
+            b"GET / HTTP/1.1\r\nContent-Length: 99999999999999999999999\r\n\r\n",
+        ] {
+            assert_eq!(rejected(head), Rejection::ContentLength, "{}", String::from_utf8_lossy(head));
+        }
+
+        let too_large = format!("GET / HTTP/1.1\r\nContent-Length: {}\r\n\r\n", MAX_DISCARDED_BODY + 1);
+        assert_eq!(rejected(too_large.as_bytes()), Rejection::BodyTooLarge);
+    }
+
+    #[test]
+    fn test_bodies_are_discarded() {
+        let mut buffer = RequestBuffer::with_capacity(64);
+        buffer.spare().extend_from_slice(b"POST /a HTTP/1.1\r\nContent-Length:  10 \r\n\r\nGET /smug");
+
+        let (consumed, body) = match buffer.next_head(&LIMITS) {
+            HeadStatus::Complete { consumed, body, .. } => (consumed, body),
+            other => panic!("Expected a complete head, got {:?}", other),
+        };
+        assert_eq!(body, 10);
+        buffer.consume(consumed);
+        buffer.discard(body);
+
+        // Part of the body is buffered, the rest arrives later - none of it is parsed
+        assert_eq!(buffer.next_head(&LIMITS), HeadStatus::Partial);
+        buffer.spare().extend_from_slice(b"!GET /b HTTP/1.1\r\n\r\n");
+        match buffer.next_head(&LIMITS) {
+            HeadStatus::Complete { head, body, .. } => {
+                assert_eq!(buffer.get(head), b"GET /b HTTP/1.1");
+                assert_eq!(body, 0);
+            }
+            other => panic!("Expected the request after the body, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,96 @@
+use kiss::clock::DateHeader;
+use kiss::response::{Chunk, ResponseQueue, Sent};
+use std::sync::Arc;
+
+#[cfg(test)]
+mod response_queue_tests {
+    use super::*;
+
+    const DATE: &str = "Date: Sun, 06 Nov 1994 08:49:37 GMT\r\n";
+    const CACHED: &str = "HTTP/1.1 200 OK\r\nContent-Length: 22\r\nConnection: keep-alive\r\n\r\nConnection: keep-alive";
+
+    fn date() -> DateHeader {
+        DATE.as_bytes().try_into().unwrap()
+    }
+
+    fn queue(draining: fn() -> bool) -> ResponseQueue {
+        ResponseQueue::new(date, draining)
+    }
+
+    fn written(queue: &ResponseQueue) -> String {
+        String::from_utf8(queue.chunks().flatten().copied().collect()).unwrap()
+    }
+
+    #[test]
+    fn test_date_follows_the_status_line() {
+        let cached: Arc<[u8]> = Arc::from(CACHED.as_bytes());
+        let expected = CACHED.replacen("\r\n", &format!("\r\n{}", DATE), 1);
+
+        let mut shared = queue(|| false);
+        let sent = shared.push_shared(&cached);
+        assert_eq!(written(&shared), expected);
+        assert_eq!(sent, Sent { status: 200, bytes: expected.len() as u64 });
+        assert_eq!(Arc::strong_count(&cached), 3, "The cached response is sliced, not copied");
+
+        let mut owned = queue(|| false);
+        owned.push_owned(CACHED.as_bytes().to_vec());
+        assert_eq!(written(&owned), expected);
+    }
+
+    #[test]
+    fn test_last_pipelined_response_says_close() {
+        let cached: Arc<[u8]> = Arc::from(CACHED.as_bytes());
+        let mut output = queue(|| false);
+        output.push_shared(&cached);
+        output.push_shared(&cached);
+        output.closing = true;
+        output.push_shared(&cached);
+
+        let keep_alive = CACHED.replacen("\r\n", &format!("\r\n{}", DATE), 1);
+        let close = keep_alive.replacen("Connection: keep-alive", "Connection: close", 1);
+        assert_eq!(written(&output), format!("{}{}{}", keep_alive, keep_alive, close));
+        assert!(close.ends_with("\r\n\r\nConnection: keep-alive"), "The body is never rewritten");
+
+        let mut owned = queue(|| false);
+        owned.closing = true;
+        owned.push_owned(CACHED.as_bytes().to_vec());
+        assert_eq!(written(&owned), close);
+    }
+
+    #[test]
+    fn test_draining_closes_every_response() {
+        let mut output = queue(|| true);
+        let sent = output.push_static(b"HTTP/1.1 404 Not Found\r\nConnection: keep-alive\r\n\r\n");
+        assert_eq!(written(&output), format!("HTTP/1.1 404 Not Found\r\n{}Connection: close\r\n\r\n", DATE));
+        assert_eq!(sent.status, 404);
+    }
+
+    #[test]
+    fn test_heads_without_connection_header() {
+        let head = "HTTP/1.1 304 Not Modified\r\nETag: \"x\"\r\n\r\n";
+        let expected = format!("HTTP/1.1 304 Not Modified\r\n{}ETag: \"x\"\r\n\r\n", DATE);
+
+        let mut output = queue(|| true);
+        output.push_final(b"HTTP/1.1 304 Not Modified\r\nETag: \"x\"\r\n\r\n");
+        assert_eq!(written(&output), expected);
+
+        let mut owned = queue(|| true);
+        owned.closing = true;
+        owned.push_owned(head.as_bytes().to_vec());
+        assert_eq!(written(&owned), expected);
+    }
+
+    #[test]
+    fn test_body_chunks_follow_the_head() {
+        let file: Arc<[u8]> = Arc::from(&b"0123456789"[..]);
+        let mut output = queue(|| false);
+        let sent = output.push(
+            Chunk::Owned(b"HTTP/1.1 206 Partial Content\r\nContent-Length: 4\r\n\r\n".to_vec()),
+            Some(Chunk::shared(&file, 2..6)),
+        );
+        let expected = format!("HTTP/1.1 206 Partial Content\r\n{}Content-Length: 4\r\n\r\n2345", DATE);
+        assert_eq!(written(&output), expected);
+        assert_eq!(sent, Sent { status: 206, bytes: expected.len() as u64 });
+        assert_eq!(output.len(), expected.len());
+    }
+}
@@ -0,0 +1,234 @@
+use kiss::pattern::Pattern;
+use kiss::rules::{is_content_hashed, HeaderRules, RuleError};
+
+#[cfg(test)]
+mod glob_tests {
+    use super::*;
+
+    fn matches(glob: &str, path: &str) -> bool {
+        Pattern::glob(glob).unwrap().is_match(path)
+    }
+
+    #[test]
+    fn test_file_name_globs_match_in_any_directory() {
+        assert!(matches("*.html", "/index.html"));
+        assert!(matches("*.html", "/docs/guide/index.html"));
+        assert!(!matches("*.html", "/index.html.br"));
+        assert!(!matches("*.html", "/style.css"));
+        assert!(matches("index.html", "/a/index.html"));
+        assert!(!matches("index.html", "/a/myindex.html"));
+    }
+
+    #[test]
+    fn test_rooted_globs() {
+        assert!(matches("/assets/*", "/assets/app.js"));
+        assert!(!matches("/assets/*", "/assets/img/logo.png"), "* stays within a segment");
+        assert!(matches("/assets/**", "/assets/img/logo.png"));
+        assert!(matches("/assets/**/*.png", "/assets/logo.png"), "**/ also matches no directory");
+        assert!(matches("/assets/**/*.png", "/assets/a/b/logo.png"));
+        assert!(matches("assets/*.js", "/assets/app.js"), "Paths with a slash are rooted");
+        assert!(!matches("/assets/*", "/static/assets/app.js"));
+    }
+
+    #[test]
+    fn test_glob_classes_and_alternatives() {
+        assert!(matches("/v?/api.json", "/v1/api.json"));
+        assert!(!matches("/v?/api.json", "/v10/api.json"));
+        assert!(matches("*.{js,css}", "/app.css"));
+        assert!(!matches("*.{js,css}", "/app.json"));
+        assert!(matches("/img/[a-c]*.png", "/img/banner.png"));
+        assert!(!matches("/img/[!a-c]*.png", "/img/banner.png"));
+        assert!(!matches("/a[/]b", "/a/b"), "Classes never match the separator");
+        assert!(matches("/file\\*.txt", "/file*.txt"));
+        assert!(!matches("/file\\*.txt", "/file1.txt"));
+        assert!(matches("/caf\u{e9}.txt", "/caf\u{e9}.txt"));
+    }
+
+    #[test]
+    fn test_invalid_globs() {
+        for glob in ["", "*.{js,css", "/a}", "/[abc", "/[z-a]"] {
+            assert!(Pattern::glob(glob).is_err(), "{:?} should be rejected", glob);
+        }
+    }
+}
+
+#[cfg(test)]
+mod regex_tests {
+    use super::*;
+
+    fn matches(regex: &str, path: &str) -> bool {
+        Pattern::regex(regex).unwrap().is_match(path)
+    }
+
+    #[test]
+    fn test_unanchored_and_anchored() {
+        assert!(matches(r"\.pdf", "/docs/report.pdf"));
+        assert!(matches(r"\.pdf$", "/docs/report.pdf"));
+        assert!(!matches(r"\.pdf$", "/docs/report.pdf.gz"));
+        assert!(matches(r"^/docs/", "/docs/a"));
+        assert!(!matches(r"^/docs/", "/old/docs/a"));
+        assert!(matches(r"^/x.y$", "/xay"), ". matches any character");
+    }
+
+    #[test]
+    fn test_groups_alternation_and_quantifiers() {
+        let regex = r"^/downloads/.*\.(zip|tar\.gz)$";
+        assert!(matches(regex, "/downloads/v1/kiss.tar.gz"));
+        assert!(matches(regex, "/downloads/kiss.zip"));
+        assert!(!matches(regex, "/downloads/kiss.tar"));
+
+        assert!(matches(r"^/v\d+/", "/v12/api"));
+        assert!(!matches(r"^/v\d+/", "/v/api"));
+        assert!(matches(r"^/a{2,3}$", "/aaa"));
+        assert!(!matches(r"^/a{2,3}$", "/aaaa"));
+        assert!(matches(r"^/a{2}b?$", "/aab"));
+        assert!(matches(r"^/(?:en|fr)(/.*)?$", "/fr"));
+        assert!(matches(r"^/[^/]+\.js$", "/app.js"));
+        assert!(!matches(r"^/[^/]+\.js$", "/lib/app.js"));
+        assert!(matches(r"^/\w+-\d+\.css$", "/main-2024.css"));
+        assert!(matches(r"^/x\{1$", "/x{1"), "Escaped braces are literal");
+        assert!(matches(r"^/(a*)*b$", "/aaab"), "Empty iterations don't loop");
+    }
+
+    #[test]
+    fn test_matching_time_is_linear() {
+        // Exponential for a backtracking matcher, and a stack frame per byte for a
+        // recursive one
+        let path = format!("/{}!", "a".repeat(100_000));
+        assert!(!matches(r"^/(a+)+$", &path));
+        assert!(!matches(r"^/(a|aa)*$", &path));
+        assert!(matches(r"a!$", &path));
+        assert!(Pattern::glob("/**/*.{js,css}").unwrap().is_match(&format!("{}/app.js", "/dir".repeat(50_000))));
+    }
+
+    #[test]
+    fn test_invalid_regexes() {
+        for regex in ["(", "a)", "[abc", "*a", r"\", r"\1", "a{3,1}", "x{1", "a{1000}{1000}"] {
+            assert!(Pattern::regex(regex).is_err(), "{:?} should be rejected", regex);
+        }
+        assert_eq!(Pattern::regex("a{1000}{1000}").unwrap_err().reason, "pattern too large");
+        assert_eq!(Pattern::regex("a)").unwrap_err().to_string(), "invalid pattern 'a)': unopened group");
+    }
+}
+
+#[cfg(test)]
+mod hashed_filename_tests {
+    use super::*;
+
+    #[test]
+    fn test_hashed_names_are_detected() {
+        for path in [
+            "/assets/app.3f9a2c1d.js",
+            "/main-8e2b1c0d4f.css",
+            "/_next/static/chunks/main-0a1b2c3d4e5f6a7b.js",
+            "/fonts/inter.a1b2c3d4.woff2",
+            "/app.c0ffee42.min.js",
+            "/app.3f9a2c.js",
+            "/0a1b2c3d4e5f6a7b.js",
+        ] {
+            assert!(is_content_hashed(path), "{} should be detected", path);
+        }
+    }
+
+    #[test]
+    fn test_ordinary_names_are_not() {
+        for path in [
+            "/index.html",
+            "/app.js",
+            "/jquery-3.6.0.min.js",
+            "/report-20240101.pdf",
+            "/decade.css",
+            "/fonts/Inter-Regular2.woff2",
+            "/3f9a2c/app.js",
+            "/.htaccess",
+            "/Report22.pdf",
+            "/README12.md",
+            "/Invoice2024A.pdf",
+            "/docs/Setup-V2Guide1.pdf",
+            "/app_c0ffee42.js",
+            "/a1b2c.js",
+            "/assets/index-B2xK9f3a.js",
+        ] {
+            assert!(!is_content_hashed(path), "{} should not be detected", path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod header_rules_tests {
+    use super::*;
+
+    const RULES: &str = "\
+# Entry points must always revalidate
+*.html
+  Cache-Control: no-cache
+
+@hashed
+  Cache-Control: public, max-age=31536000, immutable
+
+~ ^/downloads/.*\\.(zip|tar\\.gz)$
+  Content-Disposition: attachment
+\tX-Robots-Tag: noindex
+
+/downloads/public.zip
+  content-disposition: inline
+";
+
+    fn syntax_error_line(text: &str) -> usize {
+        match HeaderRules::parse(text) {
+            Err(RuleError::Syntax { line, .. }) => line,
+            other => panic!("Expected a syntax error for {:?}, got {:?}", text, other),
+        }
+    }
+
+    #[test]
+    fn test_matching_rules_apply_in_order() {
+        let rules = HeaderRules::parse(RULES).unwrap();
+        assert_eq!(rules.len(), 4);
+
+        assert_eq!(rules.headers_for("/index.html"), vec![("Cache-Control", "no-cache")]);
+        assert_eq!(rules.headers_for("/app.3f9a2c.js"), vec![("Cache-Control", "public, max-age=31536000, immutable")]);
+        assert_eq!(
+            rules.headers_for("/downloads/kiss.tar.gz"),
+            vec![("Content-Disposition", "attachment"), ("X-Robots-Tag", "noindex")]
+        );
+        assert!(rules.headers_for("/style.css").is_empty());
+    }
+
+    #[test]
+    fn test_later_rules_replace_earlier_values() {
+        let rules = HeaderRules::parse(RULES).unwrap();
+        assert_eq!(
+            rules.headers_for("/downloads/public.zip"),
+            vec![("Content-Disposition", "inline"), ("X-Robots-Tag", "noindex")]
+        );
+    }
+
+    #[test]
+    fn test_syntax_errors_report_lines() {
+        assert_eq!(syntax_error_line("  Cache-Control: no-cache\n"), 1);
+        assert_eq!(syntax_error_line("*.css\n*.js\n  Cache-Control: no-cache\n"), 1);
+        assert_eq!(syntax_error_line("*.css\n"), 1);
+        assert_eq!(syntax_error_line("*.css\nCache-Control: no-cache\n"), 2);
+        assert_eq!(syntax_error_line("# a\n*.css\n  Cache-Control\n"), 3);
+        assert_eq!(syntax_error_line("*.css\n  Bad Name: x\n"), 2);
+        assert_eq!(syntax_error_line("*.css\n  X-Empty:\n"), 2);
+        assert_eq!(syntax_error_line("\n@fingerprinted\n  Cache-Control: x\n"), 2);
+        assert_eq!(syntax_error_line("~ (\n  Cache-Control: x\n"), 1);
+    }
+
+    #[test]
+    fn test_server_managed_headers_are_reserved() {
+        for header in ["Content-Length", "content-type", "ETag", "Connection", "Vary", "Date"] {
+            let text = format!("*.css\n  {}: x\n", header);
+            assert_eq!(syntax_error_line(&text), 2, "{} should be reserved", header);
+        }
+    }
+
+    #[test]
+    fn test_empty_rules() {
+        let rules = HeaderRules::parse("# nothing yet\n\n").unwrap();
+        assert!(rules.is_empty());
+        assert!(rules.headers_for("/index.html").is_empty());
+    }
+}
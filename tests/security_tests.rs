@@ -2,6 +2,7 @@ use std::fs;
 use std::io::{Read, Write};
 use std::net::TcpStream;
 use tempfile::TempDir;
+use kiss::security::{SecurityHeaders, SecurityProfile};
 
 
 #[cfg(test)]
@@ -118,4 +119,57 @@ mod filesystem_security_tests {
         let canonical_static = static_dir.canonicalize().unwrap();
         assert!(canonical.starts_with(canonical_static));
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod security_header_tests {
+    use super::*;
+
+    fn names(headers: &SecurityHeaders) -> Vec<&'static str> {
+        headers.iter().map(|(name, _)| name).collect()
+    }
+
+    #[test]
+    fn test_profiles() {
+        assert!(SecurityHeaders::new(SecurityProfile::None, &[]).render().is_empty());
+        assert_eq!(
+            names(&SecurityHeaders::new(SecurityProfile::Baseline, &[])),
+            vec!["X-Content-Type-Options", "Referrer-Policy", "X-Frame-Options"]
+        );
+        assert_eq!(names(&SecurityHeaders::new(SecurityProfile::Custom, &[])), vec!["X-Content-Type-Options"]);
+
+        let strict = SecurityHeaders::new(SecurityProfile::Strict, &[]);
+        for header in ["Content-Security-Policy", "Strict-Transport-Security", "Cross-Origin-Embedder-Policy"] {
+            assert!(names(&strict).contains(&header), "strict should send {}", header);
+        }
+        assert_eq!(SecurityProfile::parse("STRICT"), Some(SecurityProfile::Strict));
+        assert_eq!(SecurityProfile::parse("off"), Some(SecurityProfile::None));
+        assert_eq!(SecurityProfile::parse("paranoid"), None);
+    }
+
+    #[test]
+    fn test_overrides_replace_remove_and_add() {
+        let headers = SecurityHeaders::new(
+            SecurityProfile::Baseline,
+            &[
+                ("X-Frame-Options", Some("DENY".to_string())),
+                ("Referrer-Policy", None),
+                ("Content-Security-Policy", Some("default-src 'self'".to_string())),
+                ("Cross-Origin-Opener-Policy", None),
+            ],
+        );
+        assert_eq!(
+            headers.render(),
+            "X-Content-Type-Options: nosniff\r\nX-Frame-Options: DENY\r\nContent-Security-Policy: default-src 'self'\r\n"
+        );
+    }
+
+    #[test]
+    fn test_later_overrides_win() {
+        let headers = SecurityHeaders::new(
+            SecurityProfile::None,
+            &[("Strict-Transport-Security", Some("max-age=60".to_string())), ("Strict-Transport-Security", None)],
+        );
+        assert!(headers.render().is_empty());
+    }
+}
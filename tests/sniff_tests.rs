@@ -0,0 +1,88 @@
+use kiss::mime::{ContentType, MimeTypes};
+use kiss::sniff::{detect_charset, sniff, Charset};
+use kiss::MimeType;
+use std::path::Path;
+
+#[cfg(test)]
+mod sniff_tests {
+    use super::*;
+
+    #[test]
+    fn test_binary_signatures() {
+        let cases: &[(&[u8], MimeType)] = &[
+            (b"\x89PNG\r\n\x1a\n\x00\x00\x00\rIHDR", MimeType::Png),
+            (b"\xff\xd8\xff\xe0\x00\x10JFIF", MimeType::Jpeg),
+            (b"GIF89a\x01\x00", MimeType::Gif),
+            (b"%PDF-1.7\n", MimeType::Pdf),
+            (b"\x00asm\x01\x00\x00\x00", MimeType::Wasm),
+            (b"\x1f\x8b\x08\x00\x00\x00", MimeType::Gzip),
+            (b"PK\x03\x04\x14\x00", MimeType::Zip),
+            (b"wOF2\x00\x01", MimeType::Woff2),
+            (b"RIFF\x24\x00\x00\x00WEBPVP8 ", MimeType::Webp),
+            (b"\x00\x00\x00\x1cftypavif\x00\x00", MimeType::Avif),
+            (b"\x00\x00\x00\x18ftypisom\x00\x00", MimeType::Mp4),
+        ];
+        for (content, expected) in cases {
+            assert_eq!(sniff(content), Some(*expected), "{:?}", content);
+        }
+    }
+
+    #[test]
+    fn test_markup() {
+        assert_eq!(sniff(b"<!DOCTYPE html>\n<html>"), Some(MimeType::Html));
+        assert_eq!(sniff(b"\xef\xbb\xbf\n  <HTML lang=\"en\">"), Some(MimeType::Html), "BOM and whitespace are skipped");
+        assert_eq!(sniff(b"<p>legacy fragment</p>"), Some(MimeType::Html));
+        assert_eq!(sniff(b"<!-- generated -->"), Some(MimeType::Html));
+        assert_eq!(sniff(b"<svg xmlns=\"http://www.w3.org/2000/svg\">"), Some(MimeType::Svg));
+        assert_eq!(sniff(b"<?xml version=\"1.0\"?>"), Some(MimeType::Xml));
+        assert_eq!(sniff(b"<abbr>not a tag we trust</abbr>"), Some(MimeType::PlainText), "<a must be followed by a space or >");
+    }
+
+    #[test]
+    fn test_text_and_unknown_binary() {
+        assert_eq!(sniff(b"MIT License\n\nCopyright"), Some(MimeType::PlainText));
+        assert_eq!(sniff(b"caf\xe9 au lait\r\n"), Some(MimeType::PlainText), "Legacy encodings are still text");
+        assert_eq!(sniff(b"\xff\xfeh\x00i\x00"), Some(MimeType::PlainText), "UTF-16 with a BOM");
+        assert_eq!(sniff(b"BMW owner's manual"), Some(MimeType::PlainText));
+        assert_eq!(sniff(b"\x7fELF\x02\x01\x01\x00"), None);
+    }
+}
+
+#[cfg(test)]
+mod charset_tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_charset() {
+        assert_eq!(detect_charset(b"plain ascii"), Charset::Utf8);
+        assert_eq!(detect_charset("caf\u{e9}".as_bytes()), Charset::Utf8);
+        assert_eq!(detect_charset(b"\xef\xbb\xbfcaf\xc3\xa9"), Charset::Utf8);
+        assert_eq!(detect_charset(b"\xff\xfeh\x00i\x00"), Charset::Utf16);
+        assert_eq!(detect_charset(b"\xfe\xff\x00h\x00i"), Charset::Utf16);
+        assert_eq!(detect_charset(b"caf\xe9"), Charset::Latin1);
+        assert_eq!(detect_charset(b"\x93quoted\x94"), Charset::Windows1252);
+    }
+
+    #[test]
+    fn test_default_charset_follows_content() {
+        let html = ContentType::from(MimeType::Html);
+        assert_eq!(html.clone().with_charset_of(b"<p>ok</p>").as_str(), "text/html; charset=utf-8");
+        assert_eq!(html.clone().with_charset_of(b"<p>caf\xe9</p>").as_str(), "text/html; charset=iso-8859-1");
+        assert_eq!(html.with_charset_of(b"\xff\xfe<\x00p\x00").as_str(), "text/html; charset=utf-16");
+
+        // Binary types have no charset to correct
+        assert_eq!(ContentType::from(MimeType::Png).with_charset_of(b"\x89PNG\xff").as_str(), "image/png");
+    }
+
+    #[test]
+    fn test_configured_charset_is_kept() {
+        let mut types = MimeTypes::default();
+        types.insert("csv", "text/csv; charset=iso-8859-1").unwrap();
+        types.insert("rst", "text/x-rst").unwrap();
+
+        let csv = types.lookup(Path::new("data.csv")).with_charset_of("na\u{ef}ve".as_bytes());
+        assert_eq!(csv.as_str(), "text/csv; charset=iso-8859-1");
+        let rst = types.lookup(Path::new("index.rst")).with_charset_of(b"na\xefve");
+        assert_eq!(rst.as_str(), "text/x-rst; charset=iso-8859-1");
+    }
+}
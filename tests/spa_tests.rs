@@ -0,0 +1,112 @@
+mod common;
+
+use kiss::spa::{looks_like_asset, SpaFallback};
+
+#[cfg(test)]
+mod spa_fallback_tests {
+    use super::*;
+
+    fn fallback(include: &[&str], exclude: &[&str]) -> SpaFallback {
+        SpaFallback {
+            document: "/index.html".to_string(),
+            include: include.iter().map(|prefix| prefix.to_string()).collect(),
+            exclude: exclude.iter().map(|prefix| prefix.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_routes_fall_back() {
+        let spa = fallback(&["/"], &[]);
+        assert_eq!(spa.document_for("/dashboard/settings"), Some("/index.html"));
+        assert_eq!(spa.document_for("/dashboard/"), Some("/index.html"));
+        assert_eq!(spa.document_for("/"), Some("/index.html"));
+    }
+
+    #[test]
+    fn test_assets_never_fall_back() {
+        let spa = fallback(&["/"], &[]);
+        assert_eq!(spa.document_for("/assets/app.js"), None);
+        assert_eq!(spa.document_for("/favicon.ico"), None);
+        assert_eq!(spa.document_for("/v1.2/notes"), Some("/index.html"), "Only the last segment counts");
+    }
+
+    #[test]
+    fn test_prefix_controls() {
+        let spa = fallback(&["/app/", "/admin/"], &["/app/api/"]);
+        assert_eq!(spa.document_for("/app/users/42"), Some("/index.html"));
+        assert_eq!(spa.document_for("/admin/"), Some("/index.html"));
+        assert_eq!(spa.document_for("/app/api/users"), None);
+        assert_eq!(spa.document_for("/blog/post"), None);
+    }
+
+    #[test]
+    fn test_looks_like_asset() {
+        for path in ["/app.js", "/a/b/logo.svg", "/archive.tar.gz"] {
+            assert!(looks_like_asset(path), "{}", path);
+        }
+        for path in ["/", "/settings", "/docs/", "/.well-known", "/trailing."] {
+            assert!(!looks_like_asset(path), "{}", path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod spa_server_tests {
+    use crate::common::Server;
+    use std::io::{Read, Write};
+
+    const INDEX: &str = "<!doctype html><div id=\"app\"></div>";
+
+    fn start_server(args: &[&str]) -> Server {
+        let populate = |content: &std::path::Path| {
+            std::fs::write(content.join("index.html"), INDEX).unwrap();
+            std::fs::create_dir(content.join("assets")).unwrap();
+            std::fs::write(content.join("assets/app.js"), "console.log(1)").unwrap();
+        };
+        Server::start(populate, args)
+    }
+
+    fn request(server: &Server, path: &str, headers: &str) -> String {
+        let mut stream = server.connect();
+        let request = format!("GET {} HTTP/1.1\r\nHost: localhost\r\n{}Connection: close\r\n\r\n", path, headers);
+        stream.write_all(request.as_bytes()).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    fn header<'a>(response: &'a str, name: &str) -> Option<&'a str> {
+        let head = response.split("\r\n\r\n").next().unwrap();
+        head.lines().find_map(|line| line.strip_prefix(name)?.strip_prefix(": "))
+    }
+
+    #[test]
+    fn test_client_routes_get_the_index() {
+        let server = start_server(&["--spa"]);
+        let index = request(&server, "/index.html", "");
+        let etag = header(&index, "ETag").unwrap();
+
+        let route = request(&server, "/dashboard/settings/profile", "");
+        assert!(route.starts_with("HTTP/1.1 200 OK\r\n"), "{}", route);
+        assert!(route.ends_with(INDEX));
+        assert_eq!(header(&route, "ETag"), Some(etag), "The fallback is the cached document, with its validator");
+
+        let revalidated = request(&server, "/dashboard/settings/profile", &format!("If-None-Match: {}\r\n", etag));
+        assert!(revalidated.starts_with("HTTP/1.1 304 Not Modified\r\n"), "{}", revalidated);
+    }
+
+    #[test]
+    fn test_missing_assets_stay_not_found() {
+        let server = start_server(&["--spa", "--spa-exclude", "/api/"]);
+        assert!(request(&server, "/app.js", "").starts_with("HTTP/1.1 404 Not Found\r\n"));
+        assert!(request(&server, "/assets/missing.css", "").starts_with("HTTP/1.1 404 Not Found\r\n"));
+        assert!(request(&server, "/api/users", "").starts_with("HTTP/1.1 404 Not Found\r\n"));
+        assert!(request(&server, "/assets/app.js", "").ends_with("console.log(1)"));
+    }
+
+    #[test]
+    fn test_without_spa_routes_are_not_found() {
+        let server = start_server(&[]);
+        assert!(request(&server, "/dashboard", "").starts_with("HTTP/1.1 404 Not Found\r\n"));
+    }
+}
@@ -1,4 +1,5 @@
 use kiss::get_mime_type_enum;
+use kiss::mime::{parse_override, MimeTypes, MimeTypesError};
 use std::path::Path;
 
 
@@ -67,6 +68,40 @@ mod mime_type_tests {
         assert_eq!(get_mime_type_enum(Path::new("data.xyz")).as_str(), "application/octet-stream");
     }
     
+    #[test]
+    fn test_web_platform_types() {
+        assert_eq!(get_mime_type_enum(Path::new("app.wasm")).as_str(), "application/wasm");
+        assert_eq!(get_mime_type_enum(Path::new("module.mjs")).as_str(), "text/javascript; charset=utf-8");
+        assert_eq!(get_mime_type_enum(Path::new("app.js.map")).as_str(), "application/json; charset=utf-8");
+        assert_eq!(get_mime_type_enum(Path::new("site.webmanifest")).as_str(), "application/manifest+json; charset=utf-8");
+        assert_eq!(get_mime_type_enum(Path::new("photo.webp")).as_str(), "image/webp");
+        assert_eq!(get_mime_type_enum(Path::new("photo.AVIF")).as_str(), "image/avif");
+        assert_eq!(get_mime_type_enum(Path::new("font.otf")).as_str(), "font/otf");
+    }
+
+    #[test]
+    fn test_media_and_document_types() {
+        assert_eq!(get_mime_type_enum(Path::new("clip.mp4")).as_str(), "video/mp4");
+        assert_eq!(get_mime_type_enum(Path::new("clip.webm")).as_str(), "video/webm");
+        assert_eq!(get_mime_type_enum(Path::new("song.mp3")).as_str(), "audio/mpeg");
+        assert_eq!(get_mime_type_enum(Path::new("subs.vtt")).as_str(), "text/vtt; charset=utf-8");
+        assert_eq!(get_mime_type_enum(Path::new("data.csv")).as_str(), "text/csv; charset=utf-8");
+        assert_eq!(get_mime_type_enum(Path::new("README.md")).as_str(), "text/markdown; charset=utf-8");
+        assert_eq!(get_mime_type_enum(Path::new("server.log")).as_str(), "text/plain; charset=utf-8");
+        assert_eq!(get_mime_type_enum(Path::new("release.zip")).as_str(), "application/zip");
+        assert_eq!(get_mime_type_enum(Path::new("release.tar.gz")).as_str(), "application/gzip");
+    }
+
+    #[test]
+    fn test_compressible_types() {
+        for name in ["a.wasm", "a.mjs", "a.map", "a.csv", "a.md", "a.webmanifest", "a.m3u8"] {
+            assert!(get_mime_type_enum(Path::new(name)).is_compressible(), "{}", name);
+        }
+        for name in ["a.webp", "a.avif", "a.mp4", "a.mp3", "a.zip", "a.gz", "a.otf"] {
+            assert!(!get_mime_type_enum(Path::new(name)).is_compressible(), "{}", name);
+        }
+    }
+    
     #[test]
     fn test_path_with_directories() {
         assert_eq!(get_mime_type_enum(Path::new("/css/main.css")).as_str(), "text/css; charset=utf-8");
@@ -75,6 +110,71 @@ mod mime_type_tests {
     }
 }
 
+#[cfg(test)]
+mod mime_registry_tests {
+    use super::*;
+
+    fn lookup(types: &MimeTypes, name: &str) -> String {
+        types.lookup(Path::new(name)).as_str().to_string()
+    }
+
+    fn syntax_error_line(text: &str) -> usize {
+        match MimeTypes::parse(text) {
+            Err(MimeTypesError::Syntax { line, .. }) => line,
+            other => panic!("Expected a syntax error for {:?}, got {:?}", text, other),
+        }
+    }
+
+    #[test]
+    fn test_apache_format() {
+        let types = MimeTypes::parse("# type  extensions\napplication/x-custom\ttcx tcy\naudio/x-nothing\n\ntext/x-rst rst # docs\n").unwrap();
+        assert_eq!(types.len(), 3);
+        assert_eq!(lookup(&types, "a.TCY"), "application/x-custom");
+        assert_eq!(lookup(&types, "index.rst"), "text/x-rst; charset=utf-8", "Text types get the default charset");
+        assert!(types.lookup(Path::new("index.rst")).is_compressible());
+        assert_eq!(lookup(&types, "index.html"), "text/html; charset=utf-8", "Unlisted extensions keep the built-in type");
+    }
+
+    #[test]
+    fn test_nginx_format() {
+        let text = "types {\n    text/html  html htm;\n    application/javascript js\n        mjs;\n    image/x-icon ico;}\n";
+        let types = MimeTypes::parse(text).unwrap();
+        assert_eq!(lookup(&types, "app.mjs"), "application/javascript; charset=utf-8");
+        assert_eq!(lookup(&types, "page.htm"), "text/html; charset=utf-8");
+        assert_eq!(lookup(&types, "favicon.ico"), "image/x-icon");
+    }
+
+    #[test]
+    fn test_later_mappings_replace_earlier_ones() {
+        let mut types = MimeTypes::parse("application/octet-stream wasm\n").unwrap();
+        types.insert(".wasm", "application/wasm").unwrap();
+        types.insert("csv", "text/csv; charset=iso-8859-1").unwrap();
+        assert_eq!(lookup(&types, "app.wasm"), "application/wasm");
+        assert_eq!(lookup(&types, "data.csv"), "text/csv; charset=iso-8859-1");
+        assert!(types.insert("a/b", "text/plain").is_err());
+        assert!(types.insert("txt", "plain").is_err());
+    }
+
+    #[test]
+    fn test_syntax_errors_report_lines() {
+        assert_eq!(syntax_error_line("text/plain txt\nnot-a-type foo\n"), 2);
+        assert_eq!(syntax_error_line("types {\n  text/plain txt\n}\n"), 3);
+        assert_eq!(syntax_error_line("text/plain txt }\n"), 1);
+        assert_eq!(syntax_error_line("mime {\n"), 1);
+        assert_eq!(syntax_error_line("text/plain txt\n\nbad\n"), 3);
+        assert_eq!(syntax_error_line("types {\n  text/plain txt;\n"), 2);
+    }
+
+    #[test]
+    fn test_parse_override() {
+        assert_eq!(parse_override("wasm=application/wasm"), Ok(("wasm".to_string(), "application/wasm".to_string())));
+        assert_eq!(parse_override(" .rst = text/x-rst "), Ok((".rst".to_string(), "text/x-rst".to_string())));
+        assert!(parse_override("wasm").is_err());
+        assert!(parse_override("=text/plain").is_err());
+        assert!(parse_override("txt=text/plain\r\nX-Injected: 1").is_err());
+    }
+}
+
 #[cfg(test)]
 mod health_endpoint_tests {
     
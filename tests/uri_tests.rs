@@ -0,0 +1,68 @@
+use kiss::uri::{encode_path, normalize_path, parse_target, TargetError};
+
+#[cfg(test)]
+mod uri_tests {
+    use super::*;
+
+    // The canonical path and whether the target was already spelled canonically
+    fn canonical(target: &str) -> (String, bool) {
+        let target = parse_target(target).unwrap();
+        (target.path.to_string(), target.is_canonical())
+    }
+
+    #[test]
+    fn test_clean_targets_are_canonical() {
+        assert_eq!(canonical("/"), ("/".to_string(), true));
+        assert_eq!(canonical("/docs/"), ("/docs/".to_string(), true));
+        assert_eq!(canonical("/css/app.css?v=3"), ("/css/app.css".to_string(), true));
+        assert_eq!(canonical("/my%20file.html"), ("/my file.html".to_string(), true));
+        assert_eq!(canonical("/caf%C3%A9.txt"), ("/caf\u{e9}.txt".to_string(), true));
+        assert_eq!(canonical("/100%25.txt"), ("/100%.txt".to_string(), true));
+        assert_eq!(canonical("/a:b@c;d=e.txt"), ("/a:b@c;d=e.txt".to_string(), true));
+    }
+
+    #[test]
+    fn test_other_spellings_are_not() {
+        assert_eq!(canonical("/a//b.css"), ("/a/b.css".to_string(), false));
+        assert_eq!(canonical("/./x.js"), ("/x.js".to_string(), false));
+        assert_eq!(canonical("/docs/../index.html"), ("/index.html".to_string(), false));
+        assert_eq!(canonical("/%7Euser/"), ("/~user/".to_string(), false), "Unreserved characters are not escaped");
+        assert_eq!(canonical("/caf%c3%a9.txt"), ("/caf\u{e9}.txt".to_string(), false), "Escapes are uppercase");
+        assert_eq!(canonical("/%2e%2e/etc/passwd"), ("/etc/passwd".to_string(), false));
+    }
+
+    #[test]
+    fn test_canonical_uri() {
+        assert_eq!(parse_target("//a/./b%7e/../c.txt?x=1").unwrap().canonical_uri(), "/a/c.txt?x=1");
+        assert_eq!(parse_target("/caf\u{e9}.txt?q=\u{e9}\"x\"").unwrap().canonical_uri(), "/caf%C3%A9.txt?q=%C3%A9%22x%22");
+        assert_eq!(encode_path("/what?.txt"), "/what%3F.txt");
+    }
+
+    #[test]
+    fn test_invalid_targets() {
+        assert_eq!(parse_target("index.html"), Err(TargetError::NotOriginForm));
+        assert_eq!(parse_target("http://example.com/"), Err(TargetError::NotOriginForm));
+        assert_eq!(parse_target("/a%2Fb"), Err(TargetError::EncodedSlash));
+        assert_eq!(parse_target("/a%00.html"), Err(TargetError::EncodedNul));
+        assert_eq!(parse_target("/a%zz"), Err(TargetError::BadPercentEncoding));
+        assert_eq!(parse_target("/a%4"), Err(TargetError::BadPercentEncoding));
+        assert_eq!(parse_target("/%FF.html"), Err(TargetError::InvalidUtf8));
+    }
+
+    #[test]
+    fn test_control_bytes_are_rejected() {
+        for target in ["/a\rb.html", "/a\nb", "/a\tb", "/a b", "/a\x7fb", "/\x00", "/page?a\rSet-Cookie:x=1", "/page?q=a b", "/page?\x7f"] {
+            assert_eq!(parse_target(target), Err(TargetError::InvalidCharacter), "{:?}", target);
+        }
+        assert!(parse_target("/page?q=caf\u{e9}&x=%0D").is_ok(), "Escaped and non-ASCII bytes are fine");
+    }
+
+    #[test]
+    fn test_normalize_path() {
+        assert_eq!(normalize_path("/a/b/../../.."), "/");
+        assert_eq!(normalize_path("/a/b/.."), "/a/");
+        assert_eq!(normalize_path("/a/."), "/a/");
+        assert_eq!(normalize_path("///"), "/");
+        assert_eq!(normalize_path("/a/..b/c"), "/a/..b/c", "Only whole segments are dot-segments");
+    }
+}
@@ -0,0 +1,97 @@
+use kiss::url_style::{Located, TrailingSlash, UrlStyle};
+use std::collections::HashMap;
+
+#[cfg(test)]
+mod url_style_tests {
+    use super::*;
+
+    // A cache stand-in: files by path, with `index.html` answering for its directory
+    fn site() -> HashMap<&'static str, &'static str> {
+        HashMap::from([
+            ("/index.html", "home"),
+            ("/", "home"),
+            ("/about.html", "about"),
+            ("/docs/index.html", "docs"),
+            ("/docs/", "docs"),
+            ("/notes", "extensionless"),
+            ("/notes.html", "notes page"),
+        ])
+    }
+
+    fn url_style(trailing_slash: TrailingSlash, clean_urls: bool) -> UrlStyle {
+        UrlStyle { trailing_slash, clean_urls, index_files: vec!["index.html".to_string()] }
+    }
+
+    fn locate(style: &UrlStyle, path: &str) -> Located<&'static str> {
+        let site = site();
+        style.locate(path, |path| site.get(path).copied())
+    }
+
+    fn redirect(path: &str) -> Located<&'static str> {
+        Located::Redirect(path.to_string())
+    }
+
+    #[test]
+    fn test_off_keeps_plain_lookups() {
+        let style = url_style(TrailingSlash::Off, false);
+        assert_eq!(locate(&style, "/docs/"), Located::Found("docs"));
+        assert_eq!(locate(&style, "/docs"), Located::Missing);
+        assert_eq!(locate(&style, "/about"), Located::Missing);
+        assert_eq!(locate(&style, "/about.html"), Located::Found("about"));
+    }
+
+    #[test]
+    fn test_add_trailing_slash() {
+        let style = url_style(TrailingSlash::Add, false);
+        assert_eq!(locate(&style, "/docs"), redirect("/docs/"));
+        assert_eq!(locate(&style, "/docs/"), Located::Found("docs"));
+        assert_eq!(locate(&style, "/"), Located::Found("home"));
+        assert_eq!(locate(&style, "/about.html"), Located::Found("about"), "Files never get a slash");
+        assert_eq!(locate(&style, "/missing"), Located::Missing);
+    }
+
+    #[test]
+    fn test_remove_trailing_slash() {
+        let style = url_style(TrailingSlash::Remove, false);
+        assert_eq!(locate(&style, "/docs"), Located::Found("docs"));
+        assert_eq!(locate(&style, "/docs/"), redirect("/docs"));
+        assert_eq!(locate(&style, "/"), Located::Found("home"), "The root keeps its slash");
+        assert_eq!(locate(&style, "/missing/"), Located::Missing);
+    }
+
+    #[test]
+    fn test_clean_urls() {
+        let style = url_style(TrailingSlash::Add, true);
+        assert_eq!(locate(&style, "/about"), Located::Found("about"));
+        assert_eq!(locate(&style, "/about.html"), redirect("/about"));
+        assert_eq!(locate(&style, "/docs/index.html"), redirect("/docs/"));
+        assert_eq!(locate(&style, "/index.html"), redirect("/"));
+        assert_eq!(locate(&style, "/notes"), Located::Found("extensionless"));
+        assert_eq!(locate(&style, "/notes.html"), Located::Found("notes page"), "No redirect onto another file");
+
+        let without_slash = url_style(TrailingSlash::Remove, true);
+        assert_eq!(locate(&without_slash, "/docs/index.html"), redirect("/docs"));
+        assert_eq!(locate(&without_slash, "/index.html"), redirect("/"));
+    }
+
+    #[test]
+    fn test_clean_urls_respect_index_preference() {
+        let site = HashMap::from([("/docs/index.htm", "htm"), ("/docs/index.html", "html"), ("/docs/", "htm")]);
+        let style = UrlStyle {
+            trailing_slash: TrailingSlash::Add,
+            clean_urls: true,
+            index_files: vec!["index.htm".to_string(), "index.html".to_string()],
+        };
+        let lookup = |path: &str| site.get(path).copied();
+        assert_eq!(style.locate("/docs/index.html", lookup), Located::Found("html"), "Not the directory's index");
+        assert_eq!(style.locate("/docs", lookup), Located::Redirect("/docs/".to_string()));
+    }
+
+    #[test]
+    fn test_trailing_slash_modes_parse() {
+        assert_eq!(TrailingSlash::parse("off"), Some(TrailingSlash::Off));
+        assert_eq!(TrailingSlash::parse("ADD"), Some(TrailingSlash::Add));
+        assert_eq!(TrailingSlash::parse("remove"), Some(TrailingSlash::Remove));
+        assert_eq!(TrailingSlash::parse("yes"), None);
+    }
+}